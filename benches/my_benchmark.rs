@@ -1,15 +1,71 @@
 use criterion::{criterion_group, criterion_main, Criterion};
+use std::f64::consts::PI;
 
+use rust_ray_tracer_challenge::camera;
+use rust_ray_tracer_challenge::groups;
+use rust_ray_tracer_challenge::patterns;
+use rust_ray_tracer_challenge::rays;
+use rust_ray_tracer_challenge::spheres;
+use rust_ray_tracer_challenge::transformations;
 use rust_ray_tracer_challenge::tuples;
+use rust_ray_tracer_challenge::worlds;
 
 fn bench_tuple(c: &mut Criterion) {
     let mut group = c.benchmark_group("Tuples");
     let p = tuples::point(1.0, 2.0, 3.0);
-    group.bench_function("tuple_multiply", |b| b.iter(|| p.multiply(2.0)));
+    group.bench_function("tuple_multiply", |b| b.iter(|| p.multiply(&2.0)));
     group.bench_function("tuple_multiply_borrow", |b| {
         b.iter(|| tuples::tuple_multiply_borrow(&p, &2.0))
     });
+    group.bench_function("tuple_add", |b| b.iter(|| p.add(&tuples::VECTOR_NULL)));
 }
 
-criterion_group!(benches, bench_tuple);
+fn bench_transforms(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Transforms");
+    let mut s = spheres::sphere();
+    s.set_transform(transformations::matrix4_transform_chain(&vec![
+        transformations::matrix4_scaling(2.0, 1.0, 2.0),
+        transformations::matrix4_rotation_y_rad(PI / 5.0),
+        transformations::matrix4_translation(0.5, 1.0, 0.0),
+    ]));
+    let r = rays::ray(tuples::point(0.0, 1.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
+    let p = tuples::point(0.0, 1.0, -2.0);
+    let mut pat = patterns::PATTERN_DEFAULT;
+    pat.set_transform(transformations::matrix4_scaling(0.5, 0.5, 0.5));
+    let mut cam = camera::camera(20, 10, PI / 3.0);
+    cam.set_transform(transformations::view_transform(
+        &tuples::point(0.0, 0.0, -5.0),
+        &tuples::POINT_ORIGIN,
+        &tuples::vector(0.0, 1.0, 0.0),
+    ));
+    group.bench_function("shape_intersect", |b| b.iter(|| s.intersect(&r)));
+    group.bench_function("shape_normal_at", |b| b.iter(|| s.normal_at(&p)));
+    group.bench_function("pattern_at_shape", |b| {
+        b.iter(|| pat.pattern_at_shape(&s, &p))
+    });
+    group.bench_function("camera_ray_for_pixel", |b| {
+        b.iter(|| cam.ray_for_pixel(3, 7))
+    });
+    //a sphere hit inside two nested groups, carrying their transforms
+    let mut inner = groups::group_of(vec![s.clone()]);
+    inner.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
+    let mut outer = groups::group_of(vec![inner]);
+    outer.set_transform(transformations::matrix4_rotation_y_rad(PI / 4.0));
+    let nested = outer.intersect(&r).unwrap()[0].object.clone();
+    group.bench_function("nested_normal_at", |b| b.iter(|| nested.normal_at(&p)));
+}
+
+fn bench_world(c: &mut Criterion) {
+    let mut group = c.benchmark_group("World");
+    let w = worlds::world_default();
+    let mut cam = camera::camera(20, 10, PI / 3.0);
+    cam.set_transform(transformations::view_transform(
+        &tuples::point(0.0, 0.0, -5.0),
+        &tuples::POINT_ORIGIN,
+        &tuples::vector(0.0, 1.0, 0.0),
+    ));
+    group.bench_function("render_world_default", |b| b.iter(|| cam.render(&w)));
+}
+
+criterion_group!(benches, bench_tuple, bench_transforms, bench_world);
 criterion_main!(benches);
@@ -0,0 +1,252 @@
+use crate::canvas;
+use crate::patterns;
+use crate::tuples;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackgroundType {
+    Flat,
+    //from a at the bottom to b at the top
+    Gradient,
+    //one panoramic image wrapped around the whole sky
+    Equirectangular,
+    //six images on the inside of a cube
+    CubeMap,
+}
+
+//what a ray sees when it misses everything
+#[derive(Debug, Clone)]
+pub struct Background {
+    pub background_type: BackgroundType,
+    pub a: tuples::Color,
+    pub b: tuples::Color,
+    //the image pattern for equirectangular and cube map backgrounds
+    pub pattern: Option<patterns::Pattern>,
+}
+
+impl Background {
+    pub fn color_at(&self, direction: &tuples::Vector) -> tuples::Color {
+        let d = direction.normalize();
+        match (&self.background_type, &self.pattern) {
+            (BackgroundType::Gradient, _) => {
+                let fraction = (d.y + 1.0) / 2.0;
+                self.a
+                    .add(&self.b.subtract(&self.a).scalar_multiply(&fraction))
+            }
+            (BackgroundType::Equirectangular, Some(p)) => {
+                let (u, v) = patterns::spherical_map(&tuples::point(d.x, d.y, d.z));
+                //seen from inside the sphere, so u runs the other way
+                p.uv_pattern_at(1.0 - u, v)
+            }
+            (BackgroundType::CubeMap, Some(p)) => {
+                //cube_uv already lays each face out as seen from inside the cube,
+                //so unlike the sphere above no face needs flipping.
+                //push the direction out onto the -1..1 cube
+                let scale = d.x.abs().max(d.y.abs()).max(d.z.abs());
+                p.pattern_at(&tuples::point(d.x / scale, d.y / scale, d.z / scale))
+            }
+            _ => self.a,
+        }
+    }
+}
+
+pub const BACKGROUND_DEFAULT: Background = Background {
+    background_type: BackgroundType::Flat,
+    a: tuples::COLOR_BLACK,
+    b: tuples::COLOR_BLACK,
+    pattern: None,
+};
+
+pub fn flat_background(color: tuples::Color) -> Background {
+    Background {
+        a: color,
+        ..BACKGROUND_DEFAULT
+    }
+}
+
+pub fn gradient_background(bottom: tuples::Color, top: tuples::Color) -> Background {
+    Background {
+        background_type: BackgroundType::Gradient,
+        a: bottom,
+        b: top,
+        ..BACKGROUND_DEFAULT
+    }
+}
+
+pub fn equirectangular_background(image: canvas::PixelCanvas) -> Background {
+    Background {
+        background_type: BackgroundType::Equirectangular,
+        pattern: Some(sky_image(image)),
+        ..BACKGROUND_DEFAULT
+    }
+}
+
+pub fn cube_map_background(
+    left: canvas::PixelCanvas,
+    front: canvas::PixelCanvas,
+    right: canvas::PixelCanvas,
+    back: canvas::PixelCanvas,
+    up: canvas::PixelCanvas,
+    down: canvas::PixelCanvas,
+) -> Background {
+    Background {
+        background_type: BackgroundType::CubeMap,
+        pattern: Some(patterns::cube_map_pattern(
+            sky_image(left),
+            sky_image(front),
+            sky_image(right),
+            sky_image(back),
+            sky_image(up),
+            sky_image(down),
+        )),
+        ..BACKGROUND_DEFAULT
+    }
+}
+
+//clamped so the edges of the images don't bleed into each other
+fn sky_image(image: canvas::PixelCanvas) -> patterns::Pattern {
+    let mut p = patterns::image_pattern(image);
+    p.wrap = patterns::TextureWrap::Clamp;
+    p
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera;
+    use crate::transformations;
+
+    fn single_color(col: tuples::Color) -> canvas::PixelCanvas {
+        canvas::pixel_canvas(2, 2, col)
+    }
+
+    #[test]
+    fn test_the_default_background_is_black() {
+        //The default background is black whichever way the ray points
+        let col = BACKGROUND_DEFAULT.color_at(&tuples::vector(0.3, -2.0, 1.0));
+        assert!(col.is_equal_to(&tuples::COLOR_BLACK));
+        let col = flat_background(tuples::COLOR_RED).color_at(&tuples::vector(0.0, 1.0, 0.0));
+        assert!(col.is_equal_to(&tuples::COLOR_RED));
+    }
+
+    #[test]
+    fn test_a_gradient_background_blends_from_bottom_to_top() {
+        //A gradient background blends by the height of the direction
+        let b = gradient_background(tuples::COLOR_BLACK, tuples::COLOR_WHITE);
+        let tests = [
+            (tuples::vector(0.0, -1.0, 0.0), 0.0),
+            (tuples::vector(0.0, 0.0, 5.0), 0.5),
+            (tuples::vector(0.0, 3.0, 0.0), 1.0),
+            (tuples::vector(1.0, 1.0, 0.0), 0.5 + 2.0_f64.sqrt() / 4.0),
+        ];
+        for (d, expected) in tests.iter() {
+            assert!(b
+                .color_at(d)
+                .is_equal_to(&tuples::color(*expected, *expected, *expected)));
+        }
+    }
+
+    #[test]
+    fn test_an_equirectangular_background_wraps_an_image_around_the_sky() {
+        //An equirectangular image has +z in its middle and +x to the right of that
+        let mut image = canvas::pixel_canvas(5, 3, tuples::COLOR_BLACK);
+        image.pixel_set(&2, &1, tuples::COLOR_RED);
+        image.pixel_set(&3, &1, tuples::COLOR_WHITE);
+        image.pixel_set(&2, &0, tuples::color(0.0, 0.0, 1.0));
+        let mut b = equirectangular_background(image);
+        b.pattern.as_mut().unwrap().filter = patterns::TextureFilter::Nearest;
+        let tests = [
+            (tuples::vector(0.0, 0.0, 1.0), tuples::COLOR_RED),
+            (tuples::vector(1.0, 0.0, 0.0), tuples::COLOR_WHITE),
+            (tuples::vector(0.0, 1.0, 0.0), tuples::color(0.0, 0.0, 1.0)),
+            (tuples::vector(-1.0, 0.0, 0.0), tuples::COLOR_BLACK),
+        ];
+        for (d, expected) in tests.iter() {
+            assert!(b.color_at(d).is_equal_to(expected));
+        }
+    }
+
+    #[test]
+    fn test_a_cube_map_background_picks_the_face_the_ray_points_at() {
+        //A cube map background uses the image on the face the direction points at
+        let faces = [
+            tuples::color(1.0, 0.0, 0.0),
+            tuples::color(0.0, 1.0, 0.0),
+            tuples::color(0.0, 0.0, 1.0),
+            tuples::color(1.0, 1.0, 0.0),
+            tuples::color(0.0, 1.0, 1.0),
+            tuples::color(1.0, 0.0, 1.0),
+        ];
+        let b = cube_map_background(
+            single_color(faces[0]),
+            single_color(faces[1]),
+            single_color(faces[2]),
+            single_color(faces[3]),
+            single_color(faces[4]),
+            single_color(faces[5]),
+        );
+        let tests = [
+            (tuples::vector(-3.0, 1.0, 0.5), faces[0]),
+            (tuples::vector(0.2, 0.1, 0.5), faces[1]),
+            (tuples::vector(2.0, -1.0, 1.9), faces[2]),
+            (tuples::vector(0.0, 0.0, -1.0), faces[3]),
+            (tuples::vector(0.5, 4.0, -0.5), faces[4]),
+            (tuples::vector(0.0, -0.1, 0.0), faces[5]),
+        ];
+        for (d, expected) in tests.iter() {
+            assert!(b.color_at(d).is_equal_to(expected));
+        }
+    }
+
+    fn quartered() -> canvas::PixelCanvas {
+        //red top left, green top right, blue bottom left, white bottom right
+        let mut image = canvas::pixel_canvas(2, 2, tuples::COLOR_WHITE);
+        image.pixel_set(&0, &0, tuples::COLOR_RED);
+        image.pixel_set(&1, &0, tuples::color(0.0, 1.0, 0.0));
+        image.pixel_set(&0, &1, tuples::color(0.0, 0.0, 1.0));
+        image
+    }
+
+    #[test]
+    fn test_cube_map_faces_are_not_mirrored_from_inside() {
+        //A camera at the centre facing each face sees its image the right way round,
+        //turning from the front as in the usual cross layout of a sky box
+        let mut b = cube_map_background(
+            quartered(),
+            quartered(),
+            quartered(),
+            quartered(),
+            quartered(),
+            quartered(),
+        );
+        for face in b.pattern.as_mut().unwrap().children.iter_mut().flatten() {
+            face.filter = patterns::TextureFilter::Nearest;
+        }
+        let y = tuples::vector(0.0, 1.0, 0.0);
+        let views = [
+            (tuples::vector(-1.0, 0.0, 0.0), y),
+            (tuples::vector(0.0, 0.0, 1.0), y),
+            (tuples::vector(1.0, 0.0, 0.0), y),
+            (tuples::vector(0.0, 0.0, -1.0), y),
+            //tilting back from the front to look up, and forward to look down
+            (y, tuples::vector(0.0, 0.0, -1.0)),
+            (
+                tuples::vector(0.0, -1.0, 0.0),
+                tuples::vector(0.0, 0.0, 1.0),
+            ),
+        ];
+        for (to, up) in views.iter() {
+            //a quarter turn of view fills the picture with exactly one face
+            let mut c = camera::camera(4, 4, std::f64::consts::PI / 2.0);
+            c.set_transform(transformations::view_transform(
+                &tuples::POINT_ORIGIN,
+                &tuples::point(to.x, to.y, to.z),
+                up,
+            ));
+            let at = |px: u32, py: u32| b.color_at(&c.ray_for_pixel(px, py).direction);
+            assert!(at(0, 0).is_equal_to(&tuples::COLOR_RED));
+            assert!(at(3, 0).is_equal_to(&tuples::color(0.0, 1.0, 0.0)));
+            assert!(at(0, 3).is_equal_to(&tuples::color(0.0, 0.0, 1.0)));
+            assert!(at(3, 3).is_equal_to(&tuples::COLOR_WHITE));
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use crate::matrices;
+use crate::rays;
+use crate::tuples;
+
+#[derive(Debug, Copy, Clone)]
+pub struct BoundingBox {
+    pub min: tuples::Point,
+    pub max: tuples::Point,
+}
+
+pub fn bounding_box(min: tuples::Point, max: tuples::Point) -> BoundingBox {
+    BoundingBox { min, max }
+}
+
+pub fn bounding_box_empty() -> BoundingBox {
+    //inside out, so that adding anything replaces both corners
+    bounding_box(
+        tuples::point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        tuples::point(-f64::INFINITY, -f64::INFINITY, -f64::INFINITY),
+    )
+}
+
+pub fn bounding_box_infinite() -> BoundingBox {
+    bounding_box(
+        tuples::point(-f64::INFINITY, -f64::INFINITY, -f64::INFINITY),
+        tuples::point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+    )
+}
+
+impl BoundingBox {
+    pub fn add_point(&mut self, p: &tuples::Point) {
+        self.min = tuples::point(
+            self.min.x.min(p.x),
+            self.min.y.min(p.y),
+            self.min.z.min(p.z),
+        );
+        self.max = tuples::point(
+            self.max.x.max(p.x),
+            self.max.y.max(p.y),
+            self.max.z.max(p.z),
+        );
+    }
+
+    pub fn add_box(&mut self, b: &BoundingBox) {
+        self.add_point(&b.min);
+        self.add_point(&b.max);
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y, self.max.z,
+        ]
+        .iter()
+        .all(|n| n.is_finite())
+    }
+
+    pub fn contains_point(&self, p: &tuples::Point) -> bool {
+        self.min.x <= p.x
+            && p.x <= self.max.x
+            && self.min.y <= p.y
+            && p.y <= self.max.y
+            && self.min.z <= p.z
+            && p.z <= self.max.z
+    }
+
+    pub fn contains_box(&self, b: &BoundingBox) -> bool {
+        self.contains_point(&b.min) && self.contains_point(&b.max)
+    }
+
+    pub fn transform(&self, m: &matrices::Matrix4) -> BoundingBox {
+        //infinite extents can't be rotated, so stay unbounded everywhere
+        if !self.is_finite() {
+            return bounding_box_infinite();
+        }
+        let (a, b) = (self.min, self.max);
+        let corners = [
+            tuples::point(a.x, a.y, a.z),
+            tuples::point(a.x, a.y, b.z),
+            tuples::point(a.x, b.y, a.z),
+            tuples::point(a.x, b.y, b.z),
+            tuples::point(b.x, a.y, a.z),
+            tuples::point(b.x, a.y, b.z),
+            tuples::point(b.x, b.y, a.z),
+            tuples::point(b.x, b.y, b.z),
+        ];
+        let mut result = bounding_box_empty();
+        for c in corners.iter() {
+            result.add_point(&m.tuple_multiply(c));
+        }
+        result
+    }
+
+    pub fn intersects(&self, r: &rays::Ray) -> bool {
+        let (xtmin, xtmax) = check_axis(r.origin.x, r.direction.x, self.min.x, self.max.x);
+        let (ytmin, ytmax) = check_axis(r.origin.y, r.direction.y, self.min.y, self.max.y);
+        let (ztmin, ztmax) = check_axis(r.origin.z, r.direction.z, self.min.z, self.max.z);
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        //a box wholly behind the ray is missed too
+        tmin <= tmax && tmax >= 0.0
+    }
+
+    pub fn split(&self) -> (BoundingBox, BoundingBox) {
+        //halves the box across its largest dimension
+        let dx = self.max.x - self.min.x;
+        let dy = self.max.y - self.min.y;
+        let dz = self.max.z - self.min.z;
+        let greatest = dx.max(dy).max(dz);
+        let (mut x0, mut y0, mut z0) = (self.min.x, self.min.y, self.min.z);
+        let (mut x1, mut y1, mut z1) = (self.max.x, self.max.y, self.max.z);
+        if greatest == dx {
+            x0 += dx / 2.0;
+            x1 = x0;
+        } else if greatest == dy {
+            y0 += dy / 2.0;
+            y1 = y0;
+        } else {
+            z0 += dz / 2.0;
+            z1 = z0;
+        }
+        (
+            bounding_box(self.min, tuples::point(x1, y1, z1)),
+            bounding_box(tuples::point(x0, y0, z0), self.max),
+        )
+    }
+}
+
+fn check_axis(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
+    if direction.abs() < tuples::EPSILON {
+        //parallel to the slab, so either always or never inside it
+        if origin < min || origin > max {
+            return (f64::INFINITY, -f64::INFINITY);
+        }
+        return (-f64::INFINITY, f64::INFINITY);
+    }
+    let tmin = (min - origin) / direction;
+    let tmax = (max - origin) / direction;
+    if tmin > tmax {
+        (tmax, tmin)
+    } else {
+        (tmin, tmax)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_creating_an_empty_bounding_box() {
+        //Creating an empty bounding box
+        let b = bounding_box_empty();
+        assert_eq!(b.min.x, f64::INFINITY);
+        assert_eq!(b.max.x, -f64::INFINITY);
+    }
+
+    #[test]
+    fn test_adding_points_to_an_empty_bounding_box() {
+        //Adding points to an empty bounding box
+        let mut b = bounding_box_empty();
+        b.add_point(&tuples::point(-5.0, 2.0, 0.0));
+        b.add_point(&tuples::point(7.0, 0.0, -3.0));
+        assert!(b.min.is_equal_to(&tuples::point(-5.0, 0.0, -3.0)));
+        assert!(b.max.is_equal_to(&tuples::point(7.0, 2.0, 0.0)));
+    }
+
+    #[test]
+    fn test_adding_one_bounding_box_to_another() {
+        //Adding one bounding box to another
+        let mut b1 = bounding_box(tuples::point(-5.0, -2.0, 0.0), tuples::point(7.0, 4.0, 4.0));
+        let b2 = bounding_box(
+            tuples::point(8.0, -7.0, -2.0),
+            tuples::point(14.0, 2.0, 8.0),
+        );
+        b1.add_box(&b2);
+        assert!(b1.min.is_equal_to(&tuples::point(-5.0, -7.0, -2.0)));
+        assert!(b1.max.is_equal_to(&tuples::point(14.0, 4.0, 8.0)));
+    }
+
+    #[test]
+    fn test_checking_to_see_if_a_box_contains_a_given_point() {
+        //Checking to see if a box contains a given point
+        let b = bounding_box(tuples::point(5.0, -2.0, 0.0), tuples::point(11.0, 4.0, 7.0));
+        let tests: [([f64; 3], bool); 9] = [
+            ([5.0, -2.0, 0.0], true),
+            ([11.0, 4.0, 7.0], true),
+            ([8.0, 1.0, 3.0], true),
+            ([3.0, 0.0, 3.0], false),
+            ([8.0, -4.0, 3.0], false),
+            ([8.0, 1.0, -1.0], false),
+            ([13.0, 1.0, 3.0], false),
+            ([8.0, 5.0, 3.0], false),
+            ([8.0, 1.0, 8.0], false),
+        ];
+        for (p, result) in tests.iter() {
+            let point = tuples::point(p[0], p[1], p[2]);
+            assert_eq!(b.contains_point(&point), *result);
+        }
+    }
+
+    #[test]
+    fn test_checking_to_see_if_a_box_contains_a_given_box() {
+        //Checking to see if a box contains a given box
+        let b = bounding_box(tuples::point(5.0, -2.0, 0.0), tuples::point(11.0, 4.0, 7.0));
+        let tests: [([f64; 3], [f64; 3], bool); 4] = [
+            ([5.0, -2.0, 0.0], [11.0, 4.0, 7.0], true),
+            ([6.0, -1.0, 1.0], [10.0, 3.0, 6.0], true),
+            ([4.0, -3.0, -1.0], [10.0, 3.0, 6.0], false),
+            ([6.0, -1.0, 1.0], [12.0, 5.0, 8.0], false),
+        ];
+        for (min, max, result) in tests.iter() {
+            let b2 = bounding_box(
+                tuples::point(min[0], min[1], min[2]),
+                tuples::point(max[0], max[1], max[2]),
+            );
+            assert_eq!(b.contains_box(&b2), *result);
+        }
+    }
+
+    #[test]
+    fn test_transforming_a_bounding_box() {
+        //Transforming a bounding box
+        let b = bounding_box(
+            tuples::point(-1.0, -1.0, -1.0),
+            tuples::point(1.0, 1.0, 1.0),
+        );
+        let m = transformations::matrix4_transform_chain(&vec![
+            transformations::matrix4_rotation_y_rad(PI / 4.0),
+            transformations::matrix4_rotation_x_rad(PI / 4.0),
+        ]);
+        let b2 = b.transform(&m);
+        assert!(b2.min.is_equal_to(&tuples::point(
+            -std::f64::consts::SQRT_2,
+            -1.70711,
+            -1.70711
+        )));
+        assert!(b2
+            .max
+            .is_equal_to(&tuples::point(std::f64::consts::SQRT_2, 1.70711, 1.70711)));
+    }
+
+    #[test]
+    fn test_intersecting_a_ray_with_a_non_cubic_bounding_box() {
+        //Intersecting a ray with a non-cubic bounding box
+        let b = bounding_box(tuples::point(5.0, -2.0, 0.0), tuples::point(11.0, 4.0, 7.0));
+        let tests: [([f64; 3], [f64; 3], bool); 13] = [
+            ([15.0, 1.0, 2.0], [-1.0, 0.0, 0.0], true),
+            ([-5.0, -1.0, 4.0], [1.0, 0.0, 0.0], true),
+            ([7.0, 6.0, 5.0], [0.0, -1.0, 0.0], true),
+            ([9.0, -5.0, 6.0], [0.0, 1.0, 0.0], true),
+            ([8.0, 2.0, 12.0], [0.0, 0.0, -1.0], true),
+            ([6.0, 0.0, -5.0], [0.0, 0.0, 1.0], true),
+            ([8.0, 1.0, 3.5], [0.0, 0.0, 1.0], true),
+            ([9.0, -1.0, -8.0], [2.0, 4.0, 6.0], false),
+            ([8.0, 3.0, -4.0], [6.0, 2.0, 4.0], false),
+            ([9.0, -1.0, -2.0], [4.0, 6.0, 2.0], false),
+            ([4.0, 0.0, 9.0], [0.0, 0.0, -1.0], false),
+            ([8.0, 6.0, -1.0], [0.0, -1.0, 0.0], false),
+            ([12.0, 5.0, 4.0], [-1.0, 0.0, 0.0], false),
+        ];
+        for (o, d, result) in tests.iter() {
+            let r = rays::ray(
+                tuples::point(o[0], o[1], o[2]),
+                tuples::vector(d[0], d[1], d[2]).normalize(),
+            );
+            assert_eq!(b.intersects(&r), *result);
+        }
+    }
+
+    #[test]
+    fn test_a_ray_misses_a_box_behind_it() {
+        //A ray pointing away from a box misses it, one starting inside still hits
+        let b = bounding_box(
+            tuples::point(-1.0, -1.0, -1.0),
+            tuples::point(1.0, 1.0, 1.0),
+        );
+        let away = rays::ray(tuples::point(0.0, 0.0, 5.0), tuples::vector(0.0, 0.0, 1.0));
+        assert!(!b.intersects(&away));
+        let inside = rays::ray(tuples::point(0.0, 0.0, 0.5), tuples::vector(0.0, 0.0, 1.0));
+        assert!(b.intersects(&inside));
+    }
+
+    #[test]
+    fn test_splitting_a_bounding_box() {
+        //Splitting a perfect cube, and boxes wider in x, y and z
+        //box min, box max, left max, right min
+        let tests: [[[f64; 3]; 4]; 4] = [
+            [
+                [-1.0, -4.0, -5.0],
+                [9.0, 6.0, 5.0],
+                [4.0, 6.0, 5.0],
+                [4.0, -4.0, -5.0],
+            ],
+            [
+                [-1.0, -2.0, -3.0],
+                [9.0, 5.5, 3.0],
+                [4.0, 5.5, 3.0],
+                [4.0, -2.0, -3.0],
+            ],
+            [
+                [-1.0, -2.0, -3.0],
+                [5.0, 8.0, 3.0],
+                [5.0, 3.0, 3.0],
+                [-1.0, 3.0, -3.0],
+            ],
+            [
+                [-1.0, -2.0, -3.0],
+                [5.0, 3.0, 7.0],
+                [5.0, 3.0, 2.0],
+                [-1.0, -2.0, 2.0],
+            ],
+        ];
+        for [min, max, left_max, right_min] in tests.iter() {
+            let b = bounding_box(
+                tuples::point(min[0], min[1], min[2]),
+                tuples::point(max[0], max[1], max[2]),
+            );
+            let (left, right) = b.split();
+            assert!(left.min.is_equal_to(&b.min));
+            let lm = tuples::point(left_max[0], left_max[1], left_max[2]);
+            assert!(left.max.is_equal_to(&lm));
+            let rm = tuples::point(right_min[0], right_min[1], right_min[2]);
+            assert!(right.min.is_equal_to(&rm));
+            assert!(right.max.is_equal_to(&b.max));
+        }
+    }
+
+    #[test]
+    fn test_infinite_boxes_stay_infinite_when_transformed() {
+        //A plane's infinite bounds can't be rotated, so become unbounded in every direction
+        let b = bounding_box(
+            tuples::point(-f64::INFINITY, 0.0, -f64::INFINITY),
+            tuples::point(f64::INFINITY, 0.0, f64::INFINITY),
+        );
+        let b2 = b.transform(&transformations::matrix4_rotation_x_rad(PI / 4.0));
+        assert_eq!(b2.min.y, -f64::INFINITY);
+        assert_eq!(b2.max.y, f64::INFINITY);
+        let r = rays::ray(tuples::point(0.0, 5.0, 0.0), tuples::vector(0.0, 1.0, 0.0));
+        assert!(b2.intersects(&r));
+    }
+}
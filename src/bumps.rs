@@ -0,0 +1,290 @@
+use crate::canvas;
+use crate::matrices;
+use crate::noise;
+use crate::patterns;
+use crate::shapes;
+use crate::triangles;
+use crate::tuples;
+
+//how far apart, in world space, the heights are sampled to find the slope
+const STEP: f64 = 0.0001;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BumpType {
+    //fractal noise, for rough or pitted surfaces
+    Noise,
+    //sine ripples spreading out from the y axis, like water
+    Ripples,
+    //a tangent-space normal map image, red along u, green along v and blue straight out
+    NormalMap,
+}
+
+//tips a material's normals to fake detail the geometry doesn't have
+#[derive(Debug, Clone)]
+pub struct Bump {
+    pub bump_type: BumpType,
+    //how far the normal is tipped, roughly the steepest slope of the bumps
+    pub amount: f64,
+    //set with set_transform, which keeps the inverse in step
+    transform: matrices::Matrix4,
+    transform_inverse: matrices::Matrix4,
+    pub octaves: u32,
+    pub persistence: f64,
+    //the normal map image, with the mapping, filter and wrap used to sample it
+    pub normal_map: Option<patterns::Pattern>,
+}
+
+impl Bump {
+    pub fn set_transform(&mut self, transform: matrices::Matrix4) {
+        self.transform = transform;
+        self.transform_inverse = transform.inverse();
+    }
+
+    pub fn transform(&self) -> &matrices::Matrix4 {
+        &self.transform
+    }
+
+    pub fn perturb_normal(
+        &self,
+        s: &shapes::Shape,
+        world_point: &tuples::Point,
+        normal: &tuples::Vector,
+    ) -> tuples::Vector {
+        match (&self.bump_type, &self.normal_map) {
+            (BumpType::NormalMap, Some(map)) => self.normal_map_normal(map, s, world_point, normal),
+            (BumpType::NormalMap, None) => *normal,
+            _ => {
+                let slope = self.gradient(world_point, |p| self.height_at(s, p));
+                normal
+                    .subtract(&along_surface(&slope, normal).multiply(&self.amount))
+                    .normalize()
+            }
+        }
+    }
+
+    fn bump_point(&self, s: &shapes::Shape, world_point: &tuples::Point) -> tuples::Point {
+        self.transform_inverse
+            .tuple_multiply(&s.world_to_object(world_point))
+    }
+
+    fn height_at(&self, s: &shapes::Shape, world_point: &tuples::Point) -> f64 {
+        let p = self.bump_point(s, world_point);
+        match self.bump_type {
+            BumpType::Noise => noise::fractal_noise(&p, self.octaves, self.persistence),
+            BumpType::Ripples => {
+                let r = (p.x * p.x + p.z * p.z).sqrt();
+                (r * 2.0 * std::f64::consts::PI).sin() / (2.0 * std::f64::consts::PI)
+            }
+            BumpType::NormalMap => 0.0,
+        }
+    }
+
+    //the slope of f at p in world space, from a central difference along each axis
+    fn gradient<F>(&self, p: &tuples::Point, f: F) -> tuples::Vector
+    where
+        F: Fn(&tuples::Point) -> f64,
+    {
+        let along = |dx: f64, dy: f64, dz: f64| {
+            let ahead = f(&tuples::point(p.x + dx, p.y + dy, p.z + dz));
+            let behind = f(&tuples::point(p.x - dx, p.y - dy, p.z - dz));
+            (ahead - behind) / (2.0 * STEP)
+        };
+        tuples::vector(
+            along(STEP, 0.0, 0.0),
+            along(0.0, STEP, 0.0),
+            along(0.0, 0.0, STEP),
+        )
+    }
+
+    fn normal_map_normal(
+        &self,
+        map: &patterns::Pattern,
+        s: &shapes::Shape,
+        world_point: &tuples::Point,
+        normal: &tuples::Vector,
+    ) -> tuples::Vector {
+        let uv = |p: &tuples::Point| match map.mapping {
+            patterns::UvMapping::Mesh => triangles::texture_uv_at(s, &s.world_to_object(p))
+                .unwrap_or_else(|| map.uv_at(&self.bump_point(s, p))),
+            _ => map.uv_at(&self.bump_point(s, p)),
+        };
+        let (u, v) = uv(world_point);
+        //the directions u and v grow in along the surface make the tangent frame,
+        //ignoring the jump where a mapping wraps round from 1 to 0
+        let wrapped = |d: f64| d - d.round();
+        let tangent = along_surface(
+            &self.gradient(world_point, |p| wrapped(uv(p).0 - u)),
+            normal,
+        );
+        let bitangent = along_surface(
+            &self.gradient(world_point, |p| wrapped(uv(p).1 - v)),
+            normal,
+        );
+        if tangent.magnitude() == 0.0 || bitangent.magnitude() == 0.0 {
+            return *normal;
+        }
+        let c = map.uv_pattern_at(u, v);
+        let (x, y, z) = (c.red * 2.0 - 1.0, c.green * 2.0 - 1.0, c.blue * 2.0 - 1.0);
+        tangent
+            .normalize()
+            .multiply(&(x * self.amount))
+            .add(&bitangent.normalize().multiply(&(y * self.amount)))
+            .add(&normal.multiply(&z))
+            .normalize()
+    }
+}
+
+//the part of v that lies flat along the surface with normal n
+fn along_surface(v: &tuples::Vector, n: &tuples::Vector) -> tuples::Vector {
+    v.subtract(&n.multiply(&v.dot_product(n)))
+}
+
+pub const BUMP_DEFAULT: Bump = Bump {
+    bump_type: BumpType::Noise,
+    amount: 0.2,
+    transform: matrices::IDENTITY_MATRIX,
+    transform_inverse: matrices::IDENTITY_MATRIX,
+    octaves: 4,
+    persistence: 0.5,
+    normal_map: None,
+};
+
+pub fn noise_bump(amount: f64) -> Bump {
+    Bump {
+        amount,
+        ..BUMP_DEFAULT
+    }
+}
+
+pub fn ripple_bump(amount: f64) -> Bump {
+    Bump {
+        bump_type: BumpType::Ripples,
+        amount,
+        ..BUMP_DEFAULT
+    }
+}
+
+pub fn normal_map_bump(image: canvas::PixelCanvas, mapping: patterns::UvMapping) -> Bump {
+    let mut map = patterns::image_pattern(image);
+    map.mapping = mapping;
+    Bump {
+        bump_type: BumpType::NormalMap,
+        amount: 1.0,
+        normal_map: Some(map),
+        ..BUMP_DEFAULT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planes;
+    use crate::spheres;
+    use crate::transformations;
+
+    fn flat_normal_map(col: tuples::Color) -> canvas::PixelCanvas {
+        canvas::pixel_canvas(2, 2, col)
+    }
+
+    #[test]
+    fn test_a_bump_with_no_amount_leaves_the_normal_alone() {
+        //A procedural bump with no amount gives back the geometric normal
+        let s = spheres::sphere();
+        let p = tuples::point(0.0, 0.0, -1.0);
+        let n = s.normal_at(&p);
+        for b in [noise_bump(0.0), ripple_bump(0.0)].iter() {
+            assert!(b.perturb_normal(&s, &p, &n).is_equal_to(&n));
+        }
+    }
+
+    #[test]
+    fn test_ripples_tip_the_normal_down_their_slope() {
+        //Half way out, a ripple slopes down away from the y axis
+        let s = planes::plane();
+        let n = tuples::vector(0.0, 1.0, 0.0);
+        let b = ripple_bump(1.0);
+        let tipped = b.perturb_normal(&s, &tuples::point(0.5, 0.0, 0.0), &n);
+        let half = 2.0_f64.sqrt() / 2.0;
+        assert!(tipped.is_equal_to(&tuples::vector(half, half, 0.0)));
+        //at the crest there is no slope
+        let crest = b.perturb_normal(&s, &tuples::point(0.0, 0.0, 0.25), &n);
+        assert!(crest.is_equal_to(&n));
+    }
+
+    #[test]
+    fn test_bumps_follow_the_object_and_bump_transforms() {
+        //The ripples move and scale with the shape and the bump's own transform
+        let mut s = planes::plane();
+        s.set_transform(transformations::matrix4_translation(2.0, 0.0, 0.0));
+        let mut b = ripple_bump(1.0);
+        b.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
+        let n = tuples::vector(0.0, 1.0, 0.0);
+        //1 unit from the plane's centre is half way out in bump space,
+        //where the slope is halved by the scaling
+        let tipped = b.perturb_normal(&s, &tuples::point(3.0, 0.0, 0.0), &n);
+        assert!(tipped.is_equal_to(&tuples::vector(0.5, 1.0, 0.0).normalize()));
+    }
+
+    #[test]
+    fn test_noise_bumps_tip_normals_a_little() {
+        //Noise tips normals by different amounts but keeps them unit length and outward
+        let s = spheres::sphere();
+        let b = noise_bump(0.3);
+        let mut differs = false;
+        for i in 0..50 {
+            let a = i as f64 * 0.3;
+            let p = tuples::point(a.cos() * 0.6, 0.8, a.sin() * 0.6);
+            let n = s.normal_at(&p);
+            let tipped = b.perturb_normal(&s, &p, &n);
+            assert!((tipped.magnitude() - 1.0).abs() < tuples::EPSILON);
+            assert!(tipped.dot_product(&n) > 0.5);
+            if !tipped.is_equal_to(&n) {
+                differs = true;
+            }
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn test_a_normal_map_tips_the_normal_in_tangent_space() {
+        //A flat blue normal map changes nothing, red tips the normal towards +u
+        let s = planes::plane();
+        let p = tuples::point(0.3, 0.0, 0.6);
+        let n = tuples::vector(0.0, 1.0, 0.0);
+        let flat = normal_map_bump(
+            flat_normal_map(tuples::color(0.5, 0.5, 1.0)),
+            patterns::UvMapping::Planar,
+        );
+        assert!(flat.perturb_normal(&s, &p, &n).is_equal_to(&n));
+        let towards_u = normal_map_bump(
+            flat_normal_map(tuples::color(1.0, 0.5, 1.0)),
+            patterns::UvMapping::Planar,
+        );
+        let half = 2.0_f64.sqrt() / 2.0;
+        assert!(towards_u
+            .perturb_normal(&s, &p, &n)
+            .is_equal_to(&tuples::vector(half, half, 0.0)));
+        let towards_v = normal_map_bump(
+            flat_normal_map(tuples::color(0.5, 1.0, 1.0)),
+            patterns::UvMapping::Planar,
+        );
+        assert!(towards_v
+            .perturb_normal(&s, &p, &n)
+            .is_equal_to(&tuples::vector(0.0, half, half)));
+    }
+
+    #[test]
+    fn test_a_normal_map_on_a_sphere_ignores_the_seam() {
+        //Where a spherical mapping wraps round, the tangent frame still points along u
+        let s = spheres::sphere();
+        let bump = normal_map_bump(
+            flat_normal_map(tuples::color(1.0, 0.5, 0.5)),
+            patterns::UvMapping::Spherical,
+        );
+        //u is 0 (and 1) straight towards -z, and grows towards +x
+        let p = tuples::point(0.0, 0.0, -1.0);
+        let n = s.normal_at(&p);
+        let tipped = bump.perturb_normal(&s, &p, &n);
+        assert!(tipped.is_equal_to(&tuples::vector(1.0, 0.0, 0.0)));
+    }
+}
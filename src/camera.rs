@@ -1,12 +1,46 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
 use std::time::Instant;
 
 use crate::canvas;
 use crate::matrices;
 use crate::rays;
-
+use crate::sequences;
 use crate::tuples;
 use crate::worlds;
 
+//how the rays for one pixel are spread across it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sampling {
+    //evenly spaced on a square grid
+    Grid,
+    //one random point inside each cell of the grid
+    Jittered,
+    //random points anywhere in the pixel
+    Random,
+}
+
+//used by both the camera and the command line
+pub const SAMPLING_DEFAULT: Sampling = Sampling::Jittered;
+
+//what an adaptive render had to do beyond its first pass over the pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveStats {
+    pub pixels: u32,
+    pub pixels_refined: u32,
+    pub extra_rays: u64,
+}
+
+impl AdaptiveStats {
+    pub fn report(&self) -> String {
+        format!(
+            "Adaptive anti-aliasing: refined {} of {} pixels with {} extra rays",
+            self.pixels_refined, self.pixels, self.extra_rays
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Camera {
     pub hsize: u32,
@@ -14,15 +48,57 @@ pub struct Camera {
     pub half_width: f64,
     pub half_height: f64,
     pub field_of_view: f64,
-    pub transform: matrices::Matrix4,
+    //set with set_transform, which keeps the inverse in step
+    transform: matrices::Matrix4,
+    transform_inverse: matrices::Matrix4,
     pub pixel_size: f64,
+    pub recursive_depth: i32,
+    //0 uses every available core
+    pub threads: usize,
+    //rays averaged per pixel, 1 shoots only through the centre.
+    //set with set_sampling, which checks grid and jittered get a square number
+    samples: u32,
+    sampling: Sampling,
+    //for render_adaptive, the largest channel difference from a neighbour that
+    //still counts as smooth, and how many times a pixel may be split into quarters
+    pub adaptive_threshold: f64,
+    pub adaptive_depth: u32,
 }
 
 impl Camera {
+    pub fn set_transform(&mut self, transform: matrices::Matrix4) {
+        self.transform = transform;
+        self.transform_inverse = transform.inverse();
+    }
+
+    pub fn transform(&self) -> &matrices::Matrix4 {
+        &self.transform
+    }
+
+    pub fn set_sampling(&mut self, samples: u32, sampling: Sampling) -> Result<(), String> {
+        check_sampling(samples, sampling)?;
+        self.samples = samples;
+        self.sampling = sampling;
+        Ok(())
+    }
+
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn sampling(&self) -> Sampling {
+        self.sampling
+    }
+
     pub fn ray_for_pixel(&self, px: u32, py: u32) -> rays::Ray {
-        //the offset from the edge of the canvas to the pixel's center
-        let xoffset: f64 = (px as f64 + 0.5) * self.pixel_size;
-        let yoffset: f64 = (py as f64 + 0.5) * self.pixel_size;
+        self.ray_for_pixel_offset(px, py, 0.5, 0.5)
+    }
+
+    //dx and dy are how far across the pixel the ray passes, from 0 to 1
+    pub fn ray_for_pixel_offset(&self, px: u32, py: u32, dx: f64, dy: f64) -> rays::Ray {
+        //the offset from the edge of the canvas to the point in the pixel
+        let xoffset: f64 = (px as f64 + dx) * self.pixel_size;
+        let yoffset: f64 = (py as f64 + dy) * self.pixel_size;
 
         //the untransformed coordinates of the pixel in world space.
         //(remember that the camera looks toward -z, so +x is to the *left*.)
@@ -33,46 +109,227 @@ impl Camera {
         //and then compute the ray's direction vector.
         //(remember that the canvas is at z=-1)
         let pixel: tuples::Point = self
-            .transform
-            .inverse()
+            .transform_inverse
             .tuple_multiply(&tuples::point(world_x, world_y, -1.0));
         let origin: tuples::Point = self
-            .transform
-            .inverse()
+            .transform_inverse
             .tuple_multiply(&tuples::point(0.0, 0.0, 0.0));
         let direction: tuples::Vector = pixel.subtract(&origin).normalize();
 
         rays::ray(origin, direction)
     }
 
+    pub fn pixel_offsets(&self, px: u32, py: u32) -> Vec<(f64, f64)> {
+        if self.samples <= 1 {
+            return vec![(0.5, 0.5)];
+        }
+        //seeded by the pixel so the image doesn't depend on which thread rendered it
+        let seed = py as u64 * self.hsize as u64 + px as u64;
+        let mut random = sequences::random_sequence(seed);
+        let side = (self.samples as f64).sqrt().round() as u32;
+        let cell = 1.0 / side as f64;
+        match self.sampling {
+            Sampling::Grid => grid_cells(side)
+                .map(|(i, j)| ((i as f64 + 0.5) * cell, (j as f64 + 0.5) * cell))
+                .collect(),
+            Sampling::Jittered => grid_cells(side)
+                .map(|(i, j)| {
+                    (
+                        (i as f64 + random.next_value()) * cell,
+                        (j as f64 + random.next_value()) * cell,
+                    )
+                })
+                .collect(),
+            Sampling::Random => (0..self.samples)
+                .map(|_| (random.next_value(), random.next_value()))
+                .collect(),
+        }
+    }
+
+    pub fn color_at_pixel(&self, w: &worlds::World, px: u32, py: u32) -> tuples::Color {
+        let offsets = self.pixel_offsets(px, py);
+        let mut total = tuples::COLOR_BLACK;
+        for (dx, dy) in offsets.iter() {
+            let r = self.ray_for_pixel_offset(px, py, *dx, *dy);
+            total = total.add(&w.color_at(&r, &self.recursive_depth));
+        }
+        total.scalar_multiply(&(1.0 / offsets.len() as f64))
+    }
+
     pub fn render(&self, w: &worlds::World) -> canvas::PixelCanvas {
-        let mut image = canvas::pixel_canvas(self.hsize, self.vsize, tuples::COLOR_BLACK);
-        for y in 0..self.vsize {
-            for x in 0..self.hsize {
-                let r = self.ray_for_pixel(x, y);
-                let col = w.color_at(&r, &worlds::RECURSIVE_DEPTH);
-                image = image.pixel_write(&x, &y, col);
+        self.render_rows(None, |x, y| self.color_at_pixel(w, x, y))
+    }
+
+    pub fn render_percent_message(&self, w: worlds::World, incr: f64) -> canvas::PixelCanvas {
+        self.render_rows(Some(incr), |x, y| self.color_at_pixel(&w, x, y))
+    }
+
+    //renders every pixel with its usual samples, then goes back over the pixels
+    //that stand out from a neighbour and subdivides them until the samples agree
+    pub fn render_adaptive(
+        &self,
+        w: &worlds::World,
+        incr: Option<f64>,
+    ) -> (canvas::PixelCanvas, AdaptiveStats) {
+        let first = self.render_rows(None, |x, y| self.color_at_pixel(w, x, y));
+        let pixels_refined = AtomicU32::new(0);
+        let extra_rays = AtomicU64::new(0);
+        let image = self.render_rows(incr, |x, y| {
+            let col = first.get_at(&x, &y);
+            if !self.stands_out(&first, x, y) {
+                return col;
+            }
+            let mut rays = 0;
+            let refined = self.refine(w, (x, y), (0.0, 0.0), 1.0, self.adaptive_depth, &mut rays);
+            pixels_refined.fetch_add(1, Ordering::SeqCst);
+            extra_rays.fetch_add(rays, Ordering::SeqCst);
+            refined
+        });
+        let stats = AdaptiveStats {
+            pixels: self.hsize * self.vsize,
+            pixels_refined: pixels_refined.into_inner(),
+            extra_rays: extra_rays.into_inner(),
+        };
+        (image, stats)
+    }
+
+    fn stands_out(&self, image: &canvas::PixelCanvas, x: u32, y: u32) -> bool {
+        let col = image.get_at(&x, &y);
+        let neighbours = [
+            (x.checked_sub(1), Some(y)),
+            (Some(x + 1).filter(|x| *x < self.hsize), Some(y)),
+            (Some(x), y.checked_sub(1)),
+            (Some(x), Some(y + 1).filter(|y| *y < self.vsize)),
+        ];
+        neighbours.iter().any(|n| match n {
+            (Some(nx), Some(ny)) => {
+                color_difference(&col, &image.get_at(nx, ny)) > self.adaptive_threshold
             }
+            _ => false,
+        })
+    }
+
+    //samples the centres of the four quarters of a square within the pixel,
+    //and splits any square whose samples still disagree
+    fn refine(
+        &self,
+        w: &worlds::World,
+        (px, py): (u32, u32),
+        corner: (f64, f64),
+        size: f64,
+        depth: u32,
+        rays: &mut u64,
+    ) -> tuples::Color {
+        let half = size / 2.0;
+        let quarters = [
+            (corner.0, corner.1),
+            (corner.0 + half, corner.1),
+            (corner.0, corner.1 + half),
+            (corner.0 + half, corner.1 + half),
+        ];
+        let samples: Vec<tuples::Color> = quarters
+            .iter()
+            .map(|(qx, qy)| {
+                let r = self.ray_for_pixel_offset(px, py, qx + half / 2.0, qy + half / 2.0);
+                w.color_at(&r, &self.recursive_depth)
+            })
+            .collect();
+        *rays += 4;
+        let smooth = samples.iter().all(|a| {
+            samples
+                .iter()
+                .all(|b| color_difference(a, b) <= self.adaptive_threshold)
+        });
+        let cols: Vec<tuples::Color> = if smooth || depth <= 1 {
+            samples
+        } else {
+            quarters
+                .iter()
+                .map(|q| self.refine(w, (px, py), *q, half, depth - 1, rays))
+                .collect()
+        };
+        let mut total = tuples::COLOR_BLACK;
+        for col in cols.iter() {
+            total = total.add(col);
         }
-        image
+        total.scalar_multiply(&0.25)
     }
 
-    pub fn render_percent_message(&self, w: worlds::World, incr: f64) -> canvas::PixelCanvas {
-        let mut image = canvas::pixel_canvas(self.hsize, self.vsize, tuples::COLOR_BLACK);
-        let mut pc = 0.0;
+    pub fn thread_count(&self) -> usize {
+        let n = if self.threads == 0 {
+            thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            self.threads
+        };
+        n.min(self.vsize.max(1) as usize)
+    }
+
+    fn render_rows<F>(&self, incr: Option<f64>, pixel_color: F) -> canvas::PixelCanvas
+    where
+        F: Fn(u32, u32) -> tuples::Color + Sync,
+    {
+        //each thread takes the next unrendered row until none are left,
+        //every pixel only depends on its own rays so the thread count can't change the image
+        let next_row = AtomicU32::new(0);
+        let progress = Mutex::new((0, 0.0));
         let timer = Instant::now();
-        for y in 0..self.vsize {
-            pc = percent_message(y as f64, self.vsize as f64, pc, incr, timer.elapsed());
-            for x in 0..self.hsize {
-                let r = self.ray_for_pixel(x, y);
-                let col = w.color_at(&r, &worlds::RECURSIVE_DEPTH);
-                image = image.pixel_write(&x, &y, col);
+        let rows: Vec<(u32, Vec<tuples::Color>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.thread_count())
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = vec![];
+                        loop {
+                            let y = next_row.fetch_add(1, Ordering::SeqCst);
+                            if y >= self.vsize {
+                                break;
+                            }
+                            let row: Vec<tuples::Color> =
+                                (0..self.hsize).map(|x| pixel_color(x, y)).collect();
+                            done.push((y, row));
+                            if let Some(incr) = incr {
+                                let mut p = progress.lock().unwrap();
+                                p.0 += 1;
+                                p.1 = percent_message(
+                                    p.0 as f64,
+                                    self.vsize as f64,
+                                    p.1,
+                                    incr,
+                                    timer.elapsed(),
+                                );
+                            }
+                        }
+                        done
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        let mut image = canvas::pixel_canvas(self.hsize, self.vsize, tuples::COLOR_BLACK);
+        for (y, row) in rows {
+            for (x, col) in row.into_iter().enumerate() {
+                image.pixel_set(&(x as u32), &y, col);
             }
         }
         image
     }
 }
 
+//grid and jittered sampling lay their samples out on a square grid, so any other
+//count would mean casting more or fewer rays than were asked for
+pub fn check_sampling(samples: u32, sampling: Sampling) -> Result<(), String> {
+    let side = (samples as f64).sqrt().round() as u32;
+    if sampling != Sampling::Random && side * side != samples {
+        return Err(format!(
+            "Grid and jittered sampling need a square number of samples, not {}",
+            samples
+        ));
+    }
+    Ok(())
+}
+
 pub fn camera(hsize: u32, vsize: u32, field_of_view: f64) -> Camera {
     let half_view: f64 = (field_of_view / 2.0).tan();
     let aspect: f64 = (hsize as f64) / vsize as f64;
@@ -90,10 +347,29 @@ pub fn camera(hsize: u32, vsize: u32, field_of_view: f64) -> Camera {
         half_height: half_height,
         field_of_view: field_of_view,
         transform: matrices::IDENTITY_MATRIX,
+        transform_inverse: matrices::IDENTITY_MATRIX,
         pixel_size: pixel_size,
+        recursive_depth: worlds::RECURSIVE_DEPTH,
+        threads: 0,
+        samples: 1,
+        sampling: SAMPLING_DEFAULT,
+        adaptive_threshold: 0.1,
+        adaptive_depth: 2,
     }
 }
 
+//the largest difference between any of the red, green and blue channels
+pub fn color_difference(a: &tuples::Color, b: &tuples::Color) -> f64 {
+    (a.red - b.red)
+        .abs()
+        .max((a.green - b.green).abs())
+        .max((a.blue - b.blue).abs())
+}
+
+fn grid_cells(side: u32) -> impl Iterator<Item = (u32, u32)> {
+    (0..side).flat_map(move |j| (0..side).map(move |i| (i, j)))
+}
+
 pub fn percent_message(
     val: f64,
     total: f64,
@@ -124,6 +400,7 @@ pub fn percent_message(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::planes;
     use crate::transformations;
     use std::f64::consts::PI;
 
@@ -140,7 +417,9 @@ mod tests {
             tuples::get_bool_numbers_are_equal(&(c.field_of_view as f64), &(PI / 2.0)),
             true
         );
-        assert_eq!(matrices::IDENTITY_MATRIX.is_equal_to(&c.transform), true);
+        assert_eq!(matrices::IDENTITY_MATRIX.is_equal_to(c.transform()), true);
+        assert_eq!(c.recursive_depth, worlds::RECURSIVE_DEPTH);
+        assert_eq!(c.threads, 0);
     }
 
     #[test]
@@ -194,7 +473,10 @@ mod tests {
         let mut c = camera(201, 101, PI / 2.0);
         let rot = transformations::matrix4_rotation_y_rad(PI / 4.0);
         let tran = transformations::matrix4_translation(0.0, -2.0, 5.0);
-        c.transform = rot.multiply(&tran);
+        c.set_transform(rot.multiply(&tran));
+        assert!(c
+            .transform_inverse
+            .is_equal_to(&rot.multiply(&tran).inverse()));
         let r = c.ray_for_pixel(100, 50);
         assert_eq!(r.origin.is_equal_to(&tuples::point(0.0, 2.0, -5.0)), true);
         assert_eq!(
@@ -215,10 +497,206 @@ mod tests {
         let to = tuples::point(0.0, 0.0, 0.0);
         let up = tuples::vector(0.0, 1.0, 0.0);
         let mut c = camera(11, 11, PI / 2.0);
-        c.transform = transformations::view_transform(&from, &to, &up);
+        c.set_transform(transformations::view_transform(&from, &to, &up));
         let image = c.render(&w);
         let pa = image.get_at(&5, &5);
         let col = tuples::color(0.38066, 0.47583, 0.2855);
         assert_eq!(pa.is_equal_to(&col), true);
     }
+
+    #[test]
+    fn test_rendering_with_no_recursion_ignores_reflections() {
+        //Rendering with a recursive depth of zero skips reflected light
+        let mut w = worlds::world_default();
+        let mut s = planes::plane();
+        s.material.reflective = 0.5;
+        s.set_transform(transformations::matrix4_translation(0.0, -1.0, 0.0));
+        w.objects.push(s);
+        let from = tuples::point(0.0, 0.0, -3.0);
+        let to = tuples::point(0.0, -1.0, -2.0);
+        let up = tuples::vector(0.0, 1.0, 0.0);
+        let mut c = camera(11, 11, PI / 2.0);
+        c.set_transform(transformations::view_transform(&from, &to, &up));
+        let reflected = c.render(&w).get_at(&5, &5);
+        c.recursive_depth = 0;
+        let unreflected = c.render(&w).get_at(&5, &5);
+        assert!(reflected.is_equal_to(&tuples::color(0.87676, 0.92434, 0.82917)));
+        assert!(unreflected.is_equal_to(&tuples::color(0.68643, 0.68643, 0.68643)));
+    }
+
+    #[test]
+    fn test_rendering_is_the_same_for_any_thread_count() {
+        //Rendering gives the same image whether it uses one thread or many
+        let w = worlds::world_default();
+        let from = tuples::point(0.0, 1.0, -5.0);
+        let to = tuples::point(0.0, 0.0, 0.0);
+        let up = tuples::vector(0.0, 1.0, 0.0);
+        let mut c = camera(21, 13, PI / 2.0);
+        c.set_transform(transformations::view_transform(&from, &to, &up));
+        c.threads = 1;
+        let single = c.render(&w);
+        for threads in [2, 5, 64].iter() {
+            c.threads = *threads;
+            let multi = c.render(&w);
+            for i in 0..single.data.len() {
+                assert!(single.data[i].is_equal_to(&multi.data[i]));
+            }
+        }
+        assert_eq!(c.thread_count(), 13);
+    }
+
+    #[test]
+    fn test_a_single_sample_goes_through_the_pixel_centre() {
+        //With one sample per pixel the ray passes through the centre, jittered by default like the cli
+        let c = camera(201, 101, PI / 2.0);
+        assert_eq!(c.samples(), 1);
+        assert_eq!(c.sampling(), Sampling::Jittered);
+        assert_eq!(c.pixel_offsets(3, 7), vec![(0.5, 0.5)]);
+    }
+
+    #[test]
+    fn test_grid_sampling_spreads_samples_evenly() {
+        //Grid sampling places the samples at the centres of a square grid
+        let mut c = camera(201, 101, PI / 2.0);
+        c.set_sampling(4, Sampling::Grid).unwrap();
+        assert_eq!(
+            c.pixel_offsets(0, 0),
+            vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+        );
+        //a count that isn't square is refused rather than rounded up to more rays
+        assert_eq!(
+            c.set_sampling(5, Sampling::Grid).unwrap_err(),
+            "Grid and jittered sampling need a square number of samples, not 5"
+        );
+        assert!(c.set_sampling(8, Sampling::Jittered).is_err());
+        assert_eq!(c.samples(), 4);
+        assert_eq!(c.pixel_offsets(0, 0).len(), 4);
+    }
+
+    #[test]
+    fn test_jittered_sampling_keeps_one_sample_in_each_cell() {
+        //Jittered sampling puts one random sample inside each grid cell
+        let mut c = camera(201, 101, PI / 2.0);
+        c.set_sampling(9, Sampling::Jittered).unwrap();
+        let offsets = c.pixel_offsets(10, 20);
+        assert_eq!(offsets.len(), 9);
+        for (n, (dx, dy)) in offsets.iter().enumerate() {
+            let i = (n % 3) as f64;
+            let j = (n / 3) as f64;
+            assert!(*dx >= i / 3.0 && *dx < (i + 1.0) / 3.0);
+            assert!(*dy >= j / 3.0 && *dy < (j + 1.0) / 3.0);
+        }
+        assert_eq!(offsets, c.pixel_offsets(10, 20));
+        assert_ne!(offsets, c.pixel_offsets(11, 20));
+    }
+
+    #[test]
+    fn test_random_sampling_uses_exactly_n_samples_inside_the_pixel() {
+        //Random sampling takes exactly the requested number of samples
+        let mut c = camera(201, 101, PI / 2.0);
+        c.set_sampling(5, Sampling::Random).unwrap();
+        let offsets = c.pixel_offsets(4, 4);
+        assert_eq!(offsets.len(), 5);
+        for (dx, dy) in offsets.iter() {
+            assert!((0.0..1.0).contains(dx));
+            assert!((0.0..1.0).contains(dy));
+        }
+    }
+
+    #[test]
+    fn test_supersampling_averages_the_samples() {
+        //A supersampled pixel is the average of the colors of its rays
+        let w = worlds::world_default();
+        let from = tuples::point(0.0, 0.0, -5.0);
+        let to = tuples::point(0.0, 0.0, 0.0);
+        let up = tuples::vector(0.0, 1.0, 0.0);
+        let mut c = camera(11, 11, PI / 2.0);
+        c.set_transform(transformations::view_transform(&from, &to, &up));
+        c.set_sampling(4, SAMPLING_DEFAULT).unwrap();
+        let mut expected = tuples::COLOR_BLACK;
+        for (dx, dy) in c.pixel_offsets(4, 5).iter() {
+            let r = c.ray_for_pixel_offset(4, 5, *dx, *dy);
+            expected = expected.add(&w.color_at(&r, &c.recursive_depth));
+        }
+        expected = expected.scalar_multiply(&0.25);
+        let image = c.render(&w);
+        assert!(image.get_at(&4, &5).is_equal_to(&expected));
+        c.set_sampling(1, SAMPLING_DEFAULT).unwrap();
+        assert!(!image
+            .get_at(&4, &5)
+            .is_equal_to(&c.render(&w).get_at(&4, &5)));
+    }
+
+    #[test]
+    fn test_color_difference_is_the_largest_channel_difference() {
+        //The difference between colors is their largest channel difference
+        let a = tuples::color(0.2, 0.5, 0.9);
+        let b = tuples::color(0.3, 0.1, 0.8);
+        assert!(tuples::get_bool_numbers_are_equal(
+            &color_difference(&a, &b),
+            &0.4
+        ));
+    }
+
+    #[test]
+    fn test_adaptive_rendering_leaves_flat_images_alone() {
+        //An image without any contrast needs no extra rays
+        let w = worlds::world();
+        let c = camera(11, 11, PI / 2.0);
+        let (image, stats) = c.render_adaptive(&w, None);
+        assert_eq!(stats.pixels, 121);
+        assert_eq!(stats.pixels_refined, 0);
+        assert_eq!(stats.extra_rays, 0);
+        assert!(image.get_at(&5, &5).is_equal_to(&tuples::COLOR_BLACK));
+    }
+
+    #[test]
+    fn test_adaptive_rendering_refines_only_high_contrast_pixels() {
+        //Only pixels that stand out from a neighbour are subdivided
+        let w = worlds::world_default();
+        let from = tuples::point(0.0, 0.0, -5.0);
+        let to = tuples::point(0.0, 0.0, 0.0);
+        let up = tuples::vector(0.0, 1.0, 0.0);
+        let mut c = camera(11, 11, PI / 2.0);
+        c.set_transform(transformations::view_transform(&from, &to, &up));
+        let plain = c.render(&w);
+        let (image, stats) = c.render_adaptive(&w, None);
+        assert!(stats.pixels_refined > 0);
+        assert!(stats.pixels_refined < stats.pixels);
+        assert!(stats.extra_rays >= 4 * stats.pixels_refined as u64);
+        assert_eq!(stats.extra_rays % 4, 0);
+        //the corner is background surrounded by background
+        assert!(image.get_at(&0, &0).is_equal_to(&plain.get_at(&0, &0)));
+        //the edge of the sphere is a mix of sphere and background
+        assert!(!image.get_at(&4, &5).is_equal_to(&plain.get_at(&4, &5)));
+        c.adaptive_depth = 1;
+        let (_, shallow) = c.render_adaptive(&w, None);
+        assert_eq!(shallow.pixels_refined, stats.pixels_refined);
+        assert_eq!(shallow.extra_rays, 4 * shallow.pixels_refined as u64);
+        assert!(shallow.extra_rays <= stats.extra_rays);
+    }
+
+    #[test]
+    fn test_adaptive_rendering_keeps_the_samples_of_unrefined_pixels() {
+        //Pixels that aren't refined are still supersampled like a plain render
+        let w = worlds::world_default();
+        let from = tuples::point(0.0, 0.0, -5.0);
+        let to = tuples::point(0.0, 0.0, 0.0);
+        let up = tuples::vector(0.0, 1.0, 0.0);
+        let mut c = camera(11, 11, PI / 2.0);
+        c.set_transform(transformations::view_transform(&from, &to, &up));
+        c.set_sampling(4, Sampling::Grid).unwrap();
+        c.adaptive_threshold = 10.0;
+        let plain = c.render(&w);
+        let (image, stats) = c.render_adaptive(&w, None);
+        assert_eq!(stats.pixels_refined, 0);
+        for y in 0..11 {
+            for x in 0..11 {
+                assert!(image.get_at(&x, &y).is_equal_to(&plain.get_at(&x, &y)));
+            }
+        }
+        //the edge of the sphere is not what the centre ray alone sees
+        let centre = w.color_at(&c.ray_for_pixel(4, 5), &c.recursive_depth);
+        assert!(!image.get_at(&4, &5).is_equal_to(&centre));
+    }
 }
@@ -24,6 +24,14 @@ impl PixelCanvas {
         }
     }
 
+    pub fn pixel_set(&mut self, x: &u32, y: &u32, col: tuples::Color) {
+        //writes in place, instead of rebuilding the canvas like pixel_write
+        let index = self.width * y + x;
+        if index < self.length {
+            self.data[index as usize] = col;
+        }
+    }
+
     pub fn get_at(&self, x: &u32, y: &u32) -> tuples::Color {
         let index = self.width * y + x;
         let mut col = tuples::color(1.0, 0.8, 0.8); //default bright pink?
@@ -151,6 +159,25 @@ pub fn pixel_canvas(width: u32, height: u32, default_color: tuples::Color) -> Pi
     }
 }
 
+//loads a png, ppm or anything else the image crate can read, e.g. for textures
+pub fn pixel_canvas_from_path(path: &str) -> Result<PixelCanvas, String> {
+    let img = image::open(path).map_err(|e| format!("Problem loading {}: {}", path, e))?;
+    Ok(pixel_canvas_from_image(&img.to_rgb8()))
+}
+
+pub fn pixel_canvas_from_image(img: &image::RgbImage) -> PixelCanvas {
+    let mut c = pixel_canvas(img.width(), img.height(), tuples::COLOR_BLACK);
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let col = tuples::color(
+            pixel[0] as f64 / CLAMP_LIMIT as f64,
+            pixel[1] as f64 / CLAMP_LIMIT as f64,
+            pixel[2] as f64 / CLAMP_LIMIT as f64,
+        );
+        c.pixel_set(&x, &y, col);
+    }
+    c
+}
+
 pub fn str_from_color_get(col: tuples::Color) -> String {
     let color_clamped_to_zero_to_one = color_clamp(col);
     let r = (color_clamped_to_zero_to_one.red * CLAMP_LIMIT as f64) as u32;
@@ -215,6 +242,40 @@ mod tests {
         assert_eq!(pc.data[32].is_equal_to(&red), true)
     }
 
+    #[test]
+    fn test_pixel_canvas_from_saved_ppm_and_png() {
+        //A saved canvas loads back from ppm or png with the same colors
+        let mut pc = pixel_canvas(3, 2, tuples::COLOR_BLACK);
+        pc.pixel_set(&0, &0, tuples::color(1.0, 0.0, 0.0));
+        pc.pixel_set(&2, &1, tuples::color(0.0, 1.0, 1.0));
+        let dir = std::env::temp_dir();
+        let ppm = dir.join("canvas_from_path_test.ppm");
+        let png = dir.join("canvas_from_path_test.png");
+        std::fs::write(&ppm, pc.ppm_get()).unwrap();
+        pc.png_get().save(&png).unwrap();
+        for path in [ppm, png].iter() {
+            let loaded = pixel_canvas_from_path(path.to_str().unwrap()).unwrap();
+            assert_eq!(loaded.width, 3);
+            assert_eq!(loaded.height, 2);
+            for i in 0..pc.data.len() {
+                assert!(loaded.data[i].is_equal_to(&pc.data[i]));
+            }
+            std::fs::remove_file(path).unwrap();
+        }
+        assert!(pixel_canvas_from_path("no/such/texture.png").is_err());
+    }
+
+    #[test]
+    fn test_pixel_set() {
+        //Writing a pixel in place
+        let mut pc = pixel_canvas(10, 20, tuples::COLOR_BLACK);
+        let red = tuples::color(1.0, 0.0, 0.0);
+        pc.pixel_set(&2, &3, red);
+        pc.pixel_set(&10, &20, red);
+        assert!(pc.get_at(&2, &3).is_equal_to(&red));
+        assert_eq!(pc.data.iter().filter(|c| c.is_equal_to(&red)).count(), 1);
+    }
+
     #[test]
     fn test_str_from_color_get() {
         //getString_fromColor - returns clamped color string of 3 numbers, separated and ending with a space
@@ -0,0 +1,523 @@
+use chrono::prelude::*;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+use rust_ray_tracer_challenge::camera;
+use rust_ray_tracer_challenge::canvas;
+use rust_ray_tracer_challenge::groups;
+use rust_ray_tracer_challenge::scenes;
+use rust_ray_tracer_challenge::worlds;
+
+use crate::program_chapter_10_patterns;
+use crate::program_chapter_11_reflection;
+use crate::program_chapter_11_refractions;
+use crate::program_chapter_9_planes;
+use crate::program_fire_canon;
+use crate::program_sphere_lighting;
+use crate::program_sphere_outline;
+use crate::program_world;
+
+//name, default width, default height - same order as the interactive menu
+pub const PROGRAMS: [(&str, u32, u32); 8] = [
+    ("fire_canon", 500, 250),
+    ("sphere_outline", 200, 200),
+    ("sphere_lighting", 400, 400),
+    ("world", 200, 100),
+    ("chapter_9_planes", 100, 50),
+    ("chapter_10_patterns", 100, 50),
+    ("chapter_11_reflection", 100, 50),
+    ("chapter_11_refractions", 100, 50),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Progress,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub program: String,
+    pub width: u32,
+    pub height: u32,
+    pub output: String,
+    pub format: ImageFormat,
+    pub depth: i32,
+    pub threads: usize,
+    pub samples: u32,
+    pub sampling: camera::Sampling,
+    //threshold for adaptive anti-aliasing, None renders every pixel the same way
+    pub adaptive: Option<f64>,
+    pub adaptive_depth: u32,
+    pub verbosity: Verbosity,
+}
+
+pub fn app() -> App<'static, 'static> {
+    App::new("rust_ray_tracer_challenge")
+        .about("Ray tracer from The Ray Tracer Challenge. Run without arguments for the interactive menu.")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("list").about("Lists the programs that can be rendered"))
+        .subcommand(
+            SubCommand::with_name("render")
+                .about("Renders a program to an image file")
+                .arg(
+                    Arg::with_name("program")
+                        .help("Name of the program to render (see 'list')")
+                        .required(true)
+                        .possible_values(&program_names()),
+                )
+                .args(&image_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("scene")
+                .about("Renders a yaml scene file to an image file")
+                .arg(
+                    Arg::with_name("file")
+                        .help("Path to the yaml scene description")
+                        .required(true),
+                )
+                .args(&image_args()),
+        )
+}
+
+fn image_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("width")
+            .long("width")
+            .short("x")
+            .takes_value(true)
+            .help("Image width in pixels (defaults to the program's or camera's size)"),
+        Arg::with_name("height")
+            .long("height")
+            .short("y")
+            .takes_value(true)
+            .help("Image height in pixels (defaults to the program's or camera's size)"),
+        Arg::with_name("output")
+            .long("output")
+            .short("o")
+            .takes_value(true)
+            .help("Output file (defaults to images/<name>_<date>.<format>)"),
+        Arg::with_name("format")
+            .long("format")
+            .short("f")
+            .takes_value(true)
+            .possible_values(&["png", "ppm"])
+            .help("Output format (defaults to the output extension, else png)"),
+        Arg::with_name("depth")
+            .long("depth")
+            .short("d")
+            .takes_value(true)
+            .help("Maximum recursion depth for reflection and refraction rays"),
+        Arg::with_name("threads")
+            .long("threads")
+            .short("t")
+            .takes_value(true)
+            .help("Number of rendering threads (defaults to 0, every available core)"),
+        Arg::with_name("samples")
+            .long("samples")
+            .short("s")
+            .takes_value(true)
+            .help("Rays per pixel for anti-aliasing (defaults to 1, grid and jittered need a square number like 4, 9 or 16)"),
+        Arg::with_name("sampling")
+            .long("sampling")
+            .takes_value(true)
+            .possible_values(&["grid", "jittered", "random"])
+            .help("How the rays are spread across each pixel (defaults to jittered)"),
+        Arg::with_name("adaptive")
+            .long("adaptive")
+            .short("a")
+            .takes_value(true)
+            .help("Only refines pixels whose color differs from a neighbour by more than this (e.g. 0.1)"),
+        Arg::with_name("adaptive-depth")
+            .long("adaptive-depth")
+            .takes_value(true)
+            .requires("adaptive")
+            .help("How many times an adaptive pixel may be split into quarters (defaults to 2)"),
+        Arg::with_name("quiet")
+            .long("quiet")
+            .short("q")
+            .conflicts_with("progress")
+            .help("Prints nothing except errors"),
+        Arg::with_name("progress")
+            .long("progress")
+            .short("p")
+            .help("Prints percentage progress while rendering"),
+    ]
+}
+
+pub fn run(args: Vec<String>) -> Result<(), String> {
+    let matches = match app().get_matches_from_safe(args) {
+        Ok(m) => m,
+        Err(e) => {
+            //--help and --version come back as errors that belong on stdout
+            if e.use_stderr() {
+                return Err(e.message);
+            }
+            println!("{}", e.message);
+            return Ok(());
+        }
+    };
+    match matches.subcommand() {
+        ("list", _) => {
+            for (name, w, h) in PROGRAMS.iter() {
+                println!("{} ({}x{})", name, w, h);
+            }
+            Ok(())
+        }
+        ("render", Some(sub)) => render(&program_options(sub)?),
+        ("scene", Some(sub)) => {
+            let file = sub.value_of("file").unwrap_or("");
+            let scene = scenes::scene_from_file(file)?;
+            if !sub.is_present("quiet") {
+                for (path, count) in scene.skipped.iter() {
+                    println!("{}: skipped {} unsupported lines", path, count);
+                }
+            }
+            let name = Path::new(file)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("scene");
+            let opts = render_options(sub, name, scene.camera.hsize, scene.camera.vsize)?;
+            render_scene(scene, &opts)
+        }
+        _ => Err("No subcommand given".to_string()),
+    }
+}
+
+fn program_names() -> Vec<&'static str> {
+    PROGRAMS.iter().map(|p| p.0).collect()
+}
+
+pub fn program_options(m: &ArgMatches) -> Result<RenderOptions, String> {
+    let program = m.value_of("program").unwrap_or("");
+    match PROGRAMS.iter().find(|p| p.0 == program) {
+        Some(p) => render_options(m, program, p.1, p.2),
+        None => Err(format!("Unknown program '{}'", program)),
+    }
+}
+
+pub fn render_options(
+    m: &ArgMatches,
+    name: &str,
+    default_width: u32,
+    default_height: u32,
+) -> Result<RenderOptions, String> {
+    let width = parse_arg(m, "width", default_width)?;
+    let height = parse_arg(m, "height", default_height)?;
+    if width == 0 || height == 0 {
+        return Err("Width and height must be greater than zero".to_string());
+    }
+    let depth = parse_arg(m, "depth", worlds::RECURSIVE_DEPTH)?;
+    if depth < 0 {
+        return Err("Depth must not be negative".to_string());
+    }
+    let threads = parse_arg(m, "threads", 0)?;
+    let samples = parse_arg(m, "samples", 1)?;
+    if samples == 0 {
+        return Err("Samples must be greater than zero".to_string());
+    }
+    let sampling = match m.value_of("sampling") {
+        Some(s) => sampling_from_str(s)?,
+        None => camera::SAMPLING_DEFAULT,
+    };
+    camera::check_sampling(samples, sampling)?;
+    let format = match m.value_of("format") {
+        Some(f) => format_from_str(f)?,
+        None => match m.value_of("output") {
+            Some(o) => format_from_extension(o),
+            None => ImageFormat::Png,
+        },
+    };
+    let output = match m.value_of("output") {
+        Some(o) => o.to_string(),
+        None => {
+            let d = Utc::now().format("%Y-%m-%d-%H-%M").to_string();
+            format!("images/{}_{}.{}", name, d, extension(format))
+        }
+    };
+    let adaptive = match m.value_of("adaptive") {
+        Some(_) => Some(parse_arg(m, "adaptive", 0.0)?),
+        None => None,
+    };
+    let adaptive_depth = parse_arg(m, "adaptive-depth", 2)?;
+    if adaptive_depth == 0 {
+        return Err("Adaptive depth must be greater than zero".to_string());
+    }
+    let verbosity = if m.is_present("quiet") {
+        Verbosity::Quiet
+    } else if m.is_present("progress") {
+        Verbosity::Progress
+    } else {
+        Verbosity::Normal
+    };
+    Ok(RenderOptions {
+        program: name.to_string(),
+        width,
+        height,
+        output,
+        format,
+        depth,
+        threads,
+        samples,
+        sampling,
+        adaptive,
+        adaptive_depth,
+        verbosity,
+    })
+}
+
+fn parse_arg<T: std::str::FromStr>(m: &ArgMatches, name: &str, default: T) -> Result<T, String> {
+    match m.value_of(name) {
+        Some(v) => v
+            .parse::<T>()
+            .map_err(|_| format!("Invalid value for --{}: '{}'", name, v)),
+        None => Ok(default),
+    }
+}
+
+fn format_from_str(s: &str) -> Result<ImageFormat, String> {
+    match s.to_lowercase().as_str() {
+        "png" => Ok(ImageFormat::Png),
+        "ppm" => Ok(ImageFormat::Ppm),
+        _ => Err(format!("Unknown format '{}'", s)),
+    }
+}
+
+fn sampling_from_str(s: &str) -> Result<camera::Sampling, String> {
+    match s.to_lowercase().as_str() {
+        "grid" => Ok(camera::Sampling::Grid),
+        "jittered" => Ok(camera::Sampling::Jittered),
+        "random" => Ok(camera::Sampling::Random),
+        _ => Err(format!("Unknown sampling '{}'", s)),
+    }
+}
+
+fn format_from_extension(path: &str) -> ImageFormat {
+    match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some(e) if e.eq_ignore_ascii_case("ppm") => ImageFormat::Ppm,
+        _ => ImageFormat::Png,
+    }
+}
+
+fn extension(format: ImageFormat) -> &'static str {
+    match format {
+        ImageFormat::Png => "png",
+        ImageFormat::Ppm => "ppm",
+    }
+}
+
+fn scene(program: &str, w: u32, h: u32) -> Option<(worlds::World, camera::Camera)> {
+    match program {
+        "world" => Some(program_world::scene(w, h)),
+        "chapter_9_planes" => Some(program_chapter_9_planes::scene(w, h)),
+        "chapter_10_patterns" => Some(program_chapter_10_patterns::scene(w, h)),
+        "chapter_11_reflection" => Some(program_chapter_11_reflection::scene(w, h)),
+        "chapter_11_refractions" => Some(program_chapter_11_refractions::scene(w, h)),
+        _ => None,
+    }
+}
+
+fn draw(program: &str, w: u32, h: u32) -> Option<canvas::PixelCanvas> {
+    match program {
+        "fire_canon" => Some(program_fire_canon::draw(w, h)),
+        "sphere_outline" => Some(program_sphere_outline::draw(w, h)),
+        "sphere_lighting" => Some(program_sphere_lighting::draw(w, h)),
+        _ => None,
+    }
+}
+
+pub fn render(opts: &RenderOptions) -> Result<(), String> {
+    let start = Instant::now();
+    let image = match scene(&opts.program, opts.width, opts.height) {
+        Some((world, c)) => render_world(world, c, opts)?,
+        None => match draw(&opts.program, opts.width, opts.height) {
+            Some(c) => c,
+            None => return Err(format!("Unknown program '{}'", opts.program)),
+        },
+    };
+    finish(&image, opts, start)
+}
+
+pub fn render_scene(scene: scenes::Scene, opts: &RenderOptions) -> Result<(), String> {
+    let start = Instant::now();
+    let mut c = scene.camera;
+    if c.hsize != opts.width || c.vsize != opts.height {
+        let transform = *c.transform();
+        c = camera::camera(opts.width, opts.height, c.field_of_view);
+        c.set_transform(transform);
+    }
+    let image = render_world(scene.world, c, opts)?;
+    finish(&image, opts, start)
+}
+
+fn render_world(
+    mut world: worlds::World,
+    mut c: camera::Camera,
+    opts: &RenderOptions,
+) -> Result<canvas::PixelCanvas, String> {
+    let stats = world.build_bvh(groups::BVH_THRESHOLD);
+    if opts.verbosity != Verbosity::Quiet {
+        println!("{}", stats.report());
+    }
+    c.recursive_depth = opts.depth;
+    c.threads = opts.threads;
+    c.set_sampling(opts.samples, opts.sampling)?;
+    if let Some(threshold) = opts.adaptive {
+        c.adaptive_threshold = threshold;
+        c.adaptive_depth = opts.adaptive_depth;
+        let incr = if opts.verbosity == Verbosity::Progress {
+            Some(0.01)
+        } else {
+            None
+        };
+        let (image, stats) = c.render_adaptive(&world, incr);
+        if opts.verbosity != Verbosity::Quiet {
+            println!("{}", stats.report());
+        }
+        return Ok(image);
+    }
+    if opts.verbosity == Verbosity::Progress {
+        Ok(c.render_percent_message(world, 0.01))
+    } else {
+        Ok(c.render(&world))
+    }
+}
+
+fn finish(image: &canvas::PixelCanvas, opts: &RenderOptions, start: Instant) -> Result<(), String> {
+    let duration = start.elapsed();
+    save(image, &opts.output, opts.format)?;
+    if opts.verbosity != Verbosity::Quiet {
+        println!(
+            "Rendered {} ({}x{}) in {:?} to {}",
+            opts.program, opts.width, opts.height, duration, opts.output
+        );
+    }
+    Ok(())
+}
+
+fn save(image: &canvas::PixelCanvas, path: &str, format: ImageFormat) -> Result<(), String> {
+    if let Some(dir) = Path::new(path).parent() {
+        if !dir.as_os_str().is_empty() {
+            fs::create_dir_all(dir).map_err(|e| format!("Problem creating {:?}: {}", dir, e))?;
+        }
+    }
+    let saved = match format {
+        ImageFormat::Ppm => fs::write(path, image.ppm_get()).map_err(|e| e.to_string()),
+        ImageFormat::Png => image
+            .png_get()
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|e| e.to_string()),
+    };
+    saved.map_err(|e| format!("Problem saving {}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(args: &[&str]) -> Result<RenderOptions, String> {
+        let mut all = vec!["rust_ray_tracer_challenge", "render"];
+        all.extend_from_slice(args);
+        let matches = app().get_matches_from_safe(all).map_err(|e| e.message)?;
+        program_options(matches.subcommand_matches("render").unwrap())
+    }
+
+    #[test]
+    fn test_render_options_use_program_defaults() {
+        //Render options fall back to the program's default size, png and normal output
+        let o = options(&["world"]).unwrap();
+        assert_eq!(o.width, 200);
+        assert_eq!(o.height, 100);
+        assert_eq!(o.depth, worlds::RECURSIVE_DEPTH);
+        assert_eq!(o.threads, 0);
+        assert_eq!(o.samples, 1);
+        assert_eq!(o.adaptive, None);
+        assert_eq!(o.format, ImageFormat::Png);
+        assert_eq!(o.verbosity, Verbosity::Normal);
+        assert!(o.output.starts_with("images/world_"));
+        assert!(o.output.ends_with(".png"));
+    }
+
+    #[test]
+    fn test_render_options_from_flags() {
+        //Render options read size, output, depth, threads and quiet flags
+        let o = options(&[
+            "chapter_9_planes",
+            "--width",
+            "320",
+            "--height",
+            "240",
+            "-o",
+            "out/plane.ppm",
+            "-d",
+            "2",
+            "--threads",
+            "3",
+            "-q",
+        ])
+        .unwrap();
+        assert_eq!(o.width, 320);
+        assert_eq!(o.height, 240);
+        assert_eq!(o.output, "out/plane.ppm");
+        assert_eq!(o.format, ImageFormat::Ppm);
+        assert_eq!(o.depth, 2);
+        assert_eq!(o.threads, 3);
+        assert_eq!(o.verbosity, Verbosity::Quiet);
+    }
+
+    #[test]
+    fn test_render_options_explicit_format_overrides_extension() {
+        //An explicit format wins over the output file extension
+        let o = options(&["world", "-o", "a.ppm", "--format", "png", "--progress"]).unwrap();
+        assert_eq!(o.format, ImageFormat::Png);
+        assert_eq!(o.verbosity, Verbosity::Progress);
+    }
+
+    #[test]
+    fn test_render_options_read_anti_aliasing() {
+        //Samples, sampling and adaptive set up anti-aliasing, jittered by default
+        let o = options(&["world", "-s", "16"]).unwrap();
+        assert_eq!(o.samples, 16);
+        assert_eq!(o.sampling, camera::Sampling::Jittered);
+        let o = options(&["world", "--samples", "4", "--sampling", "grid"]).unwrap();
+        assert_eq!(o.samples, 4);
+        assert_eq!(o.sampling, camera::Sampling::Grid);
+        let o = options(&["world", "-a", "0.05", "--adaptive-depth", "3"]).unwrap();
+        assert_eq!(o.adaptive, Some(0.05));
+        assert_eq!(o.adaptive_depth, 3);
+    }
+
+    #[test]
+    fn test_render_options_reject_bad_values() {
+        //Invalid sizes, unknown programs and conflicting flags are errors
+        assert!(options(&["world", "--width", "abc"]).is_err());
+        assert!(options(&["world", "--height", "0"]).is_err());
+        assert!(options(&["world", "--threads", "-1"]).is_err());
+        assert_eq!(
+            options(&["world", "--depth=-1"]).unwrap_err(),
+            "Depth must not be negative"
+        );
+        assert!(options(&["world", "--depth", "0"]).is_ok());
+        assert!(options(&["world", "--samples", "0"]).is_err());
+        assert!(options(&["world", "--sampling", "poisson"]).is_err());
+        assert_eq!(
+            options(&["world", "--samples", "5"]).unwrap_err(),
+            "Grid and jittered sampling need a square number of samples, not 5"
+        );
+        assert!(options(&["world", "--samples", "8", "--sampling", "grid"]).is_err());
+        assert!(options(&["world", "--samples", "5", "--sampling", "random"]).is_ok());
+        assert!(options(&["world", "--adaptive-depth", "3"]).is_err());
+        assert!(options(&["world", "-a", "0.1", "--adaptive-depth", "0"]).is_err());
+        assert!(options(&["not_a_program"]).is_err());
+        assert!(options(&["world", "-q", "-p"]).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::bounds;
+use crate::cylinders;
+use crate::intersections;
+use crate::rays;
+use crate::shapes;
+use crate::tuples;
+
+pub fn cone() -> shapes::Shape {
+    shapes::shape(shapes::ShapeType::Cone)
+}
+
+pub fn cone_truncated(minimum: f64, maximum: f64, closed: bool) -> shapes::Shape {
+    let mut s = cone();
+    s.minimum = minimum;
+    s.maximum = maximum;
+    s.closed = closed;
+    s
+}
+
+pub fn local_intersect(
+    s: &shapes::Shape,
+    local_r: &rays::Ray,
+) -> Result<Vec<intersections::Intersection>, String> {
+    let mut xs: Vec<intersections::Intersection> = vec![];
+    let o = local_r.origin;
+    let d = local_r.direction;
+    let a = d.x.powi(2) - d.y.powi(2) + d.z.powi(2);
+    let b = 2.0 * o.x * d.x - 2.0 * o.y * d.y + 2.0 * o.z * d.z;
+    let c = o.x.powi(2) - o.y.powi(2) + o.z.powi(2);
+    let mut ts: Vec<f64> = vec![];
+    if a.abs() < tuples::EPSILON {
+        //ray is parallel to one of the cone's halves, so hits the other half once
+        if b.abs() >= tuples::EPSILON {
+            ts.push(-c / (2.0 * b));
+        }
+    } else {
+        let disc = b.powi(2) - 4.0 * a * c;
+        if disc >= 0.0 {
+            let t0 = (-b - disc.sqrt()) / (2.0 * a);
+            let t1 = (-b + disc.sqrt()) / (2.0 * a);
+            ts.push(t0.min(t1));
+            ts.push(t0.max(t1));
+        }
+    }
+    for t in ts.iter() {
+        let y = o.y + t * d.y;
+        if s.minimum < y && y < s.maximum {
+            xs.push(intersections::intersection(*t, s.clone()));
+        }
+    }
+    intersect_caps(s, local_r, &mut xs);
+    if xs.is_empty() {
+        Err("No intersections".to_string())
+    } else {
+        Ok(intersections::intersection_list(xs))
+    }
+}
+
+fn intersect_caps(
+    s: &shapes::Shape,
+    local_r: &rays::Ray,
+    xs: &mut Vec<intersections::Intersection>,
+) {
+    //the radius of each cap is the distance of its plane from the apex
+    if !s.closed || local_r.direction.y.abs() < tuples::EPSILON {
+        return;
+    }
+    let t_lower = (s.minimum - local_r.origin.y) / local_r.direction.y;
+    if cylinders::check_cap(local_r, t_lower, s.minimum.abs()) {
+        xs.push(intersections::intersection(t_lower, s.clone()));
+    }
+    let t_upper = (s.maximum - local_r.origin.y) / local_r.direction.y;
+    if cylinders::check_cap(local_r, t_upper, s.maximum.abs()) {
+        xs.push(intersections::intersection(t_upper, s.clone()));
+    }
+}
+
+pub fn local_bounds(s: &shapes::Shape) -> bounds::BoundingBox {
+    let limit = s.minimum.abs().max(s.maximum.abs());
+    bounds::bounding_box(
+        tuples::point(-limit, s.minimum, -limit),
+        tuples::point(limit, s.maximum, limit),
+    )
+}
+
+pub fn local_normal_at(s: &shapes::Shape, local_point: &tuples::Point) -> tuples::Vector {
+    let dist = local_point.x.powi(2) + local_point.z.powi(2);
+    if dist < s.maximum.powi(2) && local_point.y >= s.maximum - tuples::EPSILON {
+        tuples::vector(0.0, 1.0, 0.0)
+    } else if dist < s.minimum.powi(2) && local_point.y <= s.minimum + tuples::EPSILON {
+        tuples::vector(0.0, -1.0, 0.0)
+    } else if dist < tuples::EPSILON && local_point.y.abs() < tuples::EPSILON {
+        //at the apex the side normal collapses to zero, so point along the axis
+        //away from whichever nappe is present (the upper one for a double cone)
+        if s.maximum <= tuples::EPSILON {
+            tuples::vector(0.0, 1.0, 0.0)
+        } else {
+            tuples::vector(0.0, -1.0, 0.0)
+        }
+    } else {
+        let mut y = dist.sqrt();
+        if local_point.y > 0.0 {
+            y = -y;
+        }
+        tuples::vector(local_point.x, y, local_point.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_ray(origin: [f64; 3], direction: [f64; 3]) -> rays::Ray {
+        rays::ray(
+            tuples::point(origin[0], origin[1], origin[2]),
+            tuples::vector(direction[0], direction[1], direction[2]).normalize(),
+        )
+    }
+
+    #[test]
+    fn test_intersecting_a_cone_with_a_ray() {
+        //Intersecting a cone with a ray
+        let shape = cone();
+        let tests: [[[f64; 3]; 3]; 3] = [
+            [[0.0, 0.0, -5.0], [0.0, 0.0, 1.0], [5.0, 5.0, 0.0]],
+            [[0.0, 0.0, -5.0], [1.0, 1.0, 1.0], [8.66025, 8.66025, 0.0]],
+            [
+                [1.0, 1.0, -5.0],
+                [-0.5, -1.0, 1.0],
+                [4.55006, 49.44994, 0.0],
+            ],
+        ];
+        for test in tests.iter() {
+            let r = local_ray(test[0], test[1]);
+            match local_intersect(&shape, &r) {
+                Ok(xs) => {
+                    assert_eq!(xs.len(), 2);
+                    assert!(tuples::get_bool_numbers_are_equal(&xs[0].t, &test[2][0]));
+                    assert!(tuples::get_bool_numbers_are_equal(&xs[1].t, &test[2][1]));
+                }
+                Err(_) => {
+                    println!("Not possible in this test");
+                    assert_eq!(false, true);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_intersecting_a_cone_with_a_ray_parallel_to_one_of_its_halves() {
+        //Intersecting a cone with a ray parallel to one of its halves
+        let shape = cone();
+        let r = local_ray([0.0, 0.0, -1.0], [0.0, 1.0, 1.0]);
+        match local_intersect(&shape, &r) {
+            Ok(xs) => {
+                assert_eq!(xs.len(), 1);
+                assert!(tuples::get_bool_numbers_are_equal(&xs[0].t, &0.35355));
+            }
+            Err(_) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_intersecting_a_cones_end_caps() {
+        //Intersecting a cone's end caps
+        let shape = cone_truncated(-0.5, 0.5, true);
+        let tests: [([f64; 3], [f64; 3], usize); 3] = [
+            ([0.0, 0.0, -5.0], [0.0, 1.0, 0.0], 0),
+            ([0.0, 0.0, -0.25], [0.0, 1.0, 1.0], 2),
+            ([0.0, 0.0, -0.25], [0.0, 1.0, 0.0], 4),
+        ];
+        for (origin, direction, count) in tests.iter() {
+            let r = local_ray(*origin, *direction);
+            let found = match local_intersect(&shape, &r) {
+                Ok(xs) => xs.len(),
+                Err(_) => 0,
+            };
+            assert_eq!(found, *count);
+        }
+    }
+
+    #[test]
+    fn test_computing_the_normal_vector_on_a_cone() {
+        //Computing the normal vector on a cone
+        let shape = cone();
+        let tests: [[[f64; 3]; 2]; 2] = [
+            [[1.0, 1.0, 1.0], [1.0, -std::f64::consts::SQRT_2, 1.0]],
+            [[-1.0, -1.0, 0.0], [-1.0, 1.0, 0.0]],
+        ];
+        for test in tests.iter() {
+            let p = tuples::point(test[0][0], test[0][1], test[0][2]);
+            let n = local_normal_at(&shape, &p);
+            let expected = tuples::vector(test[1][0], test[1][1], test[1][2]);
+            assert!(n.is_equal_to(&expected));
+        }
+    }
+
+    #[test]
+    fn test_normal_at_the_apex_of_a_cone() {
+        //The normal at the apex points along the axis, away from the cone's nappe
+        let p = tuples::POINT_ORIGIN;
+        let upper = cone_truncated(0.0, 1.0, false);
+        let n = local_normal_at(&upper, &p);
+        assert!(n.is_equal_to(&tuples::vector(0.0, -1.0, 0.0)));
+        let lower = cone_truncated(-1.0, 0.0, false);
+        let n = local_normal_at(&lower, &p);
+        assert!(n.is_equal_to(&tuples::vector(0.0, 1.0, 0.0)));
+        let n = cone().normal_at(&p);
+        assert!(n.is_equal_to(&tuples::vector(0.0, -1.0, 0.0)));
+    }
+
+    #[test]
+    fn test_bounding_box_of_a_cone() {
+        //An unbounded and a bounded cone have a bounding box
+        let b = cone().bounds_of();
+        assert!(b.min.x.is_infinite() && b.max.y.is_infinite());
+        let b = cone_truncated(-5.0, 3.0, false).bounds_of();
+        assert!(b.min.is_equal_to(&tuples::point(-5.0, -5.0, -5.0)));
+        assert!(b.max.is_equal_to(&tuples::point(5.0, 3.0, 5.0)));
+    }
+
+    #[test]
+    fn test_normal_vector_on_a_cones_end_caps() {
+        //The normal vector on a cone's end caps
+        let shape = cone_truncated(-1.0, 2.0, true);
+        let tests: [[[f64; 3]; 2]; 3] = [
+            [[0.5, -1.0, 0.0], [0.0, -1.0, 0.0]],
+            [[1.5, 2.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.0, 2.0, -1.0], [0.0, 1.0, 0.0]],
+        ];
+        for test in tests.iter() {
+            let p = tuples::point(test[0][0], test[0][1], test[0][2]);
+            let n = local_normal_at(&shape, &p);
+            let expected = tuples::vector(test[1][0], test[1][1], test[1][2]);
+            assert!(n.is_equal_to(&expected));
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::intersections;
+use crate::rays;
+use crate::shapes;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CsgOperation {
+    Difference,
+    Intersection,
+    Union,
+}
+
+pub fn csg(operation: CsgOperation, left: shapes::Shape, right: shapes::Shape) -> shapes::Shape {
+    let mut s = shapes::shape(shapes::ShapeType::Csg);
+    s.operation = Some(operation);
+    s.add_child(left);
+    s.add_child(right);
+    s
+}
+
+pub fn intersection_allowed(op: CsgOperation, lhit: bool, inl: bool, inr: bool) -> bool {
+    match op {
+        CsgOperation::Union => (lhit && !inr) || (!lhit && !inl),
+        CsgOperation::Intersection => (lhit && inr) || (!lhit && inl),
+        CsgOperation::Difference => (lhit && !inr) || (!lhit && inl),
+    }
+}
+
+fn includes(s: &shapes::Shape, id: &str) -> bool {
+    s.id == id || s.children().iter().any(|c| includes(c, id))
+}
+
+pub fn filter_intersections(
+    s: &shapes::Shape,
+    xs: Vec<intersections::Intersection>,
+) -> Vec<intersections::Intersection> {
+    let op = match s.operation {
+        Some(op) => op,
+        None => return xs,
+    };
+    //begin outside of both children
+    let mut inl = false;
+    let mut inr = false;
+    let mut result: Vec<intersections::Intersection> = vec![];
+    for i in xs {
+        let lhit = includes(&s.children()[0], &i.object.id);
+        if intersection_allowed(op, lhit, inl, inr) {
+            result.push(i);
+        }
+        if lhit {
+            inl = !inl;
+        } else {
+            inr = !inr;
+        }
+    }
+    result
+}
+
+pub fn local_intersect(
+    s: &shapes::Shape,
+    local_r: &rays::Ray,
+) -> Result<Vec<intersections::Intersection>, String> {
+    if let Some(b) = s.cached_bounds() {
+        if !b.intersects(local_r) {
+            return Err("No intersections".to_string());
+        }
+    }
+    let mut xs: Vec<intersections::Intersection> = vec![];
+    for child in s.children().iter() {
+        if let Ok(child_xs) = child.intersect(local_r) {
+            for mut x in child_xs {
+                x.object.add_parent_inverse(s.transform_inverse());
+                //outer csgs are reached later, so the outermost one is kept
+                x.csg_id = Some(s.id.clone());
+                x.outermost_id = Some(s.id.clone());
+                xs.push(x);
+            }
+        }
+    }
+    let filtered = filter_intersections(s, intersections::intersection_list(xs));
+    if filtered.is_empty() {
+        Err("No intersections".to_string())
+    } else {
+        Ok(filtered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cubes;
+    use crate::materials;
+    use crate::spheres;
+    use crate::transformations;
+    use crate::tuples;
+
+    #[test]
+    fn test_csg_is_created_with_an_operation_and_two_shapes() {
+        //CSG is created with an operation and two shapes
+        let s1 = spheres::sphere();
+        let s2 = cubes::cube();
+        let (id1, id2) = (s1.id.clone(), s2.id.clone());
+        let c = csg(CsgOperation::Union, s1, s2);
+        assert_eq!(c.operation, Some(CsgOperation::Union));
+        assert_eq!(c.children()[0].id, id1);
+        assert_eq!(c.children()[1].id, id2);
+    }
+
+    #[test]
+    fn test_evaluating_the_rule_for_a_csg_operation() {
+        //Evaluating the rule for a CSG operation
+        let u = CsgOperation::Union;
+        let i = CsgOperation::Intersection;
+        let d = CsgOperation::Difference;
+        let tests: [(CsgOperation, bool, bool, bool, bool); 24] = [
+            (u, true, true, true, false),
+            (u, true, true, false, true),
+            (u, true, false, true, false),
+            (u, true, false, false, true),
+            (u, false, true, true, false),
+            (u, false, true, false, false),
+            (u, false, false, true, true),
+            (u, false, false, false, true),
+            (i, true, true, true, true),
+            (i, true, true, false, false),
+            (i, true, false, true, true),
+            (i, true, false, false, false),
+            (i, false, true, true, true),
+            (i, false, true, false, true),
+            (i, false, false, true, false),
+            (i, false, false, false, false),
+            (d, true, true, true, false),
+            (d, true, true, false, true),
+            (d, true, false, true, false),
+            (d, true, false, false, true),
+            (d, false, true, true, true),
+            (d, false, true, false, true),
+            (d, false, false, true, false),
+            (d, false, false, false, false),
+        ];
+        for (op, lhit, inl, inr, result) in tests.iter() {
+            assert_eq!(intersection_allowed(*op, *lhit, *inl, *inr), *result);
+        }
+    }
+
+    #[test]
+    fn test_filtering_a_list_of_intersections() {
+        //Filtering a list of intersections
+        let tests: [(CsgOperation, usize, usize); 3] = [
+            (CsgOperation::Union, 0, 3),
+            (CsgOperation::Intersection, 1, 2),
+            (CsgOperation::Difference, 0, 1),
+        ];
+        for (op, x0, x1) in tests.iter() {
+            let c = csg(*op, spheres::sphere(), cubes::cube());
+            let s1 = c.children()[0].clone();
+            let s2 = c.children()[1].clone();
+            let xs = intersections::intersection_list(vec![
+                intersections::intersection(1.0, s1.clone()),
+                intersections::intersection(2.0, s2.clone()),
+                intersections::intersection(3.0, s1),
+                intersections::intersection(4.0, s2),
+            ]);
+            let result = filter_intersections(&c, xs.clone());
+            assert_eq!(result.len(), 2);
+            assert!(result[0].is_equal_to(&xs[*x0]));
+            assert!(result[1].is_equal_to(&xs[*x1]));
+        }
+    }
+
+    #[test]
+    fn test_a_ray_misses_a_csg_object() {
+        //A ray misses a CSG object
+        let c = csg(CsgOperation::Union, spheres::sphere(), spheres::sphere());
+        let r = rays::ray(tuples::point(0.0, 2.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
+        match local_intersect(&c, &r) {
+            Err(e) => assert_eq!(e, "No intersections"),
+            Ok(_xs) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_a_ray_hits_a_csg_object() {
+        //A ray hits a CSG object
+        let s1 = spheres::sphere();
+        let mut s2 = spheres::sphere();
+        s2.set_transform(transformations::matrix4_translation(0.0, 0.0, 0.5));
+        let (id1, id2) = (s1.id.clone(), s2.id.clone());
+        let c = csg(CsgOperation::Union, s1, s2);
+        let r = rays::ray(tuples::point(0.0, 0.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
+        match local_intersect(&c, &r) {
+            Ok(xs) => {
+                assert_eq!(xs.len(), 2);
+                assert!(tuples::get_bool_numbers_are_equal(&xs[0].t, &4.0));
+                assert_eq!(xs[0].object.id, id1);
+                assert!(tuples::get_bool_numbers_are_equal(&xs[1].t, &6.5));
+                assert_eq!(xs[1].object.id, id2);
+            }
+            Err(_) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_refraction_sees_only_the_surface_of_a_csg_union() {
+        //Glass spheres unioned into one lens leave through the outer surface into air
+        let mut glass = materials::MATERIAL_DEFAULT;
+        glass.transparency = 1.0;
+        glass.refractive_index = 1.5;
+        let mut s1 = spheres::sphere();
+        s1.material = glass.clone();
+        let mut s2 = spheres::sphere();
+        s2.material = glass;
+        s2.set_transform(transformations::matrix4_translation(0.0, 0.0, 0.5));
+        let c = csg(CsgOperation::Union, s1, s2);
+        let r = rays::ray(tuples::point(0.0, 0.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
+        let xs = c.intersect(&r).unwrap();
+        let entering = xs[0].prepare_computations(&r, &Some(xs.clone()));
+        assert_eq!(entering.n1, materials::REFRACTIVE_INDEX_VACUUM);
+        assert_eq!(entering.n2, 1.5);
+        let leaving = xs[1].prepare_computations(&r, &Some(xs.clone()));
+        assert_eq!(leaving.n1, 1.5);
+        assert_eq!(leaving.n2, materials::REFRACTIVE_INDEX_VACUUM);
+    }
+}
@@ -0,0 +1,284 @@
+use crate::bounds;
+use crate::intersections;
+use crate::rays;
+use crate::shapes;
+use crate::tuples;
+
+pub fn cylinder() -> shapes::Shape {
+    shapes::shape(shapes::ShapeType::Cylinder)
+}
+
+pub fn cylinder_truncated(minimum: f64, maximum: f64, closed: bool) -> shapes::Shape {
+    let mut s = cylinder();
+    s.minimum = minimum;
+    s.maximum = maximum;
+    s.closed = closed;
+    s
+}
+
+pub fn local_intersect(
+    s: &shapes::Shape,
+    local_r: &rays::Ray,
+) -> Result<Vec<intersections::Intersection>, String> {
+    let mut xs: Vec<intersections::Intersection> = vec![];
+    let a = local_r.direction.x.powi(2) + local_r.direction.z.powi(2);
+    //ray is parallel to the y axis, so can only hit the caps
+    if a.abs() >= tuples::EPSILON {
+        let b = 2.0 * local_r.origin.x * local_r.direction.x
+            + 2.0 * local_r.origin.z * local_r.direction.z;
+        let c = local_r.origin.x.powi(2) + local_r.origin.z.powi(2) - 1.0;
+        let disc = b.powi(2) - 4.0 * a * c;
+        if disc < 0.0 {
+            return Err("No intersections".to_string());
+        }
+        let mut t0 = (-b - disc.sqrt()) / (2.0 * a);
+        let mut t1 = (-b + disc.sqrt()) / (2.0 * a);
+        if t0 > t1 {
+            std::mem::swap(&mut t0, &mut t1);
+        }
+        for t in [t0, t1].iter() {
+            let y = local_r.origin.y + t * local_r.direction.y;
+            if s.minimum < y && y < s.maximum {
+                xs.push(intersections::intersection(*t, s.clone()));
+            }
+        }
+    }
+    intersect_caps(s, local_r, &mut xs);
+    if xs.is_empty() {
+        Err("No intersections".to_string())
+    } else {
+        Ok(intersections::intersection_list(xs))
+    }
+}
+
+pub fn check_cap(local_r: &rays::Ray, t: f64, radius: f64) -> bool {
+    //is the intersection at t within the radius of the cap from the y axis
+    let x = local_r.origin.x + t * local_r.direction.x;
+    let z = local_r.origin.z + t * local_r.direction.z;
+    x.powi(2) + z.powi(2) <= radius.powi(2)
+}
+
+fn intersect_caps(
+    s: &shapes::Shape,
+    local_r: &rays::Ray,
+    xs: &mut Vec<intersections::Intersection>,
+) {
+    if !s.closed || local_r.direction.y.abs() < tuples::EPSILON {
+        return;
+    }
+    let t_lower = (s.minimum - local_r.origin.y) / local_r.direction.y;
+    if check_cap(local_r, t_lower, 1.0) {
+        xs.push(intersections::intersection(t_lower, s.clone()));
+    }
+    let t_upper = (s.maximum - local_r.origin.y) / local_r.direction.y;
+    if check_cap(local_r, t_upper, 1.0) {
+        xs.push(intersections::intersection(t_upper, s.clone()));
+    }
+}
+
+pub fn local_bounds(s: &shapes::Shape) -> bounds::BoundingBox {
+    bounds::bounding_box(
+        tuples::point(-1.0, s.minimum, -1.0),
+        tuples::point(1.0, s.maximum, 1.0),
+    )
+}
+
+pub fn local_normal_at(s: &shapes::Shape, local_point: &tuples::Point) -> tuples::Vector {
+    let dist = local_point.x.powi(2) + local_point.z.powi(2);
+    if dist < 1.0 && local_point.y >= s.maximum - tuples::EPSILON {
+        tuples::vector(0.0, 1.0, 0.0)
+    } else if dist < 1.0 && local_point.y <= s.minimum + tuples::EPSILON {
+        tuples::vector(0.0, -1.0, 0.0)
+    } else {
+        tuples::vector(local_point.x, 0.0, local_point.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_ray(origin: [f64; 3], direction: [f64; 3]) -> rays::Ray {
+        rays::ray(
+            tuples::point(origin[0], origin[1], origin[2]),
+            tuples::vector(direction[0], direction[1], direction[2]).normalize(),
+        )
+    }
+
+    #[test]
+    fn test_ray_misses_a_cylinder() {
+        //A ray misses a cylinder
+        let cyl = cylinder();
+        let test_rays: [[[f64; 3]; 2]; 3] = [
+            [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.0, 0.0, -5.0], [1.0, 1.0, 1.0]],
+        ];
+        for test in test_rays.iter() {
+            let r = local_ray(test[0], test[1]);
+            match local_intersect(&cyl, &r) {
+                Err(e) => assert_eq!(e, "No intersections"),
+                Ok(_xs) => {
+                    println!("Not possible in this test");
+                    assert_eq!(false, true);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ray_strikes_a_cylinder() {
+        //A ray strikes a cylinder
+        let cyl = cylinder();
+        let test_rays: [[[f64; 3]; 3]; 3] = [
+            [[1.0, 0.0, -5.0], [0.0, 0.0, 1.0], [5.0, 5.0, 0.0]],
+            [[0.0, 0.0, -5.0], [0.0, 0.0, 1.0], [4.0, 6.0, 0.0]],
+            [[0.5, 0.0, -5.0], [0.1, 1.0, 1.0], [6.80798, 7.08872, 0.0]],
+        ];
+        for test in test_rays.iter() {
+            let r = local_ray(test[0], test[1]);
+            match local_intersect(&cyl, &r) {
+                Ok(xs) => {
+                    assert_eq!(xs.len(), 2);
+                    assert!(tuples::get_bool_numbers_are_equal(&xs[0].t, &test[2][0]));
+                    assert!(tuples::get_bool_numbers_are_equal(&xs[1].t, &test[2][1]));
+                }
+                Err(_) => {
+                    println!("Not possible in this test");
+                    assert_eq!(false, true);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_normal_vector_on_a_cylinder() {
+        //Normal vector on a cylinder
+        let cyl = cylinder();
+        let tests: [[[f64; 3]; 2]; 4] = [
+            [[1.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+            [[0.0, 5.0, -1.0], [0.0, 0.0, -1.0]],
+            [[0.0, -2.0, 1.0], [0.0, 0.0, 1.0]],
+            [[-1.0, 1.0, 0.0], [-1.0, 0.0, 0.0]],
+        ];
+        for test in tests.iter() {
+            let p = tuples::point(test[0][0], test[0][1], test[0][2]);
+            let n = local_normal_at(&cyl, &p);
+            let expected = tuples::vector(test[1][0], test[1][1], test[1][2]);
+            assert!(n.is_equal_to(&expected));
+        }
+    }
+
+    #[test]
+    fn test_default_minimum_and_maximum_for_a_cylinder() {
+        //The default minimum and maximum for a cylinder
+        let cyl = cylinder();
+        assert_eq!(cyl.minimum, -f64::INFINITY);
+        assert_eq!(cyl.maximum, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_intersecting_a_constrained_cylinder() {
+        //Intersecting a constrained cylinder
+        let cyl = cylinder_truncated(1.0, 2.0, false);
+        let tests: [([f64; 3], [f64; 3], usize); 6] = [
+            ([0.0, 1.5, 0.0], [0.1, 1.0, 0.0], 0),
+            ([0.0, 3.0, -5.0], [0.0, 0.0, 1.0], 0),
+            ([0.0, 0.0, -5.0], [0.0, 0.0, 1.0], 0),
+            ([0.0, 2.0, -5.0], [0.0, 0.0, 1.0], 0),
+            ([0.0, 1.0, -5.0], [0.0, 0.0, 1.0], 0),
+            ([0.0, 1.5, -2.0], [0.0, 0.0, 1.0], 2),
+        ];
+        for (origin, direction, count) in tests.iter() {
+            let r = local_ray(*origin, *direction);
+            let found = match local_intersect(&cyl, &r) {
+                Ok(xs) => xs.len(),
+                Err(_) => 0,
+            };
+            assert_eq!(found, *count);
+        }
+    }
+
+    #[test]
+    fn test_default_closed_value_for_a_cylinder() {
+        //The default closed value for a cylinder
+        let cyl = cylinder();
+        assert!(!cyl.closed);
+    }
+
+    #[test]
+    fn test_intersecting_the_caps_of_a_closed_cylinder() {
+        //Intersecting the caps of a closed cylinder
+        let cyl = cylinder_truncated(1.0, 2.0, true);
+        let tests: [([f64; 3], [f64; 3], usize); 5] = [
+            ([0.0, 3.0, 0.0], [0.0, -1.0, 0.0], 2),
+            ([0.0, 3.0, -2.0], [0.0, -1.0, 2.0], 2),
+            ([0.0, 4.0, -2.0], [0.0, -1.0, 1.0], 2),
+            ([0.0, 0.0, -2.0], [0.0, 1.0, 2.0], 2),
+            ([0.0, -1.0, -2.0], [0.0, 1.0, 1.0], 2),
+        ];
+        for (origin, direction, count) in tests.iter() {
+            let r = local_ray(*origin, *direction);
+            let found = match local_intersect(&cyl, &r) {
+                Ok(xs) => xs.len(),
+                Err(_) => 0,
+            };
+            assert_eq!(found, *count);
+        }
+    }
+
+    #[test]
+    fn test_normal_vector_on_a_cylinders_end_caps() {
+        //The normal vector on a cylinder's end caps
+        let cyl = cylinder_truncated(1.0, 2.0, true);
+        let tests: [[[f64; 3]; 2]; 6] = [
+            [[0.0, 1.0, 0.0], [0.0, -1.0, 0.0]],
+            [[0.5, 1.0, 0.0], [0.0, -1.0, 0.0]],
+            [[0.0, 1.0, 0.5], [0.0, -1.0, 0.0]],
+            [[0.0, 2.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.5, 2.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.0, 2.0, 0.5], [0.0, 1.0, 0.0]],
+        ];
+        for test in tests.iter() {
+            let p = tuples::point(test[0][0], test[0][1], test[0][2]);
+            let n = local_normal_at(&cyl, &p);
+            let expected = tuples::vector(test[1][0], test[1][1], test[1][2]);
+            assert!(n.is_equal_to(&expected));
+        }
+    }
+
+    #[test]
+    fn test_bounding_box_of_a_cylinder() {
+        //An unbounded and a bounded cylinder have a bounding box
+        let b = cylinder().bounds_of();
+        assert_eq!(b.min.y, -f64::INFINITY);
+        assert_eq!(b.max.y, f64::INFINITY);
+        let b = cylinder_truncated(-5.0, 3.0, false).bounds_of();
+        assert!(b.min.is_equal_to(&tuples::point(-1.0, -5.0, -1.0)));
+        assert!(b.max.is_equal_to(&tuples::point(1.0, 3.0, 1.0)));
+    }
+
+    #[test]
+    fn test_intersecting_a_transformed_closed_cylinder() {
+        //Intersecting a scaled, closed cylinder through its world transform
+        let mut cyl = cylinder_truncated(0.0, 1.0, true);
+        cyl.set_transform(crate::transformations::matrix4_scaling(2.0, 3.0, 2.0));
+        let r = rays::ray(
+            tuples::point(0.0, 10.0, 0.0),
+            tuples::vector(0.0, -1.0, 0.0),
+        );
+        match cyl.intersect(&r) {
+            Ok(xs) => {
+                assert_eq!(xs.len(), 2);
+                assert!(tuples::get_bool_numbers_are_equal(&xs[0].t, &7.0));
+                assert!(tuples::get_bool_numbers_are_equal(&xs[1].t, &10.0));
+                let n = cyl.normal_at(&r.position(xs[0].t));
+                assert!(n.is_equal_to(&tuples::vector(0.0, 1.0, 0.0)));
+            }
+            Err(_) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+}
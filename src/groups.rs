@@ -0,0 +1,469 @@
+use crate::bounds;
+use crate::intersections;
+use crate::rays;
+use crate::shapes;
+
+//groups with at least this many children are split when building a bvh
+pub const BVH_THRESHOLD: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BvhStats {
+    pub nodes: usize,
+    pub leaves: usize,
+    pub shapes: usize,
+    pub min_leaf_size: usize,
+    pub max_leaf_size: usize,
+    pub depth: usize,
+}
+
+impl BvhStats {
+    pub fn report(&self) -> String {
+        let average = if self.leaves == 0 {
+            0.0
+        } else {
+            self.shapes as f64 / self.leaves as f64
+        };
+        format!(
+            "BVH: {} nodes, {} leaves, leaf sizes {}-{} (average {:.1}), depth {}",
+            self.nodes, self.leaves, self.min_leaf_size, self.max_leaf_size, average, self.depth
+        )
+    }
+}
+
+pub fn group() -> shapes::Shape {
+    shapes::shape(shapes::ShapeType::Group)
+}
+
+pub fn group_of(children: Vec<shapes::Shape>) -> shapes::Shape {
+    let mut g = group();
+    for child in children {
+        g.add_child(child);
+    }
+    g
+}
+
+//a group the bvh adds to split up another group's children
+pub fn partition_of(children: Vec<shapes::Shape>) -> shapes::Shape {
+    let mut g = group_of(children);
+    g.partition = true;
+    g
+}
+
+pub fn local_intersect(
+    s: &shapes::Shape,
+    local_r: &rays::Ray,
+) -> Result<Vec<intersections::Intersection>, String> {
+    if let Some(b) = s.cached_bounds() {
+        if !b.intersects(local_r) {
+            return Err("No intersections".to_string());
+        }
+    }
+    let mut xs: Vec<intersections::Intersection> = vec![];
+    for child in s.children().iter() {
+        if let Ok(child_xs) = child.intersect(local_r) {
+            for mut x in child_xs {
+                //outer groups are reached later, so the chain builds up innermost first
+                x.object.add_parent_inverse(s.transform_inverse());
+                if !s.partition {
+                    x.outermost_id = Some(s.id.clone());
+                }
+                xs.push(x);
+            }
+        }
+    }
+    if xs.is_empty() {
+        Err("No intersections".to_string())
+    } else {
+        Ok(intersections::intersection_list(xs))
+    }
+}
+
+pub fn local_bounds(s: &shapes::Shape) -> bounds::BoundingBox {
+    let mut b = bounds::bounding_box_empty();
+    for child in s.children().iter() {
+        b.add_box(&child.parent_space_bounds_of());
+    }
+    b
+}
+
+pub fn partition_children(s: &mut shapes::Shape) -> (Vec<shapes::Shape>, Vec<shapes::Shape>) {
+    //children that fit wholly in one half of the group's finite bounds are moved out
+    let mut b = bounds::bounding_box_empty();
+    for child in s.children().iter() {
+        let cb = child.parent_space_bounds_of();
+        if cb.is_finite() {
+            b.add_box(&cb);
+        }
+    }
+    let (left_box, right_box) = b.split();
+    let mut left: Vec<shapes::Shape> = vec![];
+    let mut right: Vec<shapes::Shape> = vec![];
+    let mut remaining: Vec<shapes::Shape> = vec![];
+    for child in std::mem::take(s.children_mut()) {
+        let cb = child.parent_space_bounds_of();
+        if left_box.contains_box(&cb) {
+            left.push(child);
+        } else if right_box.contains_box(&cb) {
+            right.push(child);
+        } else {
+            remaining.push(child);
+        }
+    }
+    *s.children_mut() = remaining;
+    (left, right)
+}
+
+pub fn divide(s: &mut shapes::Shape, threshold: usize) {
+    if s.children().len() >= threshold {
+        let total = s.children().len();
+        let (left, right) = partition_children(s);
+        if left.len() == total || right.len() == total {
+            //everything landed on one side, so splitting again would never end
+            *s.children_mut() = if left.len() == total { left } else { right };
+        } else {
+            for side in [left, right] {
+                if !side.is_empty() {
+                    s.add_child(partition_of(side));
+                }
+            }
+        }
+    }
+    for child in s.children_mut().iter_mut() {
+        child.divide(threshold);
+    }
+}
+
+pub fn bvh_stats(objects: &[shapes::Shape]) -> BvhStats {
+    let mut stats = BvhStats {
+        nodes: 0,
+        leaves: 0,
+        shapes: 0,
+        min_leaf_size: 0,
+        max_leaf_size: 0,
+        depth: 0,
+    };
+    for object in objects.iter() {
+        add_stats(object, 1, &mut stats);
+    }
+    stats
+}
+
+fn add_stats(s: &shapes::Shape, depth: usize, stats: &mut BvhStats) {
+    match s.shape_type {
+        shapes::ShapeType::Group => (),
+        shapes::ShapeType::Csg => {
+            for child in s.children().iter() {
+                add_stats(child, depth, stats);
+            }
+            return;
+        }
+        _ => return,
+    }
+    stats.nodes += 1;
+    stats.depth = stats.depth.max(depth);
+    let mut leaf_size = 0;
+    for child in s.children().iter() {
+        match child.shape_type {
+            shapes::ShapeType::Group | shapes::ShapeType::Csg => add_stats(child, depth + 1, stats),
+            _ => leaf_size += 1,
+        }
+    }
+    if leaf_size > 0 {
+        stats.min_leaf_size = if stats.leaves == 0 {
+            leaf_size
+        } else {
+            stats.min_leaf_size.min(leaf_size)
+        };
+        stats.max_leaf_size = stats.max_leaf_size.max(leaf_size);
+        stats.leaves += 1;
+        stats.shapes += leaf_size;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials;
+    use crate::matrices;
+    use crate::spheres;
+    use crate::transformations;
+    use crate::tuples;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_creating_a_new_group() {
+        //Creating a new group
+        let g = group();
+        assert!(g.transform().is_equal_to(&matrices::IDENTITY_MATRIX));
+        assert_eq!(g.children().len(), 0);
+    }
+
+    #[test]
+    fn test_adding_a_child_to_a_group() {
+        //Adding a child to a group
+        let mut g = group();
+        let s = shapes::shape(shapes::ShapeType::ShapeTest);
+        let id = s.id.clone();
+        g.add_child(s);
+        assert_eq!(g.children().len(), 1);
+        assert_eq!(g.children()[0].id, id);
+    }
+
+    #[test]
+    fn test_intersecting_a_ray_with_an_empty_group() {
+        //Intersecting a ray with an empty group
+        let g = group();
+        let r = rays::ray(tuples::point(0.0, 0.0, 0.0), tuples::vector(0.0, 0.0, 1.0));
+        match local_intersect(&g, &r) {
+            Err(e) => assert_eq!(e, "No intersections"),
+            Ok(_xs) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_intersecting_a_ray_with_a_nonempty_group() {
+        //Intersecting a ray with a nonempty group
+        let s1 = spheres::sphere();
+        let mut s2 = spheres::sphere();
+        s2.set_transform(transformations::matrix4_translation(0.0, 0.0, -3.0));
+        let mut s3 = spheres::sphere();
+        s3.set_transform(transformations::matrix4_translation(5.0, 0.0, 0.0));
+        let (id1, id2) = (s1.id.clone(), s2.id.clone());
+        let g = group_of(vec![s1, s2, s3]);
+        let r = rays::ray(tuples::point(0.0, 0.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
+        match local_intersect(&g, &r) {
+            Ok(xs) => {
+                assert_eq!(xs.len(), 4);
+                assert_eq!(xs[0].object.id, id2);
+                assert_eq!(xs[1].object.id, id2);
+                assert_eq!(xs[2].object.id, id1);
+                assert_eq!(xs[3].object.id, id1);
+            }
+            Err(_) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_intersecting_a_transformed_group() {
+        //Intersecting a transformed group
+        let mut s = spheres::sphere();
+        s.set_transform(transformations::matrix4_translation(5.0, 0.0, 0.0));
+        let mut g = group_of(vec![s]);
+        g.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
+        let r = rays::ray(
+            tuples::point(10.0, 0.0, -10.0),
+            tuples::vector(0.0, 0.0, 1.0),
+        );
+        match g.intersect(&r) {
+            Ok(xs) => assert_eq!(xs.len(), 2),
+            Err(_) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_intersections_record_the_parent_chain() {
+        //A hit on a nested child carries the transforms of its groups, innermost first
+        let mut s = spheres::sphere();
+        s.set_transform(transformations::matrix4_translation(5.0, 0.0, 0.0));
+        let mut g2 = group_of(vec![s]);
+        g2.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
+        let mut g1 = group_of(vec![g2]);
+        g1.set_transform(transformations::matrix4_rotation_y_rad(PI / 2.0));
+        let r = rays::ray(
+            tuples::point(-20.0, 0.0, -10.0),
+            tuples::vector(1.0, 0.0, 0.0),
+        );
+        match g1.intersect(&r) {
+            Ok(xs) => {
+                assert_eq!(xs.len(), 2);
+                let chain = xs[0].object.parent_inverses();
+                assert_eq!(chain.len(), 2);
+                assert!(chain[0].is_equal_to(g1.children()[0].transform_inverse()));
+                assert!(chain[1].is_equal_to(g1.transform_inverse()));
+                let p = xs[0].object.world_to_object(&r.position(xs[0].t));
+                assert!(p.is_equal_to(&tuples::point(0.0, 0.0, -1.0)));
+            }
+            Err(_) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_setting_a_groups_material_propagates_to_children() {
+        //Setting a group's material sets it on every descendant
+        let inner = group_of(vec![spheres::sphere()]);
+        let mut g = group_of(vec![inner, spheres::sphere()]);
+        let mut m = materials::MATERIAL_DEFAULT;
+        m.color = tuples::color(1.0, 0.0, 0.0);
+        g.set_material(m.clone());
+        assert!(g.children()[0].children()[0]
+            .material
+            .color
+            .is_equal_to(&m.color));
+        assert!(g.children()[1].material.color.is_equal_to(&m.color));
+    }
+
+    #[test]
+    fn test_a_group_has_a_bounding_box_that_contains_its_children() {
+        //A group has a bounding box that contains its children
+        let mut s = spheres::sphere();
+        s.set_transform(transformations::matrix4_transform_chain(&vec![
+            transformations::matrix4_scaling(2.0, 2.0, 2.0),
+            transformations::matrix4_translation(2.0, 5.0, -3.0),
+        ]));
+        let mut c = crate::cylinders::cylinder_truncated(-2.0, 2.0, false);
+        c.set_transform(transformations::matrix4_transform_chain(&vec![
+            transformations::matrix4_scaling(0.5, 1.0, 0.5),
+            transformations::matrix4_translation(-4.0, -1.0, 4.0),
+        ]));
+        let g = group_of(vec![s, c]);
+        let b = g.bounds_of();
+        assert!(b.min.is_equal_to(&tuples::point(-4.5, -3.0, -5.0)));
+        assert!(b.max.is_equal_to(&tuples::point(4.0, 7.0, 4.5)));
+    }
+
+    #[test]
+    fn test_adding_children_caches_the_group_bounds() {
+        //A group keeps its bounds up to date as children are added, without a bvh
+        let mut g = group();
+        let mut s1 = spheres::sphere();
+        s1.set_transform(transformations::matrix4_translation(-2.0, 0.0, 0.0));
+        g.add_child(s1);
+        let b = g.cached_bounds().unwrap();
+        assert!(b.min.is_equal_to(&tuples::point(-3.0, -1.0, -1.0)));
+        assert!(b.max.is_equal_to(&tuples::point(-1.0, 1.0, 1.0)));
+        let mut s2 = spheres::sphere();
+        s2.set_transform(transformations::matrix4_translation(2.0, 0.0, 0.0));
+        g.add_child(s2);
+        let b = g.cached_bounds().unwrap();
+        assert!(b.min.is_equal_to(&tuples::point(-3.0, -1.0, -1.0)));
+        assert!(b.max.is_equal_to(&tuples::point(3.0, 1.0, 1.0)));
+        //so a ray that passes above both spheres is culled by the box
+        let r = rays::ray(tuples::point(0.0, 5.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
+        assert!(!b.intersects(&r));
+        assert!(g.intersect(&r).is_err());
+    }
+
+    #[test]
+    fn test_changing_a_child_does_not_leave_the_group_bounds_stale() {
+        //Moving a child after it is added drops the cached bounds, so rays
+        //toward where it went still find it until divide works them out again
+        let mut g = group();
+        g.add_child(spheres::sphere());
+        let r = rays::ray(tuples::point(0.0, 5.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
+        assert!(g.intersect(&r).is_err());
+        g.children_mut()[0].set_transform(transformations::matrix4_translation(0.0, 5.0, 0.0));
+        assert!(g.cached_bounds().is_none());
+        assert_eq!(g.intersect(&r).unwrap().len(), 2);
+        g.divide(BVH_THRESHOLD);
+        let b = g.cached_bounds().unwrap();
+        assert!(b.min.is_equal_to(&tuples::point(-1.0, 4.0, -1.0)));
+        assert!(b.max.is_equal_to(&tuples::point(1.0, 6.0, 1.0)));
+        assert_eq!(g.intersect(&r).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_partitioning_a_groups_children() {
+        //Partitioning a group's children
+        let mut s1 = spheres::sphere();
+        s1.set_transform(transformations::matrix4_translation(-2.0, 0.0, 0.0));
+        let mut s2 = spheres::sphere();
+        s2.set_transform(transformations::matrix4_translation(2.0, 0.0, 0.0));
+        let s3 = spheres::sphere();
+        let (id1, id2, id3) = (s1.id.clone(), s2.id.clone(), s3.id.clone());
+        let mut g = group_of(vec![s1, s2, s3]);
+        let (left, right) = partition_children(&mut g);
+        assert_eq!(g.children().len(), 1);
+        assert_eq!(g.children()[0].id, id3);
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].id, id1);
+        assert_eq!(right.len(), 1);
+        assert_eq!(right[0].id, id2);
+    }
+
+    #[test]
+    fn test_subdividing_a_group_partitions_its_children() {
+        //Subdividing a group partitions its children
+        let mut s1 = spheres::sphere();
+        s1.set_transform(transformations::matrix4_translation(-2.0, -2.0, 0.0));
+        let mut s2 = spheres::sphere();
+        s2.set_transform(transformations::matrix4_translation(-2.0, 2.0, 0.0));
+        let mut s3 = spheres::sphere();
+        s3.set_transform(transformations::matrix4_scaling(4.0, 4.0, 4.0));
+        let (id1, id2, id3) = (s1.id.clone(), s2.id.clone(), s3.id.clone());
+        let mut g = group_of(vec![s1, s2, s3]);
+        g.divide(1);
+        assert_eq!(g.children()[0].id, id3);
+        let subgroup = &g.children()[1];
+        assert_eq!(subgroup.children().len(), 2);
+        assert_eq!(subgroup.children()[0].children()[0].id, id1);
+        assert_eq!(subgroup.children()[1].children()[0].id, id2);
+        assert!(g.cached_bounds().is_some());
+    }
+
+    #[test]
+    fn test_subdividing_a_group_with_too_few_children() {
+        //Subdividing a group with too few children
+        let mut s1 = spheres::sphere();
+        s1.set_transform(transformations::matrix4_translation(-2.0, 0.0, 0.0));
+        let mut s2 = spheres::sphere();
+        s2.set_transform(transformations::matrix4_translation(2.0, 1.0, 0.0));
+        let mut s3 = spheres::sphere();
+        s3.set_transform(transformations::matrix4_translation(2.0, -1.0, 0.0));
+        let subgroup = group_of(vec![s1, s2, s3]);
+        let s4 = spheres::sphere();
+        let mut g = group_of(vec![subgroup, s4]);
+        g.divide(3);
+        assert_eq!(g.children().len(), 2);
+        let sub = &g.children()[0];
+        assert_eq!(sub.children().len(), 2);
+        assert_eq!(sub.children()[0].children().len(), 1);
+        assert_eq!(sub.children()[1].children().len(), 2);
+    }
+
+    #[test]
+    fn test_a_divided_group_gives_the_same_hits_and_reports_stats() {
+        //Dividing a large group keeps every hit, and the stats describe the hierarchy
+        let mut spheres_list: Vec<shapes::Shape> = vec![];
+        for x in 0..8 {
+            for y in 0..8 {
+                let mut s = spheres::sphere();
+                s.set_transform(transformations::matrix4_transform_chain(&vec![
+                    transformations::matrix4_scaling(0.4, 0.4, 0.4),
+                    transformations::matrix4_translation(x as f64, y as f64, 0.0),
+                ]));
+                spheres_list.push(s);
+            }
+        }
+        let flat = group_of(spheres_list);
+        let mut divided = flat.clone();
+        divided.divide(BVH_THRESHOLD);
+        for (x, y) in [(0.0, 0.0), (3.0, 5.0), (7.2, 6.9), (3.5, 3.5)].iter() {
+            let r = rays::ray(tuples::point(*x, *y, -5.0), tuples::vector(0.0, 0.0, 1.0));
+            let a = flat.intersect(&r).unwrap_or_default();
+            let b = divided.intersect(&r).unwrap_or_default();
+            assert_eq!(a.len(), b.len());
+            for i in 0..a.len() {
+                assert!(a[i].is_equal_to(&b[i]));
+            }
+        }
+        let stats = bvh_stats(&[divided]);
+        assert_eq!(stats.shapes, 64);
+        assert!(stats.max_leaf_size < BVH_THRESHOLD);
+        assert!(stats.depth > 1);
+        let flat_stats = bvh_stats(&[flat]);
+        assert_eq!(flat_stats.nodes, 1);
+        assert_eq!(flat_stats.max_leaf_size, 64);
+    }
+}
@@ -55,9 +55,31 @@ impl Comps {
 pub struct Intersection {
     pub t: f64,
     pub object: shapes::Shape,
+    //where the hit landed on a triangle's face, relative to its vertices
+    pub u: f64,
+    pub v: f64,
+    //the outermost csg the hit belongs to, which refraction treats as one container
+    pub csg_id: Option<String>,
+    //the outermost group or csg the hit belongs to, leaving out the groups the bvh adds
+    pub outermost_id: Option<String>,
 }
 
 impl Intersection {
+    pub fn container_id(&self) -> &str {
+        match &self.csg_id {
+            Some(id) => id,
+            None => &self.object.id,
+        }
+    }
+
+    //shadows count everything with the same id as one object
+    pub fn shadow_id(&self) -> &str {
+        match &self.outermost_id {
+            Some(id) => id,
+            None => &self.object.id,
+        }
+    }
+
     pub fn is_equal_to(&self, i2: &Intersection) -> bool {
         tuples::get_bool_numbers_are_equal(&self.t, &i2.t) && self.object.id == i2.object.id
     }
@@ -72,17 +94,22 @@ impl Intersection {
         comps.object = self.clone().object;
         comps.point = r.position(comps.t);
         comps.eyev = r.direction.multiply(&-1.0);
-        comps.normalv = comps.object.normal_at(&comps.point);
-        comps.reflectv = comps.eyev.multiply(&-1.0).reflect(&comps.normalv);
+        comps.normalv = comps.object.normal_at_hit(&comps.point, self);
+        //offset along the real surface normal, so bumps can't push the point through it
         comps.over_point = comps.point.add(&comps.normalv.multiply(&tuples::EPSILON));
         comps.under_point = comps
             .point
             .subtract(&comps.normalv.multiply(&tuples::EPSILON));
+        if let Some(bump) = &comps.object.material.bump {
+            comps.normalv = bump.perturb_normal(&comps.object, &comps.point, &comps.normalv);
+        }
+        comps.reflectv = comps.eyev.multiply(&-1.0).reflect(&comps.normalv);
         if comps.normalv.dot_product(&comps.eyev) < 0.0 {
             comps.inside = true;
             comps.normalv = comps.normalv.multiply(&-1.0);
         }
-        let mut containers: Vec<shapes::Shape> = Vec::new();
+        //each container is the id it is entered and left by, and the shape that entered it
+        let mut containers: Vec<(String, shapes::Shape)> = Vec::new();
 
         let xs: Vec<Intersection>;
         match xs_option {
@@ -103,19 +130,22 @@ impl Intersection {
                         if containers.len() == 0 {
                             comps.n1 = materials::REFRACTIVE_INDEX_VACUUM;
                         } else {
-                            comps.n1 = containers[containers.len() - 1].material.refractive_index;
+                            comps.n1 = containers[containers.len() - 1].1.material.refractive_index;
                         }
                     }
 
                     let is_object_already_in_container = containers
                         .iter()
-                        .position(|x| x.id == xs[index].clone().object.id);
+                        .position(|x| x.0 == xs[index].container_id());
                     match is_object_already_in_container {
                         Some(existing_object_index) => {
                             containers.remove(existing_object_index);
                         }
                         None => {
-                            containers.push(xs[index].clone().object);
+                            containers.push((
+                                xs[index].container_id().to_string(),
+                                xs[index].clone().object,
+                            ));
                         }
                     }
 
@@ -123,7 +153,7 @@ impl Intersection {
                         if containers.len() == 0 {
                             comps.n2 = materials::REFRACTIVE_INDEX_VACUUM;
                         } else {
-                            comps.n2 = containers[containers.len() - 1].material.refractive_index;
+                            comps.n2 = containers[containers.len() - 1].1.material.refractive_index;
                         }
                         break;
                     }
@@ -140,9 +170,17 @@ impl Intersection {
 }
 
 pub fn intersection(t: f64, object: shapes::Shape) -> Intersection {
+    intersection_with_uv(t, object, 0.0, 0.0)
+}
+
+pub fn intersection_with_uv(t: f64, object: shapes::Shape, u: f64, v: f64) -> Intersection {
     Intersection {
-        t: t,
-        object: object,
+        t,
+        object,
+        u,
+        v,
+        csg_id: None,
+        outermost_id: None,
     }
 }
 
@@ -168,6 +206,7 @@ pub fn hit(xs: &Vec<Intersection>) -> Result<Intersection, &'static str> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bumps;
     use crate::intersections;
     use crate::matrices;
     use crate::planes;
@@ -181,7 +220,7 @@ mod tests {
         let i = intersection(3.5, s);
         assert_eq!(tuples::get_bool_numbers_are_equal(&i.t, &3.5), true);
         assert_eq!(
-            i.object.transform.is_equal_to(&matrices::IDENTITY_MATRIX),
+            i.object.transform().is_equal_to(&matrices::IDENTITY_MATRIX),
             true
         );
         assert_eq!(
@@ -190,6 +229,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_an_intersection_can_encapsulate_u_and_v() {
+        //An intersection can encapsulate u and v
+        let s = crate::triangles::triangle(
+            tuples::point(0.0, 1.0, 0.0),
+            tuples::point(-1.0, 0.0, 0.0),
+            tuples::point(1.0, 0.0, 0.0),
+        );
+        let i = intersection_with_uv(3.5, s, 0.2, 0.4);
+        assert_eq!(i.u, 0.2);
+        assert_eq!(i.v, 0.4);
+    }
+
     #[test]
     fn test_aggregating_intersections() {
         //Aggregating intersections
@@ -324,7 +376,7 @@ mod tests {
         let p = tuples::point(0.0, 0.0, -5.0);
         let d = tuples::vector(0.0, 0.0, 1.0);
         let mut s = spheres::sphere();
-        s.transform = transformations::matrix4_translation(0.0, 0.0, 1.0);
+        s.set_transform(transformations::matrix4_translation(0.0, 0.0, 1.0));
         let i = intersection(5.0, s);
         let r = rays::ray(p, d);
         let comps = i.prepare_computations(&r, &None);
@@ -352,19 +404,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_a_bumped_material_tips_the_normal_before_reflecting() {
+        //A bump changes the normal and reflection vector, but not the offset points
+        let mut s = planes::plane();
+        s.material.bump = Some(bumps::ripple_bump(1.0));
+        let r = rays::ray(tuples::point(0.5, 1.0, 0.0), tuples::vector(0.0, -1.0, 0.0));
+        let i = intersections::intersection(1.0, s);
+        let comps = i.prepare_computations(&r, &None);
+        let half = 2.0_f64.sqrt() / 2.0;
+        assert!(comps.normalv.is_equal_to(&tuples::vector(half, half, 0.0)));
+        assert!(comps.reflectv.is_equal_to(&tuples::vector(1.0, 0.0, 0.0)));
+        assert!(comps
+            .over_point
+            .is_equal_to(&tuples::point(0.5, tuples::EPSILON, 0.0)));
+    }
+
     #[test]
     fn test_finding_n1_and_n2_at_various_intersections() {
         //Finding n1 and n2 at various intersections
         let mut a = spheres::sphere_glass();
-        a.transform = transformations::matrix4_scaling(2.0, 2.0, 2.0);
+        a.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
         a.material.refractive_index = 1.5;
 
         let mut b = spheres::sphere_glass();
-        b.transform = transformations::matrix4_translation(0.0, 0.0, -0.25);
+        b.set_transform(transformations::matrix4_translation(0.0, 0.0, -0.25));
         b.material.refractive_index = 2.0;
 
         let mut c = spheres::sphere_glass();
-        c.transform = transformations::matrix4_translation(0.0, 0.0, 0.25);
+        c.set_transform(transformations::matrix4_translation(0.0, 0.0, 0.25));
         c.material.refractive_index = 2.5;
 
         let r = rays::ray(tuples::point(0.0, 0.0, -4.0), tuples::vector(0.0, 0.0, 1.0));
@@ -401,7 +469,7 @@ mod tests {
     fn test_the_under_point_is_offset_below_the_surface() {
         //The under point is offset below the surface
         let mut s = spheres::sphere_glass();
-        s.transform = transformations::matrix4_translation(0.0, 0.0, 1.0);
+        s.set_transform(transformations::matrix4_translation(0.0, 0.0, 1.0));
         let r = rays::ray(tuples::point(0.0, 0.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
         let i = intersections::intersection(5.0, s);
         let xs = intersection_list(vec![i.clone()]);
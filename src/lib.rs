@@ -1 +1,101 @@
+//the modules carried over from the book chapters keep their original code, so the
+//clippy lints they trip are allowed on each of them rather than crate-wide
+
+pub mod backgrounds;
+pub mod bounds;
+pub mod bumps;
+#[allow(
+    clippy::redundant_field_names,
+    clippy::to_string_in_format_args,
+    clippy::unnecessary_cast
+)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison))]
+pub mod camera;
+#[allow(
+    clippy::chars_last_cmp,
+    clippy::deref_addrof,
+    clippy::len_zero,
+    clippy::manual_clamp,
+    clippy::redundant_field_names,
+    clippy::unnecessary_cast
+)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison))]
+pub mod canvas;
+pub mod cones;
+pub mod csgs;
+#[allow(clippy::legacy_numeric_constants, clippy::needless_late_init)]
+pub mod cubes;
+pub mod cylinders;
+pub mod groups;
+#[allow(
+    clippy::len_zero,
+    clippy::needless_borrow,
+    clippy::needless_late_init,
+    clippy::needless_range_loop,
+    clippy::needless_return,
+    clippy::ptr_arg
+)]
+#[cfg_attr(
+    test,
+    allow(
+        clippy::bool_assert_comparison,
+        clippy::clone_on_copy,
+        clippy::cmp_owned,
+        clippy::op_ref
+    )
+)]
+pub mod intersections;
+#[allow(clippy::needless_borrow, clippy::redundant_field_names)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison))]
+pub mod lights;
+#[allow(clippy::redundant_field_names)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison))]
+pub mod materials;
+#[allow(
+    clippy::assign_op_pattern,
+    clippy::bool_comparison,
+    clippy::collapsible_if,
+    clippy::needless_range_loop,
+    clippy::neg_multiply
+)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison))]
+pub mod matrices;
+pub mod noise;
+pub mod obj_files;
+#[allow(clippy::redundant_field_names)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison))]
+pub mod patterns;
+#[allow(clippy::neg_multiply)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison, clippy::useless_format))]
+pub mod planes;
+#[allow(clippy::redundant_field_names)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison, clippy::deref_addrof))]
+pub mod rays;
+pub mod scenes;
+pub mod sequences;
+#[cfg_attr(test, allow(clippy::approx_constant, clippy::bool_assert_comparison))]
+pub mod shapes;
+#[cfg_attr(
+    test,
+    allow(
+        clippy::approx_constant,
+        clippy::bool_assert_comparison,
+        clippy::useless_format
+    )
+)]
+pub mod spheres;
+#[allow(
+    clippy::needless_borrow,
+    clippy::needless_range_loop,
+    clippy::neg_multiply,
+    clippy::ptr_arg
+)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison))]
+pub mod transformations;
+pub mod triangles;
+#[allow(clippy::needless_borrow, clippy::redundant_field_names)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison))]
 pub mod tuples;
+#[allow(clippy::needless_borrow)]
+#[cfg_attr(test, allow(clippy::bool_assert_comparison, clippy::len_zero))]
+pub mod worlds;
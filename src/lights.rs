@@ -1,71 +1,277 @@
 use std::f64;
 
 use crate::materials;
+use crate::sequences;
 use crate::shapes;
 use crate::tuples;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightType {
+    Area,
+    Directional,
+    Point,
+    Spot,
+}
+
 #[derive(Debug, Copy, Clone)]
-pub struct LightPoint {
+pub struct Light {
+    //for area lights this is the centre of the rectangle
     pub position: tuples::Point,
     pub intensity: tuples::Color,
+    pub light_type: LightType,
+    //area lights are a rectangle from the corner along usteps uvecs and vsteps vvecs,
+    //each of the cells is sampled once
+    pub corner: tuples::Point,
+    pub uvec: tuples::Vector,
+    pub usteps: u32,
+    pub vvec: tuples::Vector,
+    pub vsteps: u32,
+    //samples a random point in each cell instead of its centre
+    pub jitter: bool,
+    //the way a spot light points, or the way a directional light's rays travel
+    pub direction: tuples::Vector,
+    //spot lights are full strength within the inner angle from their direction,
+    //fading smoothly to nothing at the outer angle (both in radians)
+    pub inner_angle: f64,
+    pub outer_angle: f64,
+    //point and spot lights are divided by constant + linear * d + quadratic * d * d
+    //at distance d, the default of 1, 0, 0 doesn't fade and 0, 0, 1 is inverse-square
+    pub constant: f64,
+    pub linear: f64,
+    pub quadratic: f64,
 }
 
-pub fn light_point(position: tuples::Point, intensity: tuples::Color) -> LightPoint {
-    LightPoint {
+impl Light {
+    pub fn point_on_light(
+        &self,
+        u: u32,
+        v: u32,
+        jitter_by: &mut sequences::Sequence,
+    ) -> tuples::Point {
+        self.corner
+            .add(&self.uvec.multiply(&(u as f64 + jitter_by.next_value())))
+            .add(&self.vvec.multiply(&(v as f64 + jitter_by.next_value())))
+    }
+
+    //the positions that shading and shadows are sampled from when lighting point p,
+    //a directional light has no position so shading uses light_vectors instead
+    pub fn sample_positions(&self, p: &tuples::Point) -> Vec<tuples::Point> {
+        match self.light_type {
+            LightType::Point | LightType::Spot | LightType::Directional => vec![self.position],
+            LightType::Area => {
+                let mut jitter_by = self.jitter_for(p);
+                let mut positions = vec![];
+                for v in 0..self.vsteps {
+                    for u in 0..self.usteps {
+                        positions.push(self.point_on_light(u, v, &mut jitter_by));
+                    }
+                }
+                positions
+            }
+        }
+    }
+
+    //the directions from p towards each of the light's samples
+    pub fn light_vectors(&self, p: &tuples::Point) -> Vec<tuples::Vector> {
+        match self.light_type {
+            LightType::Directional => vec![self.direction.negate().normalize()],
+            _ => self
+                .sample_positions(p)
+                .iter()
+                .map(|position| position.subtract(p).normalize())
+                .collect(),
+        }
+    }
+
+    //how much of a spot light's cone reaches p, 1 for every other kind of light
+    pub fn cone_factor(&self, p: &tuples::Point) -> f64 {
+        if self.light_type != LightType::Spot {
+            return 1.0;
+        }
+        let cos_angle = p
+            .subtract(&self.position)
+            .normalize()
+            .dot_product(&self.direction.normalize());
+        let cos_inner = self.inner_angle.cos();
+        let cos_outer = self.outer_angle.cos();
+        if cos_angle >= cos_inner {
+            1.0
+        } else if cos_angle <= cos_outer {
+            0.0
+        } else {
+            //smoothstep between the edges of the cone
+            let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+
+    //how much of a point or spot light is left after travelling to p
+    pub fn attenuation_at(&self, p: &tuples::Point) -> f64 {
+        match self.light_type {
+            LightType::Point | LightType::Spot => {
+                let d = self.position.subtract(p).magnitude();
+                let falloff = self.constant + self.linear * d + self.quadratic * d * d;
+                1.0 / falloff.max(tuples::EPSILON)
+            }
+            LightType::Area | LightType::Directional => 1.0,
+        }
+    }
+
+    pub fn set_inverse_square(&mut self) {
+        self.constant = 0.0;
+        self.linear = 0.0;
+        self.quadratic = 1.0;
+    }
+
+    //seeded by the point being lit so that shading and shadows use the same
+    //samples, and the image doesn't depend on which thread rendered it
+    fn jitter_for(&self, p: &tuples::Point) -> sequences::Sequence {
+        if self.jitter {
+            let seed =
+                p.x.to_bits() ^ p.y.to_bits().rotate_left(21) ^ p.z.to_bits().rotate_left(42);
+            sequences::random_sequence(seed)
+        } else {
+            sequences::sequence(vec![0.5])
+        }
+    }
+}
+
+pub fn light_point(position: tuples::Point, intensity: tuples::Color) -> Light {
+    Light {
         position: position,
         intensity: intensity,
+        light_type: LightType::Point,
+        corner: position,
+        uvec: tuples::VECTOR_NULL,
+        usteps: 1,
+        vvec: tuples::VECTOR_NULL,
+        vsteps: 1,
+        jitter: false,
+        direction: tuples::VECTOR_NULL,
+        inner_angle: 0.0,
+        outer_angle: 0.0,
+        constant: 1.0,
+        linear: 0.0,
+        quadratic: 0.0,
+    }
+}
+
+pub fn spot_light(
+    position: tuples::Point,
+    direction: tuples::Vector,
+    inner_angle: f64,
+    outer_angle: f64,
+    intensity: tuples::Color,
+) -> Light {
+    let mut light = light_point(position, intensity);
+    light.light_type = LightType::Spot;
+    light.direction = direction.normalize();
+    light.inner_angle = inner_angle.min(outer_angle);
+    light.outer_angle = outer_angle;
+    light
+}
+
+//like the sun, so far away that every ray arrives travelling in the same direction
+pub fn directional_light(direction: tuples::Vector, intensity: tuples::Color) -> Light {
+    let mut light = light_point(tuples::POINT_ORIGIN, intensity);
+    light.light_type = LightType::Directional;
+    light.direction = direction.normalize();
+    light
+}
+
+pub fn area_light(
+    corner: tuples::Point,
+    full_uvec: tuples::Vector,
+    usteps: u32,
+    full_vvec: tuples::Vector,
+    vsteps: u32,
+    intensity: tuples::Color,
+) -> Light {
+    let usteps = usteps.max(1);
+    let vsteps = vsteps.max(1);
+    Light {
+        position: corner
+            .add(&full_uvec.divide(&2.0))
+            .add(&full_vvec.divide(&2.0)),
+        intensity,
+        light_type: LightType::Area,
+        corner,
+        uvec: full_uvec.divide(&(usteps as f64)),
+        usteps,
+        vvec: full_vvec.divide(&(vsteps as f64)),
+        vsteps,
+        jitter: false,
+        direction: tuples::VECTOR_NULL,
+        inner_angle: 0.0,
+        outer_angle: 0.0,
+        constant: 1.0,
+        linear: 0.0,
+        quadratic: 0.0,
     }
 }
 
 pub fn lighting(
     material: &materials::Material,
     shape: &shapes::Shape,
-    light: &LightPoint,
+    light: &Light,
     point: &tuples::Point,
     eyev: &tuples::Point,
     normalv: &tuples::Vector,
-    in_shadow: &bool,
+    intensity: &tuples::Color,
 ) -> tuples::Color {
     let mut diffuse: tuples::Color = tuples::COLOR_BLACK;
     let mut specular: tuples::Color = tuples::COLOR_BLACK;
-    let reflectv: tuples::Vector;
-    let reflect_dot_eye: f64;
 
     let mut _col = tuples::COLOR_WHITE;
-    match material.pattern {
+    match &material.pattern {
         Some(p) => _col = p.pattern_at_shape(&shape, &point),
         None => _col = material.color,
     }
 
     let effective_color: tuples::Color = _col.multiply(&light.intensity);
-    let lightv: tuples::Vector = light.position.subtract(&point).normalize();
     let ambient: tuples::Color = effective_color.scalar_multiply(&material.ambient);
-    let light_dot_normal: f64 = lightv.dot_product(&normalv);
-
-    if light_dot_normal >= 0.0 {
-        diffuse = effective_color
-            .scalar_multiply(&material.diffuse)
-            .scalar_multiply(&light_dot_normal);
-        reflectv = lightv.multiply(&-1.0).reflect(&normalv);
-        reflect_dot_eye = reflectv.dot_product(&eyev);
-        if reflect_dot_eye > 0.0 {
-            let factor: f64 = reflect_dot_eye.powf(material.shininess);
-            specular = light
-                .intensity
-                .scalar_multiply(&(material.specular * factor));
-        }
+    //intensity is how much of each channel gets past any shadows
+    let falloff = light.cone_factor(point) * light.attenuation_at(point);
+    if falloff <= 0.0 || intensity.is_equal_to(&tuples::COLOR_BLACK) {
+        return ambient;
     }
-    if in_shadow == &true {
-        ambient
-    } else {
-        ambient.add(&diffuse).add(&specular)
+
+    //an area light is the average of the light from each of its samples
+    let lightvs = light.light_vectors(point);
+    for lightv in lightvs.iter() {
+        let light_dot_normal: f64 = lightv.dot_product(normalv);
+        if light_dot_normal >= 0.0 {
+            diffuse = diffuse.add(
+                &effective_color
+                    .scalar_multiply(&material.diffuse)
+                    .scalar_multiply(&light_dot_normal),
+            );
+            let reflectv: tuples::Vector = lightv.multiply(&-1.0).reflect(normalv);
+            let reflect_dot_eye: f64 = reflectv.dot_product(eyev);
+            if reflect_dot_eye > 0.0 {
+                let factor: f64 = reflect_dot_eye.powf(material.shininess);
+                specular = specular.add(
+                    &light
+                        .intensity
+                        .scalar_multiply(&(material.specular * factor)),
+                );
+            }
+        }
     }
+    let share = falloff / lightvs.len() as f64;
+    ambient.add(
+        &diffuse
+            .add(&specular)
+            .multiply(intensity)
+            .scalar_multiply(&share),
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::spheres;
+    use std::f64::consts::PI;
 
     #[test]
     fn test_light_point_has_position_intensity() {
@@ -86,7 +292,7 @@ mod tests {
         let position = tuples::point(0.0, 0.0, -10.0);
         let intensity = tuples::COLOR_WHITE;
         let light = light_point(position, intensity);
-        let in_shadow = true;
+        let intensity = tuples::COLOR_BLACK;
         let s = spheres::sphere();
         let col = lighting(
             &materials::MATERIAL_DEFAULT,
@@ -95,8 +301,309 @@ mod tests {
             &position,
             &eyev,
             &normalv,
-            &in_shadow,
+            &intensity,
         );
         assert_eq!(col.is_equal_to(&tuples::color(0.1, 0.1, 0.1)), true);
     }
+
+    #[test]
+    fn test_lighting_uses_light_intensity_to_attenuate_color() {
+        //lighting() uses light intensity to attenuate color
+        let light = light_point(tuples::point(0.0, 0.0, -10.0), tuples::COLOR_WHITE);
+        let mut s = spheres::sphere();
+        s.material.ambient = 0.1;
+        s.material.diffuse = 0.9;
+        s.material.specular = 0.0;
+        s.material.color = tuples::COLOR_WHITE;
+        let pt = tuples::point(0.0, 0.0, -1.0);
+        let eyev = tuples::vector(0.0, 0.0, -1.0);
+        let normalv = tuples::vector(0.0, 0.0, -1.0);
+        let tests = [(1.0, 1.0), (0.5, 0.55), (0.0, 0.1)];
+        for (intensity, expected) in tests.iter() {
+            let intensity = tuples::color(*intensity, *intensity, *intensity);
+            let col = lighting(&s.material, &s, &light, &pt, &eyev, &normalv, &intensity);
+            assert!(col.is_equal_to(&tuples::color(*expected, *expected, *expected)));
+        }
+    }
+
+    #[test]
+    fn test_creating_an_area_light() {
+        //Creating an area light
+        let corner = tuples::point(0.0, 0.0, 0.0);
+        let v1 = tuples::vector(2.0, 0.0, 0.0);
+        let v2 = tuples::vector(0.0, 0.0, 1.0);
+        let light = area_light(corner, v1, 4, v2, 2, tuples::COLOR_WHITE);
+        assert_eq!(light.light_type, LightType::Area);
+        assert!(light.corner.is_equal_to(&corner));
+        assert!(light.uvec.is_equal_to(&tuples::vector(0.5, 0.0, 0.0)));
+        assert_eq!(light.usteps, 4);
+        assert!(light.vvec.is_equal_to(&tuples::vector(0.0, 0.0, 0.5)));
+        assert_eq!(light.vsteps, 2);
+        assert_eq!(light.sample_positions(&tuples::POINT_ORIGIN).len(), 8);
+        assert!(light.position.is_equal_to(&tuples::point(1.0, 0.0, 0.5)));
+    }
+
+    #[test]
+    fn test_finding_a_single_point_on_an_area_light() {
+        //Finding a single point on an area light
+        let light = area_light(
+            tuples::point(0.0, 0.0, 0.0),
+            tuples::vector(2.0, 0.0, 0.0),
+            4,
+            tuples::vector(0.0, 0.0, 1.0),
+            2,
+            tuples::COLOR_WHITE,
+        );
+        let tests = [
+            (0, 0, tuples::point(0.25, 0.0, 0.25)),
+            (1, 0, tuples::point(0.75, 0.0, 0.25)),
+            (0, 1, tuples::point(0.25, 0.0, 0.75)),
+            (2, 0, tuples::point(1.25, 0.0, 0.25)),
+            (3, 1, tuples::point(1.75, 0.0, 0.75)),
+        ];
+        for (u, v, expected) in tests.iter() {
+            let mut centre = sequences::sequence(vec![0.5]);
+            let pt = light.point_on_light(*u, *v, &mut centre);
+            assert!(pt.is_equal_to(expected));
+        }
+    }
+
+    #[test]
+    fn test_finding_a_single_point_on_a_jittered_area_light() {
+        //Finding a single point on a jittered area light
+        let light = area_light(
+            tuples::point(0.0, 0.0, 0.0),
+            tuples::vector(2.0, 0.0, 0.0),
+            4,
+            tuples::vector(0.0, 0.0, 1.0),
+            2,
+            tuples::COLOR_WHITE,
+        );
+        let mut jitter_by = sequences::sequence(vec![0.3, 0.7]);
+        let tests = [
+            (0, 0, tuples::point(0.15, 0.0, 0.35)),
+            (1, 0, tuples::point(0.65, 0.0, 0.35)),
+            (0, 1, tuples::point(0.15, 0.0, 0.85)),
+            (2, 0, tuples::point(1.15, 0.0, 0.35)),
+            (3, 1, tuples::point(1.65, 0.0, 0.85)),
+        ];
+        for (u, v, expected) in tests.iter() {
+            let pt = light.point_on_light(*u, *v, &mut jitter_by);
+            assert!(pt.is_equal_to(expected));
+        }
+    }
+
+    #[test]
+    fn test_jittered_samples_stay_in_their_cells() {
+        //Jittered samples are random but stay inside their own cell, and repeat for a point
+        let mut light = area_light(
+            tuples::point(0.0, 0.0, 0.0),
+            tuples::vector(2.0, 0.0, 0.0),
+            2,
+            tuples::vector(0.0, 0.0, 2.0),
+            2,
+            tuples::COLOR_WHITE,
+        );
+        light.jitter = true;
+        let p = tuples::point(1.0, 5.0, 1.0);
+        let positions = light.sample_positions(&p);
+        assert_eq!(positions.len(), 4);
+        for (n, pos) in positions.iter().enumerate() {
+            let u = (n % 2) as f64;
+            let v = (n / 2) as f64;
+            assert!(pos.x >= u && pos.x < u + 1.0);
+            assert!(pos.z >= v && pos.z < v + 1.0);
+            assert!(pos.is_equal_to(&light.sample_positions(&p)[n]));
+        }
+    }
+
+    #[test]
+    fn test_lighting_samples_the_area_light() {
+        //lighting() samples the area light
+        let light = area_light(
+            tuples::point(-0.5, -0.5, -5.0),
+            tuples::vector(1.0, 0.0, 0.0),
+            2,
+            tuples::vector(0.0, 1.0, 0.0),
+            2,
+            tuples::COLOR_WHITE,
+        );
+        let mut s = spheres::sphere();
+        s.material.ambient = 0.1;
+        s.material.diffuse = 0.9;
+        s.material.specular = 0.0;
+        s.material.color = tuples::COLOR_WHITE;
+        let eye = tuples::point(0.0, 0.0, -5.0);
+        let tests = [
+            (tuples::point(0.0, 0.0, -1.0), 0.99650),
+            (
+                tuples::point(
+                    0.0,
+                    std::f64::consts::FRAC_1_SQRT_2,
+                    -std::f64::consts::FRAC_1_SQRT_2,
+                ),
+                0.62318,
+            ),
+        ];
+        for (pt, expected) in tests.iter() {
+            let eyev = eye.subtract(pt).normalize();
+            let normalv = tuples::vector(pt.x, pt.y, pt.z);
+            let col = lighting(
+                &s.material,
+                &s,
+                &light,
+                pt,
+                &eyev,
+                &normalv,
+                &tuples::COLOR_WHITE,
+            );
+            assert!(col.is_equal_to(&tuples::color(*expected, *expected, *expected)));
+        }
+    }
+
+    #[test]
+    fn test_creating_a_spot_light() {
+        //A spot light has a position, direction and inner and outer cone angles
+        let light = spot_light(
+            tuples::point(0.0, 5.0, 0.0),
+            tuples::vector(0.0, -2.0, 0.0),
+            PI / 8.0,
+            PI / 4.0,
+            tuples::COLOR_WHITE,
+        );
+        assert_eq!(light.light_type, LightType::Spot);
+        assert!(light.position.is_equal_to(&tuples::point(0.0, 5.0, 0.0)));
+        assert!(light.direction.is_equal_to(&tuples::vector(0.0, -1.0, 0.0)));
+        assert_eq!(light.inner_angle, PI / 8.0);
+        assert_eq!(light.outer_angle, PI / 4.0);
+    }
+
+    #[test]
+    fn test_spot_lights_fade_smoothly_between_their_cones() {
+        //A spot light is full inside its inner cone, dark outside its outer cone and fades between
+        let light = spot_light(
+            tuples::POINT_ORIGIN,
+            tuples::vector(0.0, 0.0, 1.0),
+            PI / 8.0,
+            PI / 4.0,
+            tuples::COLOR_WHITE,
+        );
+        let on_axis = tuples::point(0.0, 0.0, 10.0);
+        let outside = tuples::point(10.0, 0.0, 5.0);
+        let behind = tuples::point(0.0, 0.0, -10.0);
+        assert_eq!(light.cone_factor(&on_axis), 1.0);
+        assert_eq!(light.cone_factor(&outside), 0.0);
+        assert_eq!(light.cone_factor(&behind), 0.0);
+        //half way between the cosines of the two angles
+        let cos_half = ((PI / 8.0).cos() + (PI / 4.0).cos()) / 2.0;
+        let between = tuples::point((1.0 - cos_half * cos_half).sqrt(), 0.0, cos_half);
+        assert!(tuples::get_bool_numbers_are_equal(
+            &light.cone_factor(&between),
+            &0.5
+        ));
+        assert_eq!(
+            light_point(behind, tuples::COLOR_WHITE).cone_factor(&on_axis),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_lighting_with_a_spot_light() {
+        //Only surfaces inside a spot light's cone get its diffuse and specular light
+        let eyev = tuples::vector(0.0, 0.0, -1.0);
+        let normalv = tuples::vector(0.0, 0.0, -1.0);
+        let light = spot_light(
+            tuples::point(0.0, 0.0, -10.0),
+            tuples::vector(0.0, 0.0, 1.0),
+            PI / 16.0,
+            PI / 8.0,
+            tuples::COLOR_WHITE,
+        );
+        let s = spheres::sphere();
+        let m = materials::MATERIAL_DEFAULT;
+        let lit = lighting(
+            &m,
+            &s,
+            &light,
+            &tuples::POINT_ORIGIN,
+            &eyev,
+            &normalv,
+            &tuples::COLOR_WHITE,
+        );
+        assert!(lit.is_equal_to(&tuples::color(1.9, 1.9, 1.9)));
+        let p = tuples::point(10.0, 0.0, 0.0);
+        let unlit = lighting(&m, &s, &light, &p, &eyev, &normalv, &tuples::COLOR_WHITE);
+        assert!(unlit.is_equal_to(&tuples::color(0.1, 0.1, 0.1)));
+    }
+
+    #[test]
+    fn test_lighting_with_a_directional_light() {
+        //A directional light lights every point from the same direction
+        let eyev = tuples::vector(0.0, 0.0, -1.0);
+        let normalv = tuples::vector(0.0, 0.0, -1.0);
+        let light = directional_light(tuples::vector(0.0, 0.0, 3.0), tuples::COLOR_WHITE);
+        assert_eq!(light.light_type, LightType::Directional);
+        assert!(light.direction.is_equal_to(&tuples::vector(0.0, 0.0, 1.0)));
+        let s = spheres::sphere();
+        let m = materials::MATERIAL_DEFAULT;
+        for p in [
+            tuples::POINT_ORIGIN,
+            tuples::point(100.0, -50.0, 20.0),
+            tuples::point(0.0, 0.0, -1000.0),
+        ]
+        .iter()
+        {
+            let col = lighting(&m, &s, &light, p, &eyev, &normalv, &tuples::COLOR_WHITE);
+            assert!(col.is_equal_to(&tuples::color(1.9, 1.9, 1.9)));
+        }
+    }
+
+    #[test]
+    fn test_point_lights_do_not_fade_by_default() {
+        //A point light's default attenuation leaves it full strength at any distance
+        let light = light_point(tuples::POINT_ORIGIN, tuples::COLOR_WHITE);
+        assert_eq!(light.constant, 1.0);
+        assert_eq!(light.linear, 0.0);
+        assert_eq!(light.quadratic, 0.0);
+        assert_eq!(light.attenuation_at(&tuples::point(0.0, 0.0, 1.0)), 1.0);
+        assert_eq!(light.attenuation_at(&tuples::point(0.0, 0.0, 1000.0)), 1.0);
+    }
+
+    #[test]
+    fn test_point_light_attenuation_coefficients() {
+        //Attenuation divides a point light by constant + linear * d + quadratic * d^2
+        let mut light = light_point(tuples::POINT_ORIGIN, tuples::COLOR_WHITE);
+        light.constant = 1.0;
+        light.linear = 0.5;
+        light.quadratic = 0.25;
+        let p = tuples::point(0.0, 2.0, 0.0);
+        assert_eq!(light.attenuation_at(&p), 1.0 / 3.0);
+        light.set_inverse_square();
+        assert_eq!(light.attenuation_at(&p), 0.25);
+        assert_eq!(light.attenuation_at(&tuples::point(0.0, 0.0, -10.0)), 0.01);
+        let sun = directional_light(tuples::vector(0.0, -1.0, 0.0), tuples::COLOR_WHITE);
+        assert_eq!(sun.attenuation_at(&p), 1.0);
+    }
+
+    #[test]
+    fn test_lighting_with_an_attenuated_light() {
+        //An attenuated light's diffuse and specular fade with distance but not its ambient
+        let eyev = tuples::vector(0.0, 0.0, -1.0);
+        let normalv = tuples::vector(0.0, 0.0, -1.0);
+        let mut light = light_point(tuples::point(0.0, 0.0, -2.0), tuples::COLOR_WHITE);
+        light.set_inverse_square();
+        let s = spheres::sphere();
+        let m = materials::MATERIAL_DEFAULT;
+        let col = lighting(
+            &m,
+            &s,
+            &light,
+            &tuples::POINT_ORIGIN,
+            &eyev,
+            &normalv,
+            &tuples::COLOR_WHITE,
+        );
+        //0.1 ambient plus a quarter of the 0.9 diffuse and 0.9 specular
+        assert!(col.is_equal_to(&tuples::color(0.55, 0.55, 0.55)));
+    }
 }
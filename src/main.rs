@@ -2,33 +2,32 @@
 
 use read_input::prelude::*;
 
-mod camera;
-mod canvas;
-mod cubes;
-mod intersections;
-mod lights;
-mod materials;
-mod matrices;
-mod patterns;
-mod planes;
+mod cli;
 mod program_chapter_10_patterns;
 mod program_chapter_11_reflection;
+#[allow(clippy::let_unit_value, clippy::to_string_in_format_args)]
 mod program_chapter_11_refractions;
+#[allow(clippy::let_and_return)]
 mod program_chapter_9_planes;
+#[allow(clippy::assign_op_pattern)]
 mod program_fire_canon;
+#[allow(clippy::assign_op_pattern)]
 mod program_sphere_lighting;
 mod program_sphere_outline;
+#[allow(clippy::let_unit_value)]
 mod program_world;
-mod rays;
-mod shapes;
-mod spheres;
-mod transformations;
-mod tuples;
-mod worlds;
 
 const DEFAULT_SIZE_INDICES: [usize; 8] = [4, 2, 3, 2, 1, 1, 1, 1];
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() > 1 {
+        if let Err(e) = cli::run(args) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
     let sizes_arr: [[u32; 2]; 11] = [
         [50, 25],
         [100, 50],
@@ -87,6 +86,7 @@ fn get_selected_program() -> u32 {
     input::<u32>().msg(message).get()
 }
 
+#[allow(clippy::needless_late_init)]
 fn get_size_for_program(size: u32, prog_num: u32) -> usize {
     let index: usize;
     if size == 0 {
@@ -1,3 +1,4 @@
+use crate::bumps;
 use crate::patterns;
 use crate::tuples;
 
@@ -11,6 +12,8 @@ pub const MATERIAL_DEFAULT: Material = Material {
     reflective: 0.0,
     transparency: 0.0,
     refractive_index: REFRACTIVE_INDEX_VACUUM,
+    casts_shadow: true,
+    bump: None,
 };
 
 pub const REFRACTIVE_INDEX_VACUUM: f64 = 1.0;
@@ -19,7 +22,7 @@ pub const REFRACTIVE_INDEX_WATER: f64 = 1.333;
 pub const REFRACTIVE_INDEX_GLASS: f64 = 1.52;
 pub const REFRACTIVE_INDEX_DIAMOND: f64 = 2.417;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct Material {
     pub pattern: Option<patterns::Pattern>,
     pub color: tuples::Color,
@@ -30,8 +33,14 @@ pub struct Material {
     pub reflective: f64,
     pub transparency: f64,
     pub refractive_index: f64,
+    //false lets light straight through when testing for shadows
+    pub casts_shadow: bool,
+    //tips the normal before shading and reflection
+    pub bump: Option<bumps::Bump>,
 }
 
+//one argument per field, like the book; MATERIAL_DEFAULT with struct update syntax is easier to read
+#[allow(clippy::too_many_arguments)]
 pub fn material(
     pattern: Option<patterns::Pattern>,
     color: tuples::Color,
@@ -53,6 +62,8 @@ pub fn material(
         reflective: reflective,
         transparency: transparency,
         refractive_index: refractive_index,
+        casts_shadow: true,
+        bump: None,
     }
 }
 
@@ -71,6 +82,7 @@ mod tests {
         assert_eq!(a.diffuse, 0.9);
         assert_eq!(a.specular, 0.9);
         assert_eq!(a.shininess, 200.0);
+        assert!(a.casts_shadow);
     }
 
     #[test]
@@ -87,7 +99,7 @@ mod tests {
             &tuples::POINT_ORIGIN,
             &eyev,
             &normalv,
-            &false,
+            &tuples::COLOR_WHITE,
         );
         assert_eq!(result.is_equal_to(&tuples::color(1.9, 1.9, 1.9)), true);
     }
@@ -106,7 +118,7 @@ mod tests {
             &tuples::POINT_ORIGIN,
             &eyev,
             &normalv,
-            &false,
+            &tuples::COLOR_WHITE,
         );
         assert_eq!(result.is_equal_to(&tuples::color(1.0, 1.0, 1.0)), true);
     }
@@ -125,7 +137,7 @@ mod tests {
             &tuples::POINT_ORIGIN,
             &eyev,
             &normalv,
-            &false,
+            &tuples::COLOR_WHITE,
         );
         assert_eq!(
             result.is_equal_to(&tuples::color(0.7364, 0.7364, 0.7364)),
@@ -147,7 +159,7 @@ mod tests {
             &tuples::POINT_ORIGIN,
             &eyev,
             &normalv,
-            &false,
+            &tuples::COLOR_WHITE,
         );
         assert_eq!(
             result.is_equal_to(&tuples::color(1.6364, 1.6364, 1.6364)),
@@ -169,7 +181,7 @@ mod tests {
             &tuples::POINT_ORIGIN,
             &eyev,
             &normalv,
-            &false,
+            &tuples::COLOR_WHITE,
         );
         println!("result {},{},{}", result.red, result.green, result.blue);
         assert_eq!(result.is_equal_to(&tuples::color(0.1, 0.1, 0.1)), true);
@@ -194,7 +206,7 @@ mod tests {
             &tuples::point(0.9, 0.0, 0.0),
             &eyev,
             &normalv,
-            &false,
+            &tuples::COLOR_WHITE,
         );
         let c2 = lights::lighting(
             &m,
@@ -203,7 +215,7 @@ mod tests {
             &tuples::point(1.1, 0.0, 0.0),
             &eyev,
             &normalv,
-            &false,
+            &tuples::COLOR_WHITE,
         );
         assert_eq!(c1.is_equal_to(&tuples::COLOR_WHITE), true);
         assert_eq!(c2.is_equal_to(&tuples::COLOR_BLACK), true);
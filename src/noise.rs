@@ -0,0 +1,160 @@
+use crate::tuples;
+
+//Ken Perlin's improved noise, smooth values in roughly -1..1 that repeat every 256 units
+const PERMUTATION: [usize; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+fn perm(i: usize) -> usize {
+    PERMUTATION[i & 255]
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+//dot product of x, y, z with one of twelve gradient directions picked by the hash
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
+
+pub fn perlin(x: f64, y: f64, z: f64) -> f64 {
+    let xi = x.floor().rem_euclid(256.0) as usize;
+    let yi = y.floor().rem_euclid(256.0) as usize;
+    let zi = z.floor().rem_euclid(256.0) as usize;
+    let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+    let (u, v, w) = (fade(x), fade(y), fade(z));
+
+    let a = perm(xi) + yi;
+    let aa = perm(a) + zi;
+    let ab = perm(a + 1) + zi;
+    let b = perm(xi + 1) + yi;
+    let ba = perm(b) + zi;
+    let bb = perm(b + 1) + zi;
+
+    lerp(
+        w,
+        lerp(
+            v,
+            lerp(u, grad(perm(aa), x, y, z), grad(perm(ba), x - 1.0, y, z)),
+            lerp(
+                u,
+                grad(perm(ab), x, y - 1.0, z),
+                grad(perm(bb), x - 1.0, y - 1.0, z),
+            ),
+        ),
+        lerp(
+            v,
+            lerp(
+                u,
+                grad(perm(aa + 1), x, y, z - 1.0),
+                grad(perm(ba + 1), x - 1.0, y, z - 1.0),
+            ),
+            lerp(
+                u,
+                grad(perm(ab + 1), x, y - 1.0, z - 1.0),
+                grad(perm(bb + 1), x - 1.0, y - 1.0, z - 1.0),
+            ),
+        ),
+    )
+}
+
+//octaves of noise, each at twice the frequency and persistence times the size of the last,
+//scaled back into -1..1
+pub fn fractal_noise(p: &tuples::Point, octaves: u32, persistence: f64) -> f64 {
+    octave_sum(p, octaves, persistence, |n| n)
+}
+
+//like fractal_noise but summing the absolute noise, for sharp creases in 0..1
+pub fn turbulence(p: &tuples::Point, octaves: u32, persistence: f64) -> f64 {
+    octave_sum(p, octaves, persistence, f64::abs)
+}
+
+fn octave_sum<F>(p: &tuples::Point, octaves: u32, persistence: f64, f: F) -> f64
+where
+    F: Fn(f64) -> f64,
+{
+    let mut total = 0.0;
+    let mut frequency = 1.0;
+    let mut amplitude = 1.0;
+    let mut max = 0.0;
+    for _ in 0..octaves.max(1) {
+        total += f(perlin(p.x * frequency, p.y * frequency, p.z * frequency)) * amplitude;
+        max += amplitude;
+        frequency *= 2.0;
+        amplitude *= persistence;
+    }
+    total / max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_noise_is_zero_on_the_integer_lattice() {
+        //Perlin noise is zero at every whole number point
+        assert_eq!(perlin(0.0, 0.0, 0.0), 0.0);
+        assert_eq!(perlin(3.0, -7.0, 12.0), 0.0);
+        assert_eq!(perlin(300.0, 1.0, -256.0), 0.0);
+    }
+
+    #[test]
+    fn test_noise_is_smooth_and_in_range() {
+        //Perlin noise stays in -1..1, varies, and changes little over a small step
+        let mut min: f64 = 0.0;
+        let mut max: f64 = 0.0;
+        for i in 0..1000 {
+            let x = i as f64 * 0.137;
+            let n = perlin(x, x * 0.5 + 0.3, 2.7 - x);
+            min = min.min(n);
+            max = max.max(n);
+            assert!((-1.0..=1.0).contains(&n));
+            let step = perlin(x + 0.001, x * 0.5 + 0.3, 2.7 - x);
+            assert!((n - step).abs() < 0.01);
+        }
+        assert!(min < -0.2 && max > 0.2);
+    }
+
+    #[test]
+    fn test_fractal_noise_and_turbulence_stay_in_range() {
+        //Fractal noise stays in -1..1 and turbulence in 0..1
+        for i in 0..500 {
+            let p = tuples::point(i as f64 * 0.31, i as f64 * -0.17, 1.5);
+            let f = fractal_noise(&p, 4, 0.5);
+            let t = turbulence(&p, 4, 0.5);
+            assert!((-1.0..=1.0).contains(&f));
+            assert!((0.0..=1.0).contains(&t));
+        }
+        //one octave is just the noise
+        let p = tuples::point(0.3, 0.6, 0.9);
+        assert_eq!(fractal_noise(&p, 1, 0.5), perlin(0.3, 0.6, 0.9));
+    }
+}
@@ -0,0 +1,351 @@
+use std::fs;
+
+use crate::groups;
+use crate::shapes;
+use crate::triangles;
+use crate::tuples;
+
+#[derive(Debug, Clone)]
+pub struct ObjFile {
+    pub vertices: Vec<tuples::Point>,
+    pub normals: Vec<tuples::Vector>,
+    pub texture_coords: Vec<(f64, f64)>,
+    //faces that appear before any 'g' statement
+    pub default_group: shapes::Shape,
+    pub groups: Vec<(String, shapes::Shape)>,
+    //lines with statements this parser doesn't support, so were ignored
+    pub skipped: usize,
+}
+
+impl ObjFile {
+    pub fn named_group(&self, name: &str) -> Option<&shapes::Shape> {
+        self.groups.iter().find(|g| g.0 == name).map(|g| &g.1)
+    }
+
+    pub fn to_group(&self) -> shapes::Shape {
+        let mut g = self.default_group.clone();
+        for (_, named) in self.groups.iter() {
+            g.add_child(named.clone());
+        }
+        g
+    }
+}
+
+pub fn obj_file_from_path(path: &str) -> Result<ObjFile, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_obj_file(&contents).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn parse_obj_file(contents: &str) -> Result<ObjFile, String> {
+    let mut obj = ObjFile {
+        vertices: vec![],
+        normals: vec![],
+        texture_coords: vec![],
+        default_group: groups::group(),
+        groups: vec![],
+        skipped: 0,
+    };
+    //None while faces are going into the default group
+    let mut current: Option<usize> = None;
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() || words[0].starts_with('#') {
+            continue;
+        }
+        let args = &words[1..];
+        let at_line = |e: String| format!("line {}: {}", line_number, e);
+        match words[0] {
+            "v" => {
+                let n = numbers(args, 3, "vertex").map_err(at_line)?;
+                obj.vertices.push(tuples::point(n[0], n[1], n[2]));
+            }
+            "vn" => {
+                let n = numbers(args, 3, "vertex normal").map_err(at_line)?;
+                obj.normals.push(tuples::vector(n[0], n[1], n[2]));
+            }
+            "vt" => {
+                let n = numbers(args, 2, "texture coordinate").map_err(at_line)?;
+                obj.texture_coords.push((n[0], n[1]));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(at_line("a face needs at least 3 vertices".to_string()));
+                }
+                let triangles = parse_face(&obj, args).map_err(at_line)?;
+                let group = match current {
+                    Some(g) => &mut obj.groups[g].1,
+                    None => &mut obj.default_group,
+                };
+                for t in triangles {
+                    group.add_child(t);
+                }
+            }
+            //a 'g' with no name goes back to the default group
+            "g" if args.is_empty() => current = None,
+            "g" => {
+                let name = args.join(" ");
+                current = match obj.groups.iter().position(|g| g.0 == name) {
+                    Some(existing) => Some(existing),
+                    None => {
+                        obj.groups.push((name, groups::group()));
+                        Some(obj.groups.len() - 1)
+                    }
+                };
+            }
+            _ => obj.skipped += 1,
+        }
+    }
+    Ok(obj)
+}
+
+fn numbers(args: &[&str], count: usize, kind: &str) -> Result<Vec<f64>, String> {
+    //extra values such as a vertex's optional w are ignored
+    if args.len() < count {
+        return Err(format!("a {} needs {} numbers", kind, count));
+    }
+    args[..count]
+        .iter()
+        .map(|a| {
+            a.parse::<f64>()
+                .map_err(|_| format!("expected a number for a {}, found '{}'", kind, a))
+        })
+        .collect()
+}
+
+fn index(word: &str, len: usize, kind: &str) -> Result<Option<usize>, String> {
+    //indices start at 1, and negative ones count back from the latest
+    if word.is_empty() {
+        return Ok(None);
+    }
+    let i = word
+        .parse::<i64>()
+        .map_err(|_| format!("expected a {} index, found '{}'", kind, word))?;
+    let resolved = if i < 0 { len as i64 + i } else { i - 1 };
+    if resolved < 0 || resolved >= len as i64 {
+        Err(format!("{} index {} is out of range", kind, i))
+    } else {
+        Ok(Some(resolved as usize))
+    }
+}
+
+fn parse_face(obj: &ObjFile, args: &[&str]) -> Result<Vec<shapes::Shape>, String> {
+    //each vertex is v, v/vt, v//vn or v/vt/vn
+    let mut points: Vec<tuples::Point> = vec![];
+    let mut normals: Vec<Option<tuples::Vector>> = vec![];
+    let mut uvs: Vec<Option<(f64, f64)>> = vec![];
+    for arg in args.iter() {
+        let parts: Vec<&str> = arg.split('/').collect();
+        match index(parts[0], obj.vertices.len(), "vertex")? {
+            Some(v) => points.push(obj.vertices[v]),
+            None => return Err(format!("face vertex '{}' has no position", arg)),
+        }
+        let uv = match parts.get(1) {
+            Some(t) => {
+                index(t, obj.texture_coords.len(), "texture")?.map(|t| obj.texture_coords[t])
+            }
+            None => None,
+        };
+        uvs.push(uv);
+        let normal = match parts.get(2) {
+            Some(n) => index(n, obj.normals.len(), "normal")?.map(|n| obj.normals[n]),
+            None => None,
+        };
+        normals.push(normal);
+    }
+    let smooth = normals.iter().all(|n| n.is_some());
+    //fan triangulation around the first vertex
+    let mut triangles: Vec<shapes::Shape> = vec![];
+    for i in 1..points.len() - 1 {
+        let mut t = if smooth {
+            triangles::smooth_triangle(
+                points[0],
+                points[i],
+                points[i + 1],
+                normals[0].unwrap(),
+                normals[i].unwrap(),
+                normals[i + 1].unwrap(),
+            )
+        } else {
+            triangles::triangle(points[0], points[i], points[i + 1])
+        };
+        if let (Some(uv1), Some(uv2), Some(uv3)) = (uvs[0], uvs[i], uvs[i + 1]) {
+            if let Some(tri) = t.triangle.as_mut() {
+                tri.uvs = Some([uv1, uv2, uv3]);
+            }
+        }
+        triangles.push(t);
+    }
+    Ok(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertices_of(s: &shapes::Shape) -> triangles::Triangle {
+        s.triangle.unwrap()
+    }
+
+    #[test]
+    fn test_ignoring_unrecognized_lines() {
+        //Ignoring unrecognized lines
+        let gibberish = "There was a young lady named Bright
+who traveled much faster than light.
+She set out one day
+in a relative way,
+and came back the previous night.";
+        let obj = parse_obj_file(gibberish).unwrap();
+        assert_eq!(obj.skipped, 5);
+        assert_eq!(obj.vertices.len(), 0);
+    }
+
+    #[test]
+    fn test_vertex_records() {
+        //Vertex records
+        let file = "v -1 1 0\nv -1.0000 0.5000 0.0000\nv 1 0 0\nv 1 1 0\n";
+        let obj = parse_obj_file(file).unwrap();
+        assert!(obj.vertices[0].is_equal_to(&tuples::point(-1.0, 1.0, 0.0)));
+        assert!(obj.vertices[1].is_equal_to(&tuples::point(-1.0, 0.5, 0.0)));
+        assert!(obj.vertices[2].is_equal_to(&tuples::point(1.0, 0.0, 0.0)));
+        assert!(obj.vertices[3].is_equal_to(&tuples::point(1.0, 1.0, 0.0)));
+        assert_eq!(obj.skipped, 0);
+    }
+
+    #[test]
+    fn test_parsing_triangle_faces() {
+        //Parsing triangle faces
+        let file = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\n\nf 1 2 3\nf 1 3 4\n";
+        let obj = parse_obj_file(file).unwrap();
+        let g = &obj.default_group;
+        let t1 = vertices_of(&g.children()[0]);
+        let t2 = vertices_of(&g.children()[1]);
+        assert!(t1.p1.is_equal_to(&obj.vertices[0]));
+        assert!(t1.p2.is_equal_to(&obj.vertices[1]));
+        assert!(t1.p3.is_equal_to(&obj.vertices[2]));
+        assert!(t2.p1.is_equal_to(&obj.vertices[0]));
+        assert!(t2.p2.is_equal_to(&obj.vertices[2]));
+        assert!(t2.p3.is_equal_to(&obj.vertices[3]));
+    }
+
+    #[test]
+    fn test_triangulating_polygons() {
+        //Triangulating polygons
+        let file = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\n\nf 1 2 3 4 5\n";
+        let obj = parse_obj_file(file).unwrap();
+        let g = &obj.default_group;
+        assert_eq!(g.children().len(), 3);
+        let t3 = vertices_of(&g.children()[2]);
+        assert!(t3.p1.is_equal_to(&obj.vertices[0]));
+        assert!(t3.p2.is_equal_to(&obj.vertices[3]));
+        assert!(t3.p3.is_equal_to(&obj.vertices[4]));
+    }
+
+    #[test]
+    fn test_triangles_in_groups() {
+        //Triangles in groups
+        let file =
+            "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\ng FirstGroup\nf 1 2 3\ng SecondGroup\nf 1 3 4\n";
+        let obj = parse_obj_file(file).unwrap();
+        let g1 = obj.named_group("FirstGroup").unwrap();
+        let g2 = obj.named_group("SecondGroup").unwrap();
+        assert!(vertices_of(&g1.children()[0])
+            .p2
+            .is_equal_to(&obj.vertices[1]));
+        assert!(vertices_of(&g2.children()[0])
+            .p3
+            .is_equal_to(&obj.vertices[3]));
+        assert_eq!(obj.default_group.children().len(), 0);
+    }
+
+    #[test]
+    fn test_converting_an_obj_file_to_a_group() {
+        //Converting an OBJ file to a group
+        let file = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 4\ng FirstGroup\nf 1 2 3\ng SecondGroup\nf 1 3 4\n";
+        let obj = parse_obj_file(file).unwrap();
+        let g = obj.to_group();
+        assert_eq!(g.children().len(), 3);
+        assert_eq!(g.children()[1].children().len(), 1);
+        assert_eq!(g.children()[2].children().len(), 1);
+    }
+
+    #[test]
+    fn test_vertex_normal_records() {
+        //Vertex normal records
+        let file = "vn 0 0 1\nvn 0.707 0 -0.707\nvn 1 2 3\n";
+        let obj = parse_obj_file(file).unwrap();
+        assert!(obj.normals[0].is_equal_to(&tuples::vector(0.0, 0.0, 1.0)));
+        assert!(obj.normals[1].is_equal_to(&tuples::vector(0.707, 0.0, -0.707)));
+        assert!(obj.normals[2].is_equal_to(&tuples::vector(1.0, 2.0, 3.0)));
+    }
+
+    #[test]
+    fn test_faces_with_normals() {
+        //Faces with normals, with and without texture coordinates
+        let file = "v 0 1 0\nv -1 0 0\nv 1 0 0\nvt 0.5 1\nvt 0 0\nvt 1 0\nvn -1 0 0\nvn 1 0 0\nvn 0 1 0\nf 1//3 2//1 3//2\nf 1/1/3 2/2/1 3/3/2\n";
+        let obj = parse_obj_file(file).unwrap();
+        assert_eq!(obj.texture_coords.len(), 3);
+        let g = &obj.default_group;
+        for child in g.children().iter() {
+            match child.shape_type {
+                shapes::ShapeType::SmoothTriangle => (),
+                _ => assert_eq!(false, true),
+            }
+            let t = vertices_of(child);
+            assert!(t.p1.is_equal_to(&obj.vertices[0]));
+            assert!(t.n1.is_equal_to(&obj.normals[2]));
+            assert!(t.n2.is_equal_to(&obj.normals[0]));
+            assert!(t.n3.is_equal_to(&obj.normals[1]));
+        }
+    }
+
+    #[test]
+    fn test_negative_indices_and_bad_faces() {
+        //Negative indices count back from the latest vertex, and bad indices are errors
+        let obj = parse_obj_file("v 0 1 0\nv -1 0 0\nv 1 0 0\nf -3 -2 -1\n").unwrap();
+        let t = vertices_of(&obj.default_group.children()[0]);
+        assert!(t.p1.is_equal_to(&obj.vertices[0]));
+        assert!(t.p3.is_equal_to(&obj.vertices[2]));
+        let e = parse_obj_file("v 0 1 0\nv -1 0 0\nf 1 2 3\n").unwrap_err();
+        assert_eq!(e, "line 3: vertex index 3 is out of range");
+        let e = parse_obj_file("v 0 1 0\nv -1 0 0\nv 1 0 0\nf 1 2 x\n").unwrap_err();
+        assert_eq!(e, "line 4: expected a vertex index, found 'x'");
+    }
+
+    #[test]
+    fn test_malformed_records_are_errors_with_their_line() {
+        //Known statements that can't be read are errors, not skipped lines
+        let tests = [
+            ("v 1 2\n", "line 1: a vertex needs 3 numbers"),
+            (
+                "v 0 0 0\nvn 0 x 1\n",
+                "line 2: expected a number for a vertex normal, found 'x'",
+            ),
+            ("\nvt 0.5\n", "line 2: a texture coordinate needs 2 numbers"),
+            (
+                "v 0 0 0\nv 1 0 0\nf 1 2\n",
+                "line 3: a face needs at least 3 vertices",
+            ),
+        ];
+        for (file, expected) in tests.iter() {
+            assert_eq!(parse_obj_file(file).unwrap_err(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_faces_carry_their_texture_coordinates() {
+        //Texture coordinates on a face are attached to each triangle of its fan
+        let file = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nvt 0 1\nvt 0 0\nvt 1 0\nvt 1 1\nf 1/1 2/2 3/3 4/4\nf 1 2 3\n";
+        let obj = parse_obj_file(file).unwrap();
+        let g = &obj.default_group;
+        assert_eq!(
+            vertices_of(&g.children()[0]).uvs,
+            Some([(0.0, 1.0), (0.0, 0.0), (1.0, 0.0)])
+        );
+        assert_eq!(
+            vertices_of(&g.children()[1]).uvs,
+            Some([(0.0, 1.0), (1.0, 0.0), (1.0, 1.0)])
+        );
+        assert_eq!(vertices_of(&g.children()[2]).uvs, None);
+    }
+}
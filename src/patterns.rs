@@ -1,5 +1,10 @@
+use std::sync::Arc;
+
+use crate::canvas;
 use crate::matrices;
+use crate::noise;
 use crate::shapes;
+use crate::triangles;
 use crate::tuples;
 
 #[derive(Debug, Clone, Copy)]
@@ -9,96 +14,392 @@ pub enum PatternType {
     Gradient,
     Ring,
     Checkers,
+    Image,
+    UvCheckers,
+    AlignCheck,
+    CubeMap,
+    Perturb,
+    Marble,
+    Wood,
+    RadialGradient,
+    Blend,
+}
+
+//how the 2D patterns (image, uv checkers, align check) are wrapped onto pattern space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UvMapping {
+    //the xz plane
+    Planar,
+    Spherical,
+    //around the y axis, repeating every unit of height
+    Cylindrical,
+    //a separate face on each side of the -1..1 cube
+    Cube,
+    //a triangle's own texture coordinates from an obj file, planar on anything else
+    Mesh,
+}
+
+//in the order cube_map_pattern takes them, so a face indexes its pattern's children
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CubeFace {
+    Left,
+    Front,
+    Right,
+    Back,
+    Up,
+    Down,
+}
+
+//how an image pattern picks the color between pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureFilter {
+    //blends the four nearest pixels
+    Bilinear,
+    Nearest,
 }
 
-#[derive(Debug, Copy, Clone)]
+//what an image pattern does outside 0..1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextureWrap {
+    //repeats the edge pixels
+    Clamp,
+    //tiles the image
+    Repeat,
+}
+
+#[derive(Debug, Clone)]
 pub struct Pattern {
     pub a: tuples::Color,
     pub b: tuples::Color,
-    pub transform: matrices::Matrix4,
+    //how much of b a blend pattern mixes in, 0.5 for an even average
+    pub weight: f64,
+    //set with set_transform, which keeps the inverse in step
+    transform: matrices::Matrix4,
+    transform_inverse: matrices::Matrix4,
     pub pattern_type: PatternType,
+    //the image for image patterns, shared so cloning a material doesn't copy it
+    pub texture: Option<Arc<canvas::PixelCanvas>>,
+    pub filter: TextureFilter,
+    pub wrap: TextureWrap,
+    pub mapping: UvMapping,
+    //how many uv checkers fit across and up
+    pub uv_width: f64,
+    pub uv_height: f64,
+    //align check colors: upper left, upper right, bottom left, bottom right
+    pub corners: [tuples::Color; 4],
+    //patterns nested in this one's slots, each in its own space inside this one.
+    //slots 0 and 1 stand in for a and b, an align check's corners follow its main
+    //color in slots 1 to 4, a perturb pattern moves slot 0, and a cube map has a
+    //face in each slot in CubeFace order. an empty slot uses its plain color
+    pub children: Vec<Option<Pattern>>,
+    //how far noise moves the point, for perturb, marble and wood patterns
+    pub noise_scale: f64,
+    pub octaves: u32,
+    //how much each octave of noise counts compared to the one before
+    pub persistence: f64,
 }
 
 impl Pattern {
+    pub fn set_transform(&mut self, transform: matrices::Matrix4) {
+        self.transform = transform;
+        self.transform_inverse = transform.inverse();
+    }
+
+    pub fn transform(&self) -> &matrices::Matrix4 {
+        &self.transform
+    }
+
+    //a slot's color at p, from its nested pattern if it has one
+    pub fn slot_color(
+        &self,
+        slot: usize,
+        color: tuples::Color,
+        p: &tuples::Point,
+    ) -> tuples::Color {
+        match self.children.get(slot) {
+            Some(Some(nested)) => nested.nested_pattern_at(p),
+            _ => color,
+        }
+    }
+
+    pub fn color_a(&self, p: &tuples::Point) -> tuples::Color {
+        self.slot_color(0, self.a, p)
+    }
+
+    pub fn color_b(&self, p: &tuples::Point) -> tuples::Color {
+        self.slot_color(1, self.b, p)
+    }
+
+    pub fn set_nested(&mut self, slot: usize, nested: Pattern) {
+        if self.children.len() <= slot {
+            self.children.resize(slot + 1, None);
+        }
+        self.children[slot] = Some(nested);
+    }
+
     pub fn stripe_at(&self, p: &tuples::Point) -> tuples::Color {
         let rem = p.x % 2.0;
         if rem < 0.0 {
             if rem.abs() < 1.0 {
-                self.b
+                self.color_b(p)
             } else {
-                self.a
+                self.color_a(p)
             }
         } else {
             if rem.abs() <= 1.0 {
-                self.a
+                self.color_a(p)
             } else {
-                self.b
+                self.color_b(p)
             }
         }
     }
 
     pub fn gradient_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
-        let distance = self.b.subtract(&self.a);
+        let a = self.color_a(p);
+        let distance = self.color_b(p).subtract(&a);
         let fraction = p.x - p.x.trunc();
         let d_times_f = distance.scalar_multiply(&fraction);
-        self.a.add(&d_times_f)
+        a.add(&d_times_f)
+    }
+
+    //fades from a to b with the distance from the y axis, starting again every unit
+    pub fn radial_gradient_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
+        let r = ((p.x * p.x) + (p.z * p.z)).sqrt();
+        blend(&self.color_a(p), &self.color_b(p), r - r.floor())
     }
 
     pub fn ring_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
         let rem = ((p.x * p.x) + (p.z * p.z)).sqrt().floor() % 2.0;
         if rem == 0.0 {
-            self.a
+            self.color_a(p)
         } else {
-            self.b
+            self.color_b(p)
         }
     }
 
     pub fn checkers_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
         let rem = (p.x.floor() + p.y.floor() + p.z.floor()) % 2.0;
         if rem == 0.0 {
-            self.a
+            self.color_a(p)
         } else {
-            self.b
+            self.color_b(p)
         }
     }
 
+    //u runs left to right across the image and v from bottom to top
+    pub fn uv_image_at(&self, u: f64, v: f64) -> tuples::Color {
+        let texture = match &self.texture {
+            Some(t) => t,
+            None => return self.color_a(&uv_point(u, v)),
+        };
+        let (u, v) = match self.wrap {
+            TextureWrap::Clamp => (u.clamp(0.0, 1.0), v.clamp(0.0, 1.0)),
+            TextureWrap::Repeat => (u.rem_euclid(1.0), v.rem_euclid(1.0)),
+        };
+        let x = u * (texture.width - 1) as f64;
+        let y = (1.0 - v) * (texture.height - 1) as f64;
+        match self.filter {
+            TextureFilter::Nearest => texture.get_at(&(x.round() as u32), &(y.round() as u32)),
+            TextureFilter::Bilinear => {
+                let x0 = x.floor() as u32;
+                let y0 = y.floor() as u32;
+                let x1 = (x0 + 1).min(texture.width - 1);
+                let y1 = (y0 + 1).min(texture.height - 1);
+                let fx = x - x0 as f64;
+                let fy = y - y0 as f64;
+                let top = blend(&texture.get_at(&x0, &y0), &texture.get_at(&x1, &y0), fx);
+                let bottom = blend(&texture.get_at(&x0, &y1), &texture.get_at(&x1, &y1), fx);
+                blend(&top, &bottom, fy)
+            }
+        }
+    }
+
+    pub fn uv_checkers_at(&self, u: f64, v: f64) -> tuples::Color {
+        let u2 = (u * self.uv_width).floor();
+        let v2 = (v * self.uv_height).floor();
+        if (u2 + v2).rem_euclid(2.0) == 0.0 {
+            self.color_a(&uv_point(u, v))
+        } else {
+            self.color_b(&uv_point(u, v))
+        }
+    }
+
+    //the main color, with a square of each corner color to check a face's orientation
+    pub fn align_check_at(&self, u: f64, v: f64) -> tuples::Color {
+        let (u, v) = (u.rem_euclid(1.0), v.rem_euclid(1.0));
+        let corner = if v > 0.8 && u < 0.2 {
+            Some(0)
+        } else if v > 0.8 && u > 0.8 {
+            Some(1)
+        } else if v < 0.2 && u < 0.2 {
+            Some(2)
+        } else if v < 0.2 && u > 0.8 {
+            Some(3)
+        } else {
+            None
+        };
+        match corner {
+            Some(c) => self.slot_color(c + 1, self.corners[c], &uv_point(u, v)),
+            None => self.color_a(&uv_point(u, v)),
+        }
+    }
+
+    pub fn uv_pattern_at(&self, u: f64, v: f64) -> tuples::Color {
+        match self.pattern_type {
+            PatternType::Image => self.uv_image_at(u, v),
+            PatternType::UvCheckers => self.uv_checkers_at(u, v),
+            PatternType::AlignCheck => self.align_check_at(u, v),
+            //3D patterns have no 2D form
+            _ => self.a,
+        }
+    }
+
+    //where p lands on the 2D pattern under this pattern's mapping
+    pub fn uv_at(&self, p: &tuples::Point) -> (f64, f64) {
+        match self.mapping {
+            UvMapping::Planar | UvMapping::Mesh => planar_map(p),
+            UvMapping::Spherical => spherical_map(p),
+            UvMapping::Cylindrical => cylindrical_map(p),
+            UvMapping::Cube => cube_uv(face_from_point(p), p),
+        }
+    }
+
+    pub fn mapped_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
+        let (u, v) = self.uv_at(p);
+        match (self.mapping, self.pattern_type) {
+            (UvMapping::Cube, PatternType::CubeMap) => {
+                match &self.children.get(face_from_point(p) as usize) {
+                    Some(Some(face)) => face.uv_pattern_at(u, v),
+                    _ => self.a,
+                }
+            }
+            _ => self.uv_pattern_at(u, v),
+        }
+    }
+
+    //nudges the point with a little noise on each axis before looking up the inner pattern
+    pub fn perturb_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
+        let inner = match self.children.first() {
+            Some(Some(inner)) => inner,
+            _ => return self.a,
+        };
+        //offset so the three axes get unrelated noise
+        let n = |dx: f64, dy: f64, dz: f64| {
+            let q = tuples::point(p.x + dx, p.y + dy, p.z + dz);
+            noise::fractal_noise(&q, self.octaves, self.persistence) * self.noise_scale
+        };
+        let moved = tuples::point(
+            p.x + n(0.0, 0.0, 0.0),
+            p.y + n(5.2, 1.3, 7.1),
+            p.z + n(1.7, 9.2, 3.4),
+        );
+        inner.nested_pattern_at(&moved)
+    }
+
+    //bands along x that turbulence twists into veins
+    pub fn marble_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
+        let t = p.x + self.noise_scale * noise::turbulence(p, self.octaves, self.persistence);
+        let fraction = ((t * std::f64::consts::PI).sin() + 1.0) / 2.0;
+        blend(&self.color_a(p), &self.color_b(p), fraction)
+    }
+
+    //rings around the y axis like a ring pattern, each fading from a to b, warped by turbulence
+    pub fn wood_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
+        let r = (p.x * p.x + p.z * p.z).sqrt()
+            + self.noise_scale * noise::turbulence(p, self.octaves, self.persistence);
+        blend(&self.color_a(p), &self.color_b(p), r - r.floor())
+    }
+
+    pub fn blend_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
+        blend(&self.color_a(p), &self.color_b(p), self.weight)
+    }
+
     pub fn test_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
         tuples::color(p.x, p.y, p.z)
     }
 
-    pub fn pattern_at_shape(&self, s: &shapes::Shape, p: &tuples::Point) -> tuples::Color {
-        let local_point: tuples::Point = s.transform.inverse().tuple_multiply(&p);
-        let pattern_point: tuples::Point = self.transform.inverse().tuple_multiply(&local_point);
+    pub fn pattern_at(&self, p: &tuples::Point) -> tuples::Color {
         match self.pattern_type {
-            PatternType::Stripe => self.stripe_at(&pattern_point),
-            PatternType::PatternTest => self.test_pattern_at(&pattern_point),
-            PatternType::Gradient => self.gradient_pattern_at(&pattern_point),
-            PatternType::Ring => self.ring_pattern_at(&pattern_point),
-            PatternType::Checkers => self.checkers_pattern_at(&pattern_point),
+            PatternType::Stripe => self.stripe_at(p),
+            PatternType::PatternTest => self.test_pattern_at(p),
+            PatternType::Gradient => self.gradient_pattern_at(p),
+            PatternType::Ring => self.ring_pattern_at(p),
+            PatternType::Checkers => self.checkers_pattern_at(p),
+            PatternType::Image
+            | PatternType::UvCheckers
+            | PatternType::AlignCheck
+            | PatternType::CubeMap => self.mapped_pattern_at(p),
+            PatternType::Perturb => self.perturb_pattern_at(p),
+            PatternType::Marble => self.marble_pattern_at(p),
+            PatternType::Wood => self.wood_pattern_at(p),
+            PatternType::RadialGradient => self.radial_gradient_pattern_at(p),
+            PatternType::Blend => self.blend_pattern_at(p),
         }
     }
+
+    //for a pattern inside another, p is in the outer pattern's space
+    pub fn nested_pattern_at(&self, p: &tuples::Point) -> tuples::Color {
+        self.pattern_at(&self.transform_inverse.tuple_multiply(p))
+    }
+
+    pub fn pattern_at_shape(&self, s: &shapes::Shape, p: &tuples::Point) -> tuples::Color {
+        let local_point: tuples::Point = s.world_to_object(p);
+        if let (UvMapping::Mesh, Some((u, v))) =
+            (self.mapping, triangles::texture_uv_at(s, &local_point))
+        {
+            return self.uv_pattern_at(u, v);
+        }
+        let pattern_point: tuples::Point = self.transform_inverse.tuple_multiply(&local_point);
+        self.pattern_at(&pattern_point)
+    }
 }
 
 pub const PATTERN_DEFAULT: Pattern = Pattern {
     a: tuples::COLOR_WHITE,
     b: tuples::COLOR_BLACK,
+    weight: 0.5,
     transform: matrices::IDENTITY_MATRIX,
+    transform_inverse: matrices::IDENTITY_MATRIX,
     pattern_type: PatternType::Stripe,
+    texture: None,
+    filter: TextureFilter::Bilinear,
+    wrap: TextureWrap::Repeat,
+    mapping: UvMapping::Planar,
+    uv_width: 1.0,
+    uv_height: 1.0,
+    corners: [tuples::COLOR_BLACK; 4],
+    children: Vec::new(),
+    noise_scale: 1.0,
+    octaves: 4,
+    persistence: 0.5,
 };
 
 pub const PATTERN_PINK: Pattern = Pattern {
     a: tuples::COLOR_WHITE,
     b: tuples::COLOR_PINK,
+    weight: 0.5,
     transform: matrices::IDENTITY_MATRIX,
+    transform_inverse: matrices::IDENTITY_MATRIX,
     pattern_type: PatternType::Stripe,
+    texture: None,
+    filter: TextureFilter::Bilinear,
+    wrap: TextureWrap::Repeat,
+    mapping: UvMapping::Planar,
+    uv_width: 1.0,
+    uv_height: 1.0,
+    corners: [tuples::COLOR_BLACK; 4],
+    children: Vec::new(),
+    noise_scale: 1.0,
+    octaves: 4,
+    persistence: 0.5,
 };
 
 pub fn stripe_pattern(a: tuples::Color, b: tuples::Color) -> Pattern {
     Pattern {
         a: a,
         b: b,
-        transform: matrices::IDENTITY_MATRIX,
         pattern_type: PatternType::Stripe,
+        ..PATTERN_DEFAULT
     }
 }
 
@@ -106,8 +407,8 @@ pub fn gradient_pattern(a: tuples::Color, b: tuples::Color) -> Pattern {
     Pattern {
         a: a,
         b: b,
-        transform: matrices::IDENTITY_MATRIX,
         pattern_type: PatternType::Gradient,
+        ..PATTERN_DEFAULT
     }
 }
 
@@ -115,8 +416,8 @@ pub fn ring_pattern(a: tuples::Color, b: tuples::Color) -> Pattern {
     Pattern {
         a: a,
         b: b,
-        transform: matrices::IDENTITY_MATRIX,
         pattern_type: PatternType::Ring,
+        ..PATTERN_DEFAULT
     }
 }
 
@@ -124,11 +425,189 @@ pub fn checkers_pattern(a: tuples::Color, b: tuples::Color) -> Pattern {
     Pattern {
         a: a,
         b: b,
-        transform: matrices::IDENTITY_MATRIX,
         pattern_type: PatternType::Checkers,
+        ..PATTERN_DEFAULT
+    }
+}
+
+pub fn radial_gradient_pattern(a: tuples::Color, b: tuples::Color) -> Pattern {
+    Pattern {
+        a,
+        b,
+        pattern_type: PatternType::RadialGradient,
+        ..PATTERN_DEFAULT
+    }
+}
+
+//mixes two patterns at every point, evenly unless weight is changed
+pub fn blend_pattern(a: Pattern, b: Pattern) -> Pattern {
+    Pattern {
+        children: vec![Some(a), Some(b)],
+        pattern_type: PatternType::Blend,
+        ..PATTERN_DEFAULT
+    }
+}
+
+pub fn image_pattern(texture: canvas::PixelCanvas) -> Pattern {
+    let mut p = PATTERN_DEFAULT;
+    p.pattern_type = PatternType::Image;
+    p.texture = Some(Arc::new(texture));
+    p
+}
+
+pub fn uv_checkers_pattern(width: f64, height: f64, a: tuples::Color, b: tuples::Color) -> Pattern {
+    Pattern {
+        a,
+        b,
+        pattern_type: PatternType::UvCheckers,
+        uv_width: width,
+        uv_height: height,
+        ..PATTERN_DEFAULT
+    }
+}
+
+pub fn align_check_pattern(
+    main: tuples::Color,
+    ul: tuples::Color,
+    ur: tuples::Color,
+    bl: tuples::Color,
+    br: tuples::Color,
+) -> Pattern {
+    Pattern {
+        a: main,
+        pattern_type: PatternType::AlignCheck,
+        corners: [ul, ur, bl, br],
+        ..PATTERN_DEFAULT
+    }
+}
+
+pub fn cube_map_pattern(
+    left: Pattern,
+    front: Pattern,
+    right: Pattern,
+    back: Pattern,
+    up: Pattern,
+    down: Pattern,
+) -> Pattern {
+    Pattern {
+        pattern_type: PatternType::CubeMap,
+        mapping: UvMapping::Cube,
+        children: vec![
+            Some(left),
+            Some(front),
+            Some(right),
+            Some(back),
+            Some(up),
+            Some(down),
+        ],
+        ..PATTERN_DEFAULT
+    }
+}
+
+pub fn perturb_pattern(inner: Pattern, scale: f64) -> Pattern {
+    Pattern {
+        pattern_type: PatternType::Perturb,
+        children: vec![Some(inner)],
+        noise_scale: scale,
+        ..PATTERN_DEFAULT
+    }
+}
+
+pub fn marble_pattern(a: tuples::Color, b: tuples::Color) -> Pattern {
+    Pattern {
+        a,
+        b,
+        pattern_type: PatternType::Marble,
+        ..PATTERN_DEFAULT
+    }
+}
+
+pub fn wood_pattern(a: tuples::Color, b: tuples::Color) -> Pattern {
+    Pattern {
+        a,
+        b,
+        pattern_type: PatternType::Wood,
+        ..PATTERN_DEFAULT
+    }
+}
+
+//wraps a 2D pattern onto pattern space with the given mapping
+pub fn texture_map(uv_pattern: Pattern, mapping: UvMapping) -> Pattern {
+    Pattern {
+        mapping,
+        ..uv_pattern
     }
 }
 
+//a pattern nested in a 2D pattern sees the uv square laid on the xz plane,
+//so a nested 2D pattern with planar mapping lines up with the outer one
+fn uv_point(u: f64, v: f64) -> tuples::Point {
+    tuples::point(u, 0.0, v)
+}
+
+//u and v are left unwrapped so image patterns can choose to repeat or clamp
+pub fn planar_map(p: &tuples::Point) -> (f64, f64) {
+    (p.x, p.z)
+}
+
+pub fn spherical_map(p: &tuples::Point) -> (f64, f64) {
+    let theta = p.x.atan2(p.z);
+    let radius = tuples::vector(p.x, p.y, p.z).magnitude();
+    if radius == 0.0 {
+        return (0.0, 0.0);
+    }
+    let phi = (p.y / radius).acos();
+    let raw_u = theta / (2.0 * std::f64::consts::PI);
+    //flip u so it runs anticlockwise seen from above
+    let u = 1.0 - (raw_u + 0.5);
+    let v = 1.0 - phi / std::f64::consts::PI;
+    (u, v)
+}
+
+pub fn cylindrical_map(p: &tuples::Point) -> (f64, f64) {
+    let theta = p.x.atan2(p.z);
+    let raw_u = theta / (2.0 * std::f64::consts::PI);
+    let u = 1.0 - (raw_u + 0.5);
+    let v = p.y.rem_euclid(1.0);
+    (u, v)
+}
+
+pub fn face_from_point(p: &tuples::Point) -> CubeFace {
+    let coord = p.x.abs().max(p.y.abs()).max(p.z.abs());
+    if coord == p.x {
+        CubeFace::Right
+    } else if coord == -p.x {
+        CubeFace::Left
+    } else if coord == p.y {
+        CubeFace::Up
+    } else if coord == -p.y {
+        CubeFace::Down
+    } else if coord == p.z {
+        CubeFace::Front
+    } else {
+        CubeFace::Back
+    }
+}
+
+//each face reads the right way round when seen from inside the cube, as a skybox is,
+//with up being +y (or -z/+z for the top and bottom). cube mapped objects seen from
+//outside show every face mirrored left to right
+pub fn cube_uv(face: CubeFace, p: &tuples::Point) -> (f64, f64) {
+    let (u, v) = match face {
+        CubeFace::Front => (p.x + 1.0, p.y + 1.0),
+        CubeFace::Back => (1.0 - p.x, p.y + 1.0),
+        CubeFace::Left => (p.z + 1.0, p.y + 1.0),
+        CubeFace::Right => (1.0 - p.z, p.y + 1.0),
+        CubeFace::Up => (p.x + 1.0, 1.0 - p.z),
+        CubeFace::Down => (p.x + 1.0, p.z + 1.0),
+    };
+    (u.rem_euclid(2.0) / 2.0, v.rem_euclid(2.0) / 2.0)
+}
+
+fn blend(a: &tuples::Color, b: &tuples::Color, fraction: f64) -> tuples::Color {
+    a.add(&b.subtract(a).scalar_multiply(&fraction))
+}
+
 pub fn test_pattern() -> Pattern {
     let mut p = PATTERN_DEFAULT;
     p.pattern_type = PatternType::PatternTest;
@@ -195,7 +674,7 @@ mod tests {
     fn test_stripes_with_an_object_transformation() {
         //Stripes with an object transformation
         let mut s = spheres::sphere();
-        s.transform = transformations::matrix4_scaling(2.0, 2.0, 2.0);
+        s.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
         let stripe_color = PATTERN_DEFAULT.pattern_at_shape(&s, &tuples::point(1.5, 0.0, 0.0));
         assert_eq!(stripe_color.is_equal_to(&tuples::COLOR_WHITE), true);
     }
@@ -205,7 +684,7 @@ mod tests {
         //Stripes with a pattern transformation
         let s = spheres::sphere();
         let mut p = PATTERN_DEFAULT;
-        p.transform = transformations::matrix4_scaling(2.0, 2.0, 2.0);
+        p.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
         let stripe_color = p.pattern_at_shape(&s, &tuples::point(1.5, 0.0, 0.0));
         assert_eq!(stripe_color.is_equal_to(&tuples::COLOR_WHITE), true);
     }
@@ -214,9 +693,9 @@ mod tests {
     fn test_stripes_with_both_an_object_and_a_pattern_transformation() {
         //Stripes with both an object and a pattern transformation
         let mut s = spheres::sphere();
-        s.transform = transformations::matrix4_scaling(2.0, 2.0, 2.0);
+        s.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
         let mut p = PATTERN_DEFAULT;
-        p.transform = transformations::matrix4_translation(0.5, 0.0, 0.0);
+        p.set_transform(transformations::matrix4_translation(0.5, 0.0, 0.0));
         let stripe_color = p.pattern_at_shape(&s, &tuples::point(2.5, 0.0, 0.0));
         assert_eq!(stripe_color.is_equal_to(&tuples::COLOR_WHITE), true);
     }
@@ -233,15 +712,16 @@ mod tests {
         //Assigning a pattern transformation
         let mut p = test_pattern();
         let t = transformations::matrix4_translation(1.0, 2.0, 3.0);
-        p.transform = t;
-        assert_eq!(p.transform.is_equal_to(&t), true);
+        p.set_transform(t);
+        assert!(p.transform.is_equal_to(&t));
+        assert!(p.transform_inverse.is_equal_to(&t.inverse()));
     }
 
     #[test]
     fn test_a_pattern_with_an_object_transformation() {
         //A pattern with an object transformation
         let mut s = spheres::sphere();
-        s.transform = transformations::matrix4_scaling(2.0, 2.0, 2.0);
+        s.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
         let p = test_pattern();
         let c = p.pattern_at_shape(&s, &tuples::point(2.0, 3.0, 4.0));
         assert_eq!(c.is_equal_to(&tuples::color(1.0, 1.5, 2.0)), true);
@@ -252,7 +732,7 @@ mod tests {
         //A pattern with a pattern transformation
         let s = spheres::sphere();
         let mut p = test_pattern();
-        p.transform = transformations::matrix4_scaling(2.0, 2.0, 2.0);
+        p.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
         let c = p.pattern_at_shape(&s, &tuples::point(2.0, 3.0, 4.0));
         assert_eq!(c.is_equal_to(&tuples::color(1.0, 1.5, 2.0)), true);
     }
@@ -261,9 +741,9 @@ mod tests {
     fn test_a_pattern_with_both_an_object_and_a_pattern_transformation() {
         //A pattern with both an object and a pattern transformation
         let mut s = spheres::sphere();
-        s.transform = transformations::matrix4_scaling(2.0, 2.0, 2.0);
+        s.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
         let mut p = test_pattern();
-        p.transform = transformations::matrix4_translation(0.5, 1.0, 1.5);
+        p.set_transform(transformations::matrix4_translation(0.5, 1.0, 1.5));
         let c = p.pattern_at_shape(&s, &tuples::point(2.5, 3.0, 3.5));
         assert_eq!(c.is_equal_to(&tuples::color(0.75, 0.5, 0.25)), true);
     }
@@ -307,4 +787,570 @@ mod tests {
         assert_eq!(c2.is_equal_to(&tuples::COLOR_WHITE), true);
         assert_eq!(c3.is_equal_to(&tuples::COLOR_BLACK), true);
     }
+
+    fn gradient_texture() -> canvas::PixelCanvas {
+        //the 10x10 ppm from the book, each pixel one step brighter than the last
+        let mut c = canvas::pixel_canvas(10, 10, tuples::COLOR_BLACK);
+        for y in 0..10 {
+            for x in 0..10 {
+                let v = ((x + y) % 10) as f64 / 10.0;
+                c.pixel_set(&x, &y, tuples::color(v, v, v));
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn test_an_image_pattern_samples_by_u_and_v() {
+        //Checker pattern in 2D is replaced by the image pixel under u and v
+        let mut p = image_pattern(gradient_texture());
+        p.filter = TextureFilter::Nearest;
+        let tests = [
+            (0.0, 0.0, 0.9),
+            (0.3, 0.0, 0.2),
+            (0.6, 0.3, 0.1),
+            (1.0, 1.0, 0.9),
+        ];
+        for (u, v, expected) in tests.iter() {
+            let col = p.uv_image_at(*u, *v);
+            assert!(col.is_equal_to(&tuples::color(*expected, *expected, *expected)));
+        }
+    }
+
+    #[test]
+    fn test_bilinear_filtering_blends_neighbouring_pixels() {
+        //Bilinear filtering blends the four pixels around u and v
+        let mut c = canvas::pixel_canvas(2, 2, tuples::COLOR_BLACK);
+        c.pixel_set(&1, &1, tuples::COLOR_WHITE);
+        let mut p = image_pattern(c);
+        assert_eq!(p.filter, TextureFilter::Bilinear);
+        p.wrap = TextureWrap::Clamp;
+        //the bottom right pixel is white
+        assert!(p.uv_image_at(1.0, 0.0).is_equal_to(&tuples::COLOR_WHITE));
+        assert!(p
+            .uv_image_at(0.5, 0.0)
+            .is_equal_to(&tuples::color(0.5, 0.5, 0.5)));
+        assert!(p
+            .uv_image_at(0.5, 0.5)
+            .is_equal_to(&tuples::color(0.25, 0.25, 0.25)));
+        p.filter = TextureFilter::Nearest;
+        assert!(p.uv_image_at(0.4, 0.0).is_equal_to(&tuples::COLOR_BLACK));
+    }
+
+    #[test]
+    fn test_image_patterns_wrap_or_clamp_outside_the_image() {
+        //Outside 0..1 an image either repeats or clamps to its edges
+        let mut p = image_pattern(gradient_texture());
+        p.filter = TextureFilter::Nearest;
+        assert_eq!(p.wrap, TextureWrap::Repeat);
+        assert!(p
+            .uv_image_at(1.3, -1.0)
+            .is_equal_to(&p.uv_image_at(0.3, 0.0)));
+        p.wrap = TextureWrap::Clamp;
+        assert!(p
+            .uv_image_at(1.3, -1.0)
+            .is_equal_to(&p.uv_image_at(1.0, 0.0)));
+        assert!(p
+            .uv_image_at(-5.0, 7.0)
+            .is_equal_to(&p.uv_image_at(0.0, 1.0)));
+    }
+
+    #[test]
+    fn test_an_image_pattern_on_a_shape() {
+        //An image pattern is laid on the xz plane of the pattern space
+        let mut p = image_pattern(gradient_texture());
+        p.filter = TextureFilter::Nearest;
+        p.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
+        let s = spheres::sphere();
+        let col = p.pattern_at_shape(&s, &tuples::point(0.6, 5.0, 0.0));
+        assert!(col.is_equal_to(&tuples::color(0.2, 0.2, 0.2)));
+    }
+
+    #[test]
+    fn test_checker_pattern_in_2d() {
+        //Checker pattern in 2D
+        let p = uv_checkers_pattern(2.0, 2.0, tuples::COLOR_BLACK, tuples::COLOR_WHITE);
+        let tests = [
+            (0.0, 0.0, tuples::COLOR_BLACK),
+            (0.5, 0.0, tuples::COLOR_WHITE),
+            (0.0, 0.5, tuples::COLOR_WHITE),
+            (0.5, 0.5, tuples::COLOR_BLACK),
+            (1.0, 1.0, tuples::COLOR_BLACK),
+        ];
+        for (u, v, expected) in tests.iter() {
+            assert!(p.uv_pattern_at(*u, *v).is_equal_to(expected));
+        }
+    }
+
+    #[test]
+    fn test_using_a_spherical_mapping_on_a_3d_point() {
+        //Using a spherical mapping on a 3D point
+        let tests = [
+            (tuples::point(0.0, 0.0, -1.0), 0.0, 0.5),
+            (tuples::point(1.0, 0.0, 0.0), 0.25, 0.5),
+            (tuples::point(0.0, 0.0, 1.0), 0.5, 0.5),
+            (tuples::point(-1.0, 0.0, 0.0), 0.75, 0.5),
+            (tuples::point(0.0, 1.0, 0.0), 0.5, 1.0),
+            (tuples::point(0.0, -1.0, 0.0), 0.5, 0.0),
+            (
+                tuples::point(2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0, 0.0),
+                0.25,
+                0.75,
+            ),
+        ];
+        for (p, u, v) in tests.iter() {
+            let (pu, pv) = spherical_map(p);
+            assert!((pu - u).abs() < tuples::EPSILON);
+            assert!((pv - v).abs() < tuples::EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_using_a_texture_map_pattern_with_a_spherical_map() {
+        //Using a texture map pattern with a spherical map
+        let checkers = uv_checkers_pattern(16.0, 8.0, tuples::COLOR_BLACK, tuples::COLOR_WHITE);
+        let p = texture_map(checkers, UvMapping::Spherical);
+        let tests = [
+            (tuples::point(0.4315, 0.4670, 0.7719), tuples::COLOR_WHITE),
+            (tuples::point(-0.9654, 0.2552, -0.0534), tuples::COLOR_BLACK),
+            (tuples::point(0.1039, 0.7090, 0.6975), tuples::COLOR_WHITE),
+            (
+                tuples::point(-0.4986, -0.7856, -0.3663),
+                tuples::COLOR_BLACK,
+            ),
+            (tuples::point(-0.0317, -0.9395, 0.3411), tuples::COLOR_BLACK),
+            (tuples::point(0.4809, -0.7721, 0.4154), tuples::COLOR_BLACK),
+            (tuples::point(0.0285, -0.9612, -0.2745), tuples::COLOR_BLACK),
+            (
+                tuples::point(-0.5734, -0.2162, -0.7903),
+                tuples::COLOR_WHITE,
+            ),
+            (tuples::point(0.7688, -0.1470, 0.6223), tuples::COLOR_BLACK),
+            (tuples::point(-0.7652, 0.2175, 0.6060), tuples::COLOR_BLACK),
+        ];
+        for (point, expected) in tests.iter() {
+            assert!(p.pattern_at(point).is_equal_to(expected));
+        }
+    }
+
+    #[test]
+    fn test_using_a_planar_mapping_on_a_3d_point() {
+        //Using a planar mapping on a 3D point, uv patterns tile themselves
+        assert_eq!(planar_map(&tuples::point(0.25, 0.0, 0.5)), (0.25, 0.5));
+        assert_eq!(planar_map(&tuples::point(1.25, 0.0, -0.5)), (1.25, -0.5));
+        let p = texture_map(
+            uv_checkers_pattern(2.0, 2.0, tuples::COLOR_BLACK, tuples::COLOR_WHITE),
+            UvMapping::Planar,
+        );
+        let a = p.pattern_at(&tuples::point(0.25, 7.0, 0.75));
+        let b = p.pattern_at(&tuples::point(-0.75, 0.0, 1.75));
+        assert!(a.is_equal_to(&tuples::COLOR_WHITE));
+        assert!(b.is_equal_to(&tuples::COLOR_WHITE));
+    }
+
+    #[test]
+    fn test_using_a_cylindrical_mapping_on_a_3d_point() {
+        //Using a cylindrical mapping on a 3D point
+        let tests = [
+            (tuples::point(0.0, 0.0, -1.0), 0.0, 0.0),
+            (tuples::point(0.0, 0.5, -1.0), 0.0, 0.5),
+            (tuples::point(0.0, 1.0, -1.0), 0.0, 0.0),
+            (
+                tuples::point(
+                    std::f64::consts::FRAC_1_SQRT_2,
+                    0.5,
+                    -std::f64::consts::FRAC_1_SQRT_2,
+                ),
+                0.125,
+                0.5,
+            ),
+            (tuples::point(1.0, 0.5, 0.0), 0.25, 0.5),
+            (
+                tuples::point(
+                    std::f64::consts::FRAC_1_SQRT_2,
+                    0.5,
+                    std::f64::consts::FRAC_1_SQRT_2,
+                ),
+                0.375,
+                0.5,
+            ),
+            (tuples::point(0.0, -0.25, 1.0), 0.5, 0.75),
+            (
+                tuples::point(
+                    -std::f64::consts::FRAC_1_SQRT_2,
+                    0.5,
+                    std::f64::consts::FRAC_1_SQRT_2,
+                ),
+                0.625,
+                0.5,
+            ),
+            (tuples::point(-1.0, 1.25, 0.0), 0.75, 0.25),
+            (
+                tuples::point(
+                    -std::f64::consts::FRAC_1_SQRT_2,
+                    0.5,
+                    -std::f64::consts::FRAC_1_SQRT_2,
+                ),
+                0.875,
+                0.5,
+            ),
+        ];
+        for (p, u, v) in tests.iter() {
+            let (pu, pv) = cylindrical_map(p);
+            assert!((pu - u).abs() < tuples::EPSILON);
+            assert!((pv - v).abs() < tuples::EPSILON);
+        }
+    }
+
+    fn align_colors() -> [tuples::Color; 8] {
+        //red, yellow, brown, green, cyan, blue, purple, white
+        [
+            tuples::color(1.0, 0.0, 0.0),
+            tuples::color(1.0, 1.0, 0.0),
+            tuples::color(1.0, 0.5, 0.0),
+            tuples::color(0.0, 1.0, 0.0),
+            tuples::color(0.0, 1.0, 1.0),
+            tuples::color(0.0, 0.0, 1.0),
+            tuples::color(1.0, 0.0, 1.0),
+            tuples::color(1.0, 1.0, 1.0),
+        ]
+    }
+
+    #[test]
+    fn test_layout_of_the_align_check_pattern() {
+        //Layout of the "align check" pattern
+        let [red, yellow, _, green, cyan, ..] = align_colors();
+        let main = tuples::COLOR_WHITE;
+        let p = align_check_pattern(main, red, yellow, green, cyan);
+        let tests = [
+            (0.5, 0.5, main),
+            (0.1, 0.9, red),
+            (0.9, 0.9, yellow),
+            (0.1, 0.1, green),
+            (0.9, 0.1, cyan),
+        ];
+        for (u, v, expected) in tests.iter() {
+            assert!(p.uv_pattern_at(*u, *v).is_equal_to(expected));
+        }
+    }
+
+    #[test]
+    fn test_identifying_the_face_of_a_cube_from_a_point() {
+        //Identifying the face of a cube from a point
+        let tests = [
+            (tuples::point(-1.0, 0.5, -0.25), CubeFace::Left),
+            (tuples::point(1.1, -0.75, 0.8), CubeFace::Right),
+            (tuples::point(0.1, 0.6, 0.9), CubeFace::Front),
+            (tuples::point(-0.7, 0.0, -2.0), CubeFace::Back),
+            (tuples::point(0.5, 1.0, 0.9), CubeFace::Up),
+            (tuples::point(-0.2, -1.3, 1.1), CubeFace::Down),
+        ];
+        for (p, face) in tests.iter() {
+            assert_eq!(face_from_point(p), *face);
+        }
+    }
+
+    #[test]
+    fn test_uv_mapping_the_faces_of_a_cube() {
+        //UV mapping the front, back, left, right, upper and lower faces of a cube
+        let tests = [
+            (CubeFace::Front, tuples::point(-0.5, 0.5, 1.0), 0.25, 0.75),
+            (CubeFace::Front, tuples::point(0.5, -0.5, 1.0), 0.75, 0.25),
+            (CubeFace::Back, tuples::point(0.5, 0.5, -1.0), 0.25, 0.75),
+            (CubeFace::Back, tuples::point(-0.5, -0.5, -1.0), 0.75, 0.25),
+            (CubeFace::Left, tuples::point(-1.0, 0.5, -0.5), 0.25, 0.75),
+            (CubeFace::Left, tuples::point(-1.0, -0.5, 0.5), 0.75, 0.25),
+            (CubeFace::Right, tuples::point(1.0, 0.5, 0.5), 0.25, 0.75),
+            (CubeFace::Right, tuples::point(1.0, -0.5, -0.5), 0.75, 0.25),
+            (CubeFace::Up, tuples::point(-0.5, 1.0, -0.5), 0.25, 0.75),
+            (CubeFace::Up, tuples::point(0.5, 1.0, 0.5), 0.75, 0.25),
+            (CubeFace::Down, tuples::point(-0.5, -1.0, 0.5), 0.25, 0.75),
+            (CubeFace::Down, tuples::point(0.5, -1.0, -0.5), 0.75, 0.25),
+        ];
+        for (face, p, u, v) in tests.iter() {
+            let (pu, pv) = cube_uv(*face, p);
+            assert!((pu - u).abs() < tuples::EPSILON);
+            assert!((pv - v).abs() < tuples::EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_finding_the_colors_on_a_mapped_cube() {
+        //Finding the colors on a mapped cube
+        let [red, yellow, brown, green, cyan, blue, purple, white] = align_colors();
+        let p = cube_map_pattern(
+            align_check_pattern(yellow, cyan, red, blue, brown),
+            align_check_pattern(cyan, red, yellow, brown, green),
+            align_check_pattern(red, yellow, purple, green, white),
+            align_check_pattern(green, purple, cyan, white, blue),
+            align_check_pattern(brown, cyan, purple, red, yellow),
+            align_check_pattern(purple, brown, green, blue, white),
+        );
+        let tests = [
+            //left
+            (tuples::point(-1.0, 0.0, 0.0), yellow),
+            (tuples::point(-1.0, 0.9, -0.9), cyan),
+            (tuples::point(-1.0, 0.9, 0.9), red),
+            (tuples::point(-1.0, -0.9, -0.9), blue),
+            (tuples::point(-1.0, -0.9, 0.9), brown),
+            //front
+            (tuples::point(0.0, 0.0, 1.0), cyan),
+            (tuples::point(-0.9, 0.9, 1.0), red),
+            (tuples::point(0.9, 0.9, 1.0), yellow),
+            (tuples::point(-0.9, -0.9, 1.0), brown),
+            (tuples::point(0.9, -0.9, 1.0), green),
+            //right
+            (tuples::point(1.0, 0.0, 0.0), red),
+            (tuples::point(1.0, 0.9, 0.9), yellow),
+            (tuples::point(1.0, 0.9, -0.9), purple),
+            (tuples::point(1.0, -0.9, 0.9), green),
+            (tuples::point(1.0, -0.9, -0.9), white),
+            //back
+            (tuples::point(0.0, 0.0, -1.0), green),
+            (tuples::point(0.9, 0.9, -1.0), purple),
+            (tuples::point(-0.9, 0.9, -1.0), cyan),
+            (tuples::point(0.9, -0.9, -1.0), white),
+            (tuples::point(-0.9, -0.9, -1.0), blue),
+            //up
+            (tuples::point(0.0, 1.0, 0.0), brown),
+            (tuples::point(-0.9, 1.0, -0.9), cyan),
+            (tuples::point(0.9, 1.0, -0.9), purple),
+            (tuples::point(-0.9, 1.0, 0.9), red),
+            (tuples::point(0.9, 1.0, 0.9), yellow),
+            //down
+            (tuples::point(0.0, -1.0, 0.0), purple),
+            (tuples::point(-0.9, -1.0, 0.9), brown),
+            (tuples::point(0.9, -1.0, 0.9), green),
+            (tuples::point(-0.9, -1.0, -0.9), blue),
+            (tuples::point(0.9, -1.0, -0.9), white),
+        ];
+        for (point, expected) in tests.iter() {
+            assert!(p.pattern_at(point).is_equal_to(expected));
+        }
+    }
+
+    #[test]
+    fn test_a_perturbed_pattern_without_noise_is_its_inner_pattern() {
+        //A perturbed pattern with no noise scale matches the inner pattern and its transform
+        let mut inner = stripe_pattern(tuples::COLOR_WHITE, tuples::COLOR_BLACK);
+        inner.set_transform(transformations::matrix4_scaling(0.5, 1.0, 1.0));
+        let p = perturb_pattern(inner.clone(), 0.0);
+        for i in 0..20 {
+            let point = tuples::point(i as f64 * 0.13, 0.4, -0.7);
+            assert!(p
+                .pattern_at(&point)
+                .is_equal_to(&inner.nested_pattern_at(&point)));
+        }
+    }
+
+    #[test]
+    fn test_a_perturbed_pattern_moves_its_edges() {
+        //Perturbing stripes moves the edges between them, but not far
+        let inner = stripe_pattern(tuples::COLOR_WHITE, tuples::COLOR_BLACK);
+        let p = perturb_pattern(inner.clone(), 0.2);
+        let mut differs = 0;
+        for i in 0..200 {
+            let point = tuples::point(i as f64 * 0.05 + 0.01, i as f64 * 0.37, i as f64 * 0.11);
+            let plain = inner.pattern_at(&point);
+            if !p.pattern_at(&point).is_equal_to(&plain) {
+                differs += 1;
+                //only points near an edge can change
+                let edge = (point.x - point.x.round()).abs();
+                assert!(edge < 0.2);
+            }
+        }
+        assert!(differs > 0);
+    }
+
+    #[test]
+    fn test_marble_and_wood_without_noise() {
+        //Without noise marble is a sine of x and wood is rings around y
+        let mut marble = marble_pattern(tuples::COLOR_BLACK, tuples::COLOR_WHITE);
+        marble.noise_scale = 0.0;
+        let tests = [(0.0, 0.5), (0.5, 1.0), (1.5, 0.0), (-0.5, 0.0)];
+        for (x, expected) in tests.iter() {
+            let col = marble.pattern_at(&tuples::point(*x, 3.0, -2.0));
+            assert!(col.is_equal_to(&tuples::color(*expected, *expected, *expected)));
+        }
+        let mut wood = wood_pattern(tuples::COLOR_BLACK, tuples::COLOR_WHITE);
+        wood.noise_scale = 0.0;
+        let tests = [(0.0, 0.0, 0.0), (0.3, 0.4, 0.5), (1.25, 0.0, 0.25)];
+        for (x, z, expected) in tests.iter() {
+            let col = wood.pattern_at(&tuples::point(*x, 7.0, *z));
+            assert!(col.is_equal_to(&tuples::color(*expected, *expected, *expected)));
+        }
+    }
+
+    #[test]
+    fn test_marble_veins_are_twisted_by_turbulence() {
+        //With noise, marble is no longer the same all along a stripe
+        let marble = marble_pattern(tuples::COLOR_BLACK, tuples::COLOR_WHITE);
+        let first = marble.pattern_at(&tuples::point(0.25, 0.1, 0.1));
+        let mut differs = false;
+        for i in 1..20 {
+            let col = marble.pattern_at(&tuples::point(0.25, i as f64 * 0.3 + 0.1, 0.1));
+            if !col.is_equal_to(&first) {
+                differs = true;
+            }
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn test_a_stripe_of_checkers() {
+        //A pattern slot can hold another pattern, looked up in its own space
+        let mut checkers = checkers_pattern(tuples::COLOR_RED, tuples::COLOR_BLACK);
+        checkers.set_transform(transformations::matrix4_scaling(0.25, 0.25, 0.25));
+        let mut p = stripe_pattern(tuples::COLOR_WHITE, tuples::COLOR_WHITE);
+        p.set_nested(0, checkers);
+        let tests = [
+            (tuples::point(0.1, 0.1, 0.1), tuples::COLOR_RED),
+            (tuples::point(0.3, 0.1, 0.1), tuples::COLOR_BLACK),
+            (tuples::point(0.6, 0.1, 0.1), tuples::COLOR_RED),
+            (tuples::point(0.6, 0.3, 0.1), tuples::COLOR_BLACK),
+            (tuples::point(1.5, 0.1, 0.1), tuples::COLOR_WHITE),
+        ];
+        for (point, expected) in tests.iter() {
+            assert!(p.pattern_at(point).is_equal_to(expected));
+        }
+    }
+
+    #[test]
+    fn test_nested_patterns_combine_their_transformations() {
+        //A nested pattern's transform applies inside the outer pattern's transform
+        let mut inner = stripe_pattern(tuples::COLOR_RED, tuples::COLOR_BLACK);
+        inner.set_transform(transformations::matrix4_scaling(0.5, 1.0, 1.0));
+        let mut outer = stripe_pattern(tuples::COLOR_WHITE, tuples::COLOR_WHITE);
+        outer.set_nested(0, inner);
+        outer.set_transform(transformations::matrix4_scaling(2.0, 1.0, 1.0));
+        let mut s = spheres::sphere();
+        s.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
+        //x of 2.5 is 1.25 in object space, 0.625 in the outer pattern and 1.25 in the inner one
+        let col = outer.pattern_at_shape(&s, &tuples::point(2.5, 0.0, 0.0));
+        assert!(col.is_equal_to(&tuples::COLOR_BLACK));
+        let col = outer.pattern_at_shape(&s, &tuples::point(1.5, 0.0, 0.0));
+        assert!(col.is_equal_to(&tuples::COLOR_RED));
+    }
+
+    #[test]
+    fn test_a_blend_pattern_averages_two_patterns() {
+        //Blending stripes along x with stripes along z averages them, or weights them
+        let a = stripe_pattern(tuples::COLOR_WHITE, tuples::COLOR_BLACK);
+        let mut b = stripe_pattern(tuples::COLOR_WHITE, tuples::COLOR_BLACK);
+        b.set_transform(transformations::matrix4_rotation_y_rad(
+            std::f64::consts::PI / 2.0,
+        ));
+        let mut p = blend_pattern(a, b);
+        let tests = [
+            (tuples::point(0.5, 0.0, -0.5), 1.0),
+            (tuples::point(1.5, 0.0, -0.5), 0.5),
+            (tuples::point(0.5, 0.0, -1.5), 0.5),
+            (tuples::point(1.5, 0.0, -1.5), 0.0),
+        ];
+        for (point, expected) in tests.iter() {
+            assert!(p
+                .pattern_at(point)
+                .is_equal_to(&tuples::color(*expected, *expected, *expected)));
+        }
+        p.weight = 0.25;
+        assert!(p
+            .pattern_at(&tuples::point(1.5, 0.0, -0.5))
+            .is_equal_to(&tuples::color(0.25, 0.25, 0.25)));
+    }
+
+    #[test]
+    fn test_a_radial_gradient_fades_out_from_the_y_axis() {
+        //A radial gradient blends with the distance from the y axis in each unit ring
+        let p = radial_gradient_pattern(tuples::COLOR_WHITE, tuples::COLOR_BLACK);
+        let tests = [
+            (tuples::point(0.0, 5.0, 0.0), 1.0),
+            (tuples::point(0.25, 0.0, 0.0), 0.75),
+            (tuples::point(0.0, -2.0, 0.5), 0.5),
+            (tuples::point(0.6, 0.0, 0.8), 1.0),
+            (tuples::point(-0.9, 0.0, 1.2), 0.5),
+        ];
+        for (point, expected) in tests.iter() {
+            assert!(p
+                .pattern_at(point)
+                .is_equal_to(&tuples::color(*expected, *expected, *expected)));
+        }
+    }
+
+    #[test]
+    fn test_a_mesh_mapping_uses_a_triangles_texture_coordinates() {
+        //A mesh mapping looks up the triangle's own uv, and falls back to planar elsewhere
+        let mut p = uv_checkers_pattern(2.0, 2.0, tuples::COLOR_BLACK, tuples::COLOR_WHITE);
+        p.mapping = UvMapping::Mesh;
+        let mut t = crate::triangles::triangle(
+            tuples::point(0.0, 0.0, 0.0),
+            tuples::point(1.0, 0.0, 0.0),
+            tuples::point(0.0, 1.0, 0.0),
+        );
+        t.triangle.as_mut().unwrap().uvs = Some([(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]);
+        let near_corner = tuples::point(0.1, 0.1, 0.0);
+        let along_u = tuples::point(0.8, 0.1, 0.0);
+        assert!(p
+            .pattern_at_shape(&t, &near_corner)
+            .is_equal_to(&tuples::COLOR_BLACK));
+        assert!(p
+            .pattern_at_shape(&t, &along_u)
+            .is_equal_to(&tuples::COLOR_WHITE));
+        //a sphere has no texture coordinates, so x and z pick the checker
+        let s = spheres::sphere();
+        assert!(p
+            .pattern_at_shape(&s, &tuples::point(0.2, 0.1, 0.3))
+            .is_equal_to(&tuples::COLOR_BLACK));
+    }
+
+    #[test]
+    fn test_a_uv_checker_can_hold_a_nested_pattern() {
+        //A uv checker slot can hold a pattern, which sees the uv square on the xz plane
+        let mut stripes = stripe_pattern(tuples::COLOR_RED, tuples::color(0.0, 0.0, 1.0));
+        stripes.set_transform(transformations::matrix4_scaling(0.25, 1.0, 1.0));
+        let mut p = uv_checkers_pattern(2.0, 2.0, tuples::COLOR_WHITE, tuples::COLOR_BLACK);
+        p.set_nested(0, stripes);
+        let tests = [
+            ((0.1, 0.1), tuples::COLOR_RED),
+            ((0.3, 0.1), tuples::color(0.0, 0.0, 1.0)),
+            ((0.6, 0.1), tuples::COLOR_BLACK),
+            ((0.6, 0.6), tuples::COLOR_RED),
+        ];
+        for ((u, v), expected) in tests.iter() {
+            assert!(p.uv_pattern_at(*u, *v).is_equal_to(expected));
+        }
+        //and a nested 2D pattern lines up with the outer one's uv
+        let mut outer = uv_checkers_pattern(1.0, 1.0, tuples::COLOR_WHITE, tuples::COLOR_BLACK);
+        outer.set_nested(
+            0,
+            uv_checkers_pattern(4.0, 4.0, tuples::COLOR_RED, tuples::COLOR_WHITE),
+        );
+        assert!(outer
+            .uv_pattern_at(0.1, 0.1)
+            .is_equal_to(&tuples::COLOR_RED));
+        assert!(outer
+            .uv_pattern_at(0.3, 0.1)
+            .is_equal_to(&tuples::COLOR_WHITE));
+    }
+
+    #[test]
+    fn test_align_check_corners_and_images_use_nested_patterns() {
+        //An align check corner, and an image with no texture, take their nested patterns
+        let mut p = align_check_pattern(
+            tuples::COLOR_WHITE,
+            tuples::COLOR_RED,
+            tuples::COLOR_RED,
+            tuples::COLOR_RED,
+            tuples::COLOR_RED,
+        );
+        p.set_nested(4, stripe_pattern(tuples::COLOR_BLACK, tuples::COLOR_BLACK));
+        assert!(p.uv_pattern_at(0.9, 0.1).is_equal_to(&tuples::COLOR_BLACK));
+        assert!(p.uv_pattern_at(0.1, 0.1).is_equal_to(&tuples::COLOR_RED));
+        assert!(p.uv_pattern_at(0.5, 0.5).is_equal_to(&tuples::COLOR_WHITE));
+        let mut image = PATTERN_DEFAULT;
+        image.pattern_type = PatternType::Image;
+        image.set_nested(0, stripe_pattern(tuples::COLOR_RED, tuples::COLOR_RED));
+        assert!(image
+            .uv_pattern_at(0.5, 0.5)
+            .is_equal_to(&tuples::COLOR_RED));
+    }
 }
@@ -1,3 +1,4 @@
+use crate::bounds;
 use crate::intersections;
 use crate::rays;
 use crate::shapes;
@@ -18,6 +19,13 @@ pub fn local_intersect(
     Ok(vec![intersections::intersection(t, s.clone())])
 }
 
+pub fn local_bounds() -> bounds::BoundingBox {
+    bounds::bounding_box(
+        tuples::point(-f64::INFINITY, 0.0, -f64::INFINITY),
+        tuples::point(f64::INFINITY, 0.0, f64::INFINITY),
+    )
+}
+
 pub fn local_normal_at() -> tuples::Vector {
     tuples::vector(0.0, 1.0, 0.0)
 }
@@ -30,6 +38,7 @@ mod tests {
         format!("{}", std::any::type_name::<T>())
     }
 
+    #[test]
     fn test_normal_of_plane_is_constant_everywhere() {
         //The normal of a plane is constant everywhere
         let s = plane();
@@ -3,21 +3,41 @@ use std::f64::consts::PI;
 use std::fs;
 use std::time::Instant;
 
-use crate::camera;
-use crate::lights;
-use crate::materials;
-use crate::patterns;
-use crate::planes;
-use crate::shapes;
-use crate::spheres;
-use crate::transformations;
-use crate::tuples;
-use crate::worlds;
+use rust_ray_tracer_challenge::camera;
+use rust_ray_tracer_challenge::lights;
+use rust_ray_tracer_challenge::materials;
+use rust_ray_tracer_challenge::patterns;
+use rust_ray_tracer_challenge::planes;
+use rust_ray_tracer_challenge::shapes;
+use rust_ray_tracer_challenge::spheres;
+use rust_ray_tracer_challenge::transformations;
+use rust_ray_tracer_challenge::tuples;
+use rust_ray_tracer_challenge::worlds;
 
 pub fn world_main(w: u32, h: u32) {
     println!("chapter 11 patterns");
     let start1 = Instant::now();
+    let (world, c) = scene(w, h);
+    let image = c.render_percent_message(world, 0.01);
+    let duration1 = start1.elapsed();
+    println!("Time to calculate data: {:?}", duration1);
+
+    let start2 = Instant::now();
+    let data = image.ppm_get();
+    let duration2 = start2.elapsed();
+    println!("Time to generate file data: {:?}", duration2);
+
+    let start3 = Instant::now();
+    let f = save(data);
+    match f {
+        Ok(file) => file,
+        Err(error) => panic!("Problem saving the file: {:?}", error),
+    };
+    let duration3 = start3.elapsed();
+    println!("Time to save file: {:?}", duration3);
+}
 
+pub fn scene(w: u32, h: u32) -> (worlds::World, camera::Camera) {
     let mut world = worlds::world_default();
     world.objects = vec![
         shape_floor(),
@@ -29,33 +49,17 @@ pub fn world_main(w: u32, h: u32) {
         shape_sphere_left(),
     ];
 
-    world.light = vec![lights::LightPoint {
-        position: tuples::point(-1.5, 5.0, -1.5),
-        intensity: tuples::COLOR_WHITE,
-    }];
+    world.light = vec![lights::light_point(
+        tuples::point(-1.5, 5.0, -1.5),
+        tuples::COLOR_WHITE,
+    )];
 
     let mut c = camera::camera(w, h, PI / 3.0);
     let from = tuples::point(-3.0, 2.0, -5.0);
     let to = tuples::point(0.0, 1.0, 0.0);
     let up = tuples::vector(0.0, 1.0, 0.0);
-    c.transform = transformations::view_transform(&from, &to, &up);
-    let image = c.render_percent_message(world, 0.01);
-    let duration1 = start1.elapsed();
-    println!("Time to calculate data: {:?}", duration1);
-
-    let start2 = Instant::now();
-    let data = image.ppm_get();
-    let duration2 = start2.elapsed();
-    println!("Time to generate file data: {:?}", duration2);
-
-    let start3 = Instant::now();
-    let f = save(data);
-    let _f = match f {
-        Ok(file) => file,
-        Err(error) => panic!("Problem saving the file: {:?}", error),
-    };
-    let duration3 = start3.elapsed();
-    println!("Time to save file: {:?}", duration3);
+    c.set_transform(transformations::view_transform(&from, &to, &up));
+    (world, c)
 }
 
 fn save(string: String) -> std::io::Result<()> {
@@ -80,12 +84,12 @@ pub fn shape_floor() -> shapes::Shape {
     let c2 = tuples::color(0.2, 0.05, 0.05);
     let mut pat = patterns::ring_pattern(c1, c2);
     let mut mat = materials::MATERIAL_DEFAULT;
-    pat.transform = transformations::matrix4_transform_chain(
+    pat.set_transform(transformations::matrix4_transform_chain(
         &(vec![
             transformations::matrix4_scaling(0.2, 0.2, 0.2),
             transformations::matrix4_translation(0.5, 0.0, 0.0),
         ]),
-    );
+    ));
     mat.pattern = Some(pat);
     mat.reflective = 0.5;
     shape.material = mat;
@@ -96,10 +100,10 @@ pub fn shape_wall_behind() -> shapes::Shape {
     let mut shape = planes::plane();
     let t1 = transformations::matrix4_rotation_x_rad(PI / -2.0);
     let t2 = transformations::matrix4_translation(0.0, 0.0, 2.0);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t1, t2]));
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t1, t2])));
     let mut mat = material_floor();
     let mut pat = patterns::PATTERN_PINK;
-    pat.transform = transformations::matrix4_rotation_y_rad(PI / 4.0);
+    pat.set_transform(transformations::matrix4_rotation_y_rad(PI / 4.0));
     mat.pattern = Some(pat);
     shape.material = mat;
     shape
@@ -110,15 +114,17 @@ pub fn shape_wall_behind_right() -> shapes::Shape {
     let t1 = transformations::matrix4_rotation_x_rad(PI / -2.0);
     let t2 = transformations::matrix4_rotation_y_rad(PI / 2.0);
     let t3 = transformations::matrix4_translation(2.5, 0.0, 2.0);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t1, t2, t3]));
+    shape.set_transform(transformations::matrix4_transform_chain(
+        &(vec![t1, t2, t3]),
+    ));
     let mut mat = material_floor();
     let mut pat = patterns::checkers_pattern(tuples::COLOR_WHITE, tuples::COLOR_BLACK);
-    pat.transform = transformations::matrix4_transform_chain(
+    pat.set_transform(transformations::matrix4_transform_chain(
         &(vec![
             transformations::matrix4_scaling(0.2, 5.0, 0.2),
             transformations::matrix4_rotation_y_rad(PI / 16.0),
         ]),
-    );
+    ));
     mat.pattern = Some(pat);
     shape.material = mat;
     shape
@@ -126,7 +132,7 @@ pub fn shape_wall_behind_right() -> shapes::Shape {
 
 pub fn shape_sphere_middle() -> shapes::Shape {
     let mut shape = spheres::sphere();
-    shape.transform = transformations::matrix4_translation(-0.5, 1.0, 0.5);
+    shape.set_transform(transformations::matrix4_translation(-0.5, 1.0, 0.5));
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(0.1, 1.0, 0.5);
     mat.diffuse = 0.8;
@@ -134,14 +140,14 @@ pub fn shape_sphere_middle() -> shapes::Shape {
     let mut pat = patterns::PATTERN_DEFAULT;
     pat.a = tuples::color(0.0, 0.8, 0.0);
     pat.b = tuples::color(0.0, 0.9, 0.5);
-    pat.transform = transformations::matrix4_transform_chain(
+    pat.set_transform(transformations::matrix4_transform_chain(
         &(vec![
             transformations::matrix4_scaling(0.1, 1.0, 0.1),
             transformations::matrix4_translation(0.5, 0.0, 0.0),
             transformations::matrix4_rotation_y_rad(PI / 4.0),
             transformations::matrix4_rotation_x_rad(PI / 4.0),
         ]),
-    );
+    ));
     mat.pattern = Some(pat);
     mat.reflective = 0.8;
     shape.material = mat;
@@ -152,7 +158,7 @@ pub fn shape_sphere_right() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(1.5, 0.5, -0.5);
     let t2 = transformations::matrix4_scaling(0.5, 0.5, 0.5);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t2, t1]));
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.diffuse = 0.7;
@@ -161,7 +167,7 @@ pub fn shape_sphere_right() -> shapes::Shape {
     pat.a = tuples::color(0.9, 1.0, 0.1);
     pat.b = tuples::color(0.1, 0.5, 0.2);
     pat.pattern_type = patterns::PatternType::Gradient;
-    pat.transform = transformations::matrix4_scaling(0.25, 0.25, 0.25);
+    pat.set_transform(transformations::matrix4_scaling(0.25, 0.25, 0.25));
     mat.pattern = Some(pat);
     shape.material = mat;
     shape
@@ -171,7 +177,7 @@ pub fn shape_sphere_right2() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(1.0, 0.5, 0.75);
     let t2 = transformations::matrix4_scaling(0.5, 0.5, 0.5);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t2, t1]));
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(0.5, 1.0, 0.1);
@@ -180,7 +186,7 @@ pub fn shape_sphere_right2() -> shapes::Shape {
     let mut pat = patterns::PATTERN_PINK;
     pat.a = tuples::color(0.0, 0.0, 0.8);
     pat.b = tuples::color(0.1, 0.1, 0.4);
-    pat.transform = transformations::matrix4_scaling(0.25, 0.25, 0.25);
+    pat.set_transform(transformations::matrix4_scaling(0.25, 0.25, 0.25));
     mat.pattern = Some(pat);
     shape.material = mat;
     shape
@@ -190,19 +196,19 @@ pub fn shape_sphere_left() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(-1.5, 0.33, -0.75);
     let t2 = transformations::matrix4_scaling(0.33, 0.33, 0.33);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t2, t1]));
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(1.0, 0.8, 0.1);
     mat.diffuse = 0.7;
     mat.specular = 0.3;
     let mut pat = patterns::PATTERN_PINK;
-    pat.transform = transformations::matrix4_transform_chain(
+    pat.set_transform(transformations::matrix4_transform_chain(
         &(vec![
             transformations::matrix4_scaling(0.25, 0.25, 0.25),
             transformations::matrix4_rotation_y_rad(PI * 2.0),
         ]),
-    );
+    ));
     mat.pattern = Some(pat);
     shape.material = mat;
     shape
@@ -8,46 +8,23 @@ use std::fs::OpenOptions;
 use std::io::Write;
 use std::time::Instant;
 
-use crate::camera;
-use crate::lights;
-use crate::materials;
-use crate::patterns;
-use crate::planes;
-use crate::shapes;
-use crate::spheres;
-use crate::transformations;
-use crate::tuples;
-use crate::worlds;
+use rust_ray_tracer_challenge::camera;
+use rust_ray_tracer_challenge::lights;
+use rust_ray_tracer_challenge::materials;
+use rust_ray_tracer_challenge::patterns;
+use rust_ray_tracer_challenge::planes;
+use rust_ray_tracer_challenge::shapes;
+use rust_ray_tracer_challenge::spheres;
+use rust_ray_tracer_challenge::transformations;
+use rust_ray_tracer_challenge::tuples;
+use rust_ray_tracer_challenge::worlds;
 
 const PROGRAM_NAME: &str = "chapter_11b";
 
 pub fn world_main(index: usize, program: u32, w: u32, h: u32) {
     println!("{} patterns", PROGRAM_NAME.to_string());
     let start1 = Instant::now();
-
-    let mut world = worlds::world_default();
-    world.objects = vec![
-        shape_floor(),
-        //shape_wall_behind(),
-        //shape_wall_behind_right(),
-        shape_sphere_middle(),
-        shape_sphere_right(),
-        shape_sphere_right2(),
-        shape_sphere_left(),
-        shape_sphere_left2(),
-        shape_sphere_left3(),
-    ];
-
-    world.light = vec![lights::LightPoint {
-        position: tuples::point(-3.0, 2.0, 0.5),
-        intensity: tuples::COLOR_WHITE,
-    }];
-
-    let mut c = camera::camera(w, h, PI / 3.0);
-    let from = tuples::point(-3.0, 2.0, -5.0);
-    let to = tuples::point(0.0, 1.0, 0.0);
-    let up = tuples::vector(0.0, 1.0, 0.0);
-    c.transform = transformations::view_transform(&from, &to, &up);
+    let (world, c) = scene(w, h);
     let image = c.render_percent_message(world, 0.01);
     let duration1 = start1.elapsed();
     println!("Time to calculate data: {:?}", duration1);
@@ -86,6 +63,33 @@ pub fn world_main(index: usize, program: u32, w: u32, h: u32) {
     println!("Time to save file: {:?}", duration3);
 }
 
+pub fn scene(w: u32, h: u32) -> (worlds::World, camera::Camera) {
+    let mut world = worlds::world_default();
+    world.objects = vec![
+        shape_floor(),
+        //shape_wall_behind(),
+        //shape_wall_behind_right(),
+        shape_sphere_middle(),
+        shape_sphere_right(),
+        shape_sphere_right2(),
+        shape_sphere_left(),
+        shape_sphere_left2(),
+        shape_sphere_left3(),
+    ];
+
+    world.light = vec![lights::light_point(
+        tuples::point(-3.0, 2.0, 0.5),
+        tuples::COLOR_WHITE,
+    )];
+
+    let mut c = camera::camera(w, h, PI / 3.0);
+    let from = tuples::point(-3.0, 2.0, -5.0);
+    let to = tuples::point(0.0, 1.0, 0.0);
+    let up = tuples::vector(0.0, 1.0, 0.0);
+    c.set_transform(transformations::view_transform(&from, &to, &up));
+    (world, c)
+}
+
 fn save_ppm(d: String, string: String) -> std::io::Result<()> {
     fs::write(
         format!("images/{}_{}.ppm", PROGRAM_NAME.to_string(), d),
@@ -126,12 +130,12 @@ pub fn shape_floor() -> shapes::Shape {
     let c2 = tuples::color(0.5, 0.5, 0.5);
     let mut pat = patterns::checkers_pattern(c1, c2);
     let mut mat = materials::MATERIAL_DEFAULT;
-    pat.transform = transformations::matrix4_transform_chain(
+    pat.set_transform(transformations::matrix4_transform_chain(
         &(vec![
             transformations::matrix4_scaling(2.0, 2.0, 2.0),
             transformations::matrix4_translation(0.5, 0.0, 0.0),
         ]),
-    );
+    ));
     mat.pattern = Some(pat);
     mat.reflective = 0.5;
     shape.material = mat;
@@ -142,10 +146,10 @@ pub fn shape_wall_behind() -> shapes::Shape {
     let mut shape = planes::plane();
     let t1 = transformations::matrix4_rotation_x_rad(PI / -2.0);
     let t2 = transformations::matrix4_translation(0.0, 0.0, 2.0);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t1, t2]));
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t1, t2])));
     let mut mat = material_floor();
     let mut pat = patterns::PATTERN_PINK;
-    pat.transform = transformations::matrix4_rotation_y_rad(PI / 4.0);
+    pat.set_transform(transformations::matrix4_rotation_y_rad(PI / 4.0));
     mat.pattern = Some(pat);
     shape.material = mat;
     shape
@@ -156,15 +160,17 @@ pub fn shape_wall_behind_right() -> shapes::Shape {
     let t1 = transformations::matrix4_rotation_x_rad(PI / -2.0);
     let t2 = transformations::matrix4_rotation_y_rad(PI / 2.0);
     let t3 = transformations::matrix4_translation(2.5, 0.0, 2.0);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t1, t2, t3]));
+    shape.set_transform(transformations::matrix4_transform_chain(
+        &(vec![t1, t2, t3]),
+    ));
     let mut mat = material_floor();
     let mut pat = patterns::checkers_pattern(tuples::COLOR_WHITE, tuples::COLOR_BLACK);
-    pat.transform = transformations::matrix4_transform_chain(
+    pat.set_transform(transformations::matrix4_transform_chain(
         &(vec![
             transformations::matrix4_scaling(0.2, 5.0, 0.2),
             transformations::matrix4_rotation_y_rad(PI / 16.0),
         ]),
-    );
+    ));
     mat.pattern = Some(pat);
     shape.material = mat;
     shape
@@ -172,7 +178,7 @@ pub fn shape_wall_behind_right() -> shapes::Shape {
 
 pub fn shape_sphere_middle() -> shapes::Shape {
     let mut shape = spheres::sphere_glass();
-    shape.transform = transformations::matrix4_translation(-0.5, 1.0, 0.5);
+    shape.set_transform(transformations::matrix4_translation(-0.5, 1.0, 0.5));
     shape.material = materials::MATERIAL_DEFAULT;
     shape.material.transparency = 1.0;
     shape.material.reflective = 0.9;
@@ -188,12 +194,12 @@ pub fn shape_sphere_middle() -> shapes::Shape {
     //let mut pat = patterns::PATTERN_DEFAULT;
     //pat.a = tuples::color(0.0, 0.8, 0.0);
     //pat.b = tuples::color(0.0, 0.9, 0.5);
-    //pat.transform = transformations::matrix4_transform_chain(vec![
+    //pat.set_transform(transformations::matrix4_transform_chain(vec![
     //    transformations::matrix4_scaling(0.1, 1.0, 0.1),
     //    transformations::matrix4_translation(0.5, 0.0, 0.0),
     //    transformations::matrix4_rotation_y_rad(PI / 4.0),
     //    transformations::matrix4_rotation_x_rad(PI / 4.0),
-    //]);
+    //]));
     //mat.pattern = Some(pat);
     //mat.reflective = 0.8;
     //shape.material = mat;
@@ -204,7 +210,7 @@ pub fn shape_sphere_right() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(0.0, 0.75, 8.0);
     let t2 = transformations::matrix4_scaling(0.75, 0.75, 0.75);
-    shape.transform = transformations::matrix4_transform_chain(&vec![t2, t1]);
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(1.0, 0.1, 0.1);
@@ -218,7 +224,7 @@ pub fn shape_sphere_right2() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(1.0, 0.5, 8.0);
     let t2 = transformations::matrix4_scaling(1.0, 1.0, 1.0);
-    shape.transform = transformations::matrix4_transform_chain(&vec![t2, t1]);
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(0.1, 0.6, 0.8);
@@ -227,7 +233,7 @@ pub fn shape_sphere_right2() -> shapes::Shape {
     let mut pat = patterns::PATTERN_PINK;
     pat.a = tuples::color(0.0, 0.0, 0.8);
     pat.b = tuples::color(0.1, 0.1, 0.4);
-    pat.transform = transformations::matrix4_scaling(0.25, 0.25, 0.25);
+    pat.set_transform(transformations::matrix4_scaling(0.25, 0.25, 0.25));
     mat.pattern = Some(pat);
     shape.material = mat;
     shape
@@ -237,7 +243,7 @@ pub fn shape_sphere_left() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(-1.0, 0.5, 3.0);
     let t2 = transformations::matrix4_scaling(0.5, 0.5, 0.5);
-    shape.transform = transformations::matrix4_transform_chain(&vec![t2, t1]);
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(1.0, 0.8, 0.1);
@@ -251,7 +257,7 @@ pub fn shape_sphere_left2() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(0.0, 0.5, 3.0);
     let t2 = transformations::matrix4_scaling(0.5, 0.5, 0.5);
-    shape.transform = transformations::matrix4_transform_chain(&vec![t2, t1]);
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(1.0, 0.8, 0.1);
@@ -265,7 +271,7 @@ pub fn shape_sphere_left3() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(1.0, 0.5, 3.0);
     let t2 = transformations::matrix4_scaling(0.5, 0.5, 0.5);
-    shape.transform = transformations::matrix4_transform_chain(&vec![t2, t1]);
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(1.0, 0.8, 0.1);
@@ -2,11 +2,32 @@ use chrono::prelude::*;
 use std::fs;
 use std::time::Instant;
 
-use crate::canvas;
-use crate::tuples;
+use rust_ray_tracer_challenge::canvas;
+use rust_ray_tracer_challenge::tuples;
 
 pub fn fire_canon_main(w: u32, h: u32) {
     println!("fire canon");
+    let start1 = Instant::now();
+    let c = draw(w, h);
+    let duration1 = start1.elapsed();
+    println!("Time to calculate data: {:?}", duration1);
+
+    let start2 = Instant::now();
+    let data = c.ppm_get();
+    let duration2 = start2.elapsed();
+    println!("Time to generate file: {:?}", duration2);
+
+    let start3 = Instant::now();
+    let f = save(data);
+    match f {
+        Ok(file) => file,
+        Err(error) => panic!("Problem saving the file: {:?}", error),
+    };
+    let duration3 = start3.elapsed();
+    println!("Time to save file: {:?}", duration3);
+}
+
+pub fn draw(w: u32, h: u32) -> canvas::PixelCanvas {
     let black = tuples::color(0.0, 0.0, 0.0);
     let mut orange = tuples::color(1.0, 1.0, 0.0);
     let mut c = canvas::pixel_canvas(w, h, black);
@@ -19,21 +40,9 @@ pub fn fire_canon_main(w: u32, h: u32) {
         tuples::vector(-0.01, 0.0, 0.0),
     );
 
-    let mut tick_count = 1;
-    println!("Projectile Velocity {:?}", proj.velocity);
-    println!("Environment Gravity {:?}", env.gravity);
-    println!("Environment Wind {:?}", env.wind);
-    println!(" {}", c.length);
-
-    let start1 = Instant::now();
     while c.height as f64 > proj.position.y {
         let y = (c.height as f64 - proj.position.y) as u32;
-        //println!(
-        //    "Tick: {:?}. Projectile Position ({:?},{:?}). Velocity:{:?}",
-        //    tick_count, proj.position.x, proj.position.y, proj.velocity
-        //);
         c = c.pixel_write(&(proj.position.x as u32), &y, orange);
-        tick_count = tick_count + 1;
         if orange.red > 0.01 {
             orange.red = orange.red - 0.01;
         }
@@ -42,26 +51,7 @@ pub fn fire_canon_main(w: u32, h: u32) {
         }
         proj = env.tick(&proj);
     }
-
-    let duration1 = start1.elapsed();
-    println!(
-        "Ticks: {:?}. Time to calculate data: {:?}",
-        tick_count, duration1
-    );
-
-    let start2 = Instant::now();
-    let data = c.ppm_get();
-    let duration2 = start2.elapsed();
-    println!("Time to generate file: {:?}", duration2);
-
-    let start3 = Instant::now();
-    let f = save(data);
-    let _f = match f {
-        Ok(file) => file,
-        Err(error) => panic!("Problem saving the file: {:?}", error),
-    };
-    let duration3 = start3.elapsed();
-    println!("Time to save file: {:?}", duration3);
+    c
 }
 
 fn save(string: String) -> std::io::Result<()> {
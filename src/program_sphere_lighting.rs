@@ -3,19 +3,39 @@ use chrono::prelude::*;
 use std::fs;
 use std::time::Instant;
 
-use crate::canvas;
-use crate::intersections;
-use crate::lights;
-use crate::materials;
+use rust_ray_tracer_challenge::canvas;
+use rust_ray_tracer_challenge::intersections;
+use rust_ray_tracer_challenge::lights;
+use rust_ray_tracer_challenge::materials;
 //use crate::matrices;
-use crate::rays;
-use crate::spheres;
+use rust_ray_tracer_challenge::rays;
+use rust_ray_tracer_challenge::spheres;
 //use crate::transformations;
-use crate::tuples;
+use rust_ray_tracer_challenge::tuples;
 
 pub fn sphere_lighting_main(w: u32, h: u32) {
     println!("sphere lighting");
     let start1 = Instant::now();
+    let c = draw(w, h);
+    let duration1 = start1.elapsed();
+    println!("Time to calculate data: {:?}", duration1);
+
+    let start2 = Instant::now();
+    let data = c.ppm_get();
+    let duration2 = start2.elapsed();
+    println!("Time to generate file: {:?}", duration2);
+
+    let start3 = Instant::now();
+    let f = save(data);
+    match f {
+        Ok(file) => file,
+        Err(error) => panic!("Problem saving the file: {:?}", error),
+    };
+    let duration3 = start3.elapsed();
+    println!("Time to save file: {:?}", duration3);
+}
+
+pub fn draw(w: u32, h: u32) -> canvas::PixelCanvas {
     let mut c = canvas::pixel_canvas(w, h, tuples::COLOR_BLACK);
     let ray_origin = tuples::point(0.0, 0.0, -5.0);
     let wall_z: f64 = 10.0;
@@ -29,7 +49,7 @@ pub fn sphere_lighting_main(w: u32, h: u32) {
     //let m2: matrices::Matrix4 = transformations::matrix4_rotation_z_rad(PI / 8.0);
     //let m3: matrices::Matrix4 = transformations::matrix4_shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
     //let t = matrices::matrix4_multiply(m1, m3);
-    //shape.transform = t;
+    //shape.set_transform(t);
     shape.material = mat;
 
     let light_position = tuples::point(-10.0, 10.0, -10.0);
@@ -65,7 +85,7 @@ pub fn sphere_lighting_main(w: u32, h: u32) {
                                 &pnt,
                                 &eye,
                                 &nrm,
-                                &false,
+                                &tuples::COLOR_WHITE,
                             );
                             c = c.pixel_write(&x, &y, col);
                         }
@@ -74,23 +94,7 @@ pub fn sphere_lighting_main(w: u32, h: u32) {
             }
         }
     }
-
-    let duration1 = start1.elapsed();
-    println!("Time to calculate data: {:?}", duration1);
-
-    let start2 = Instant::now();
-    let data = c.ppm_get();
-    let duration2 = start2.elapsed();
-    println!("Time to generate file: {:?}", duration2);
-
-    let start3 = Instant::now();
-    let f = save(data);
-    let _f = match f {
-        Ok(file) => file,
-        Err(error) => panic!("Problem saving the file: {:?}", error),
-    };
-    let duration3 = start3.elapsed();
-    println!("Time to save file: {:?}", duration3);
+    c
 }
 
 fn save(string: String) -> std::io::Result<()> {
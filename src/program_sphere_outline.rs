@@ -3,17 +3,37 @@ use std::f64::consts::PI;
 use std::fs;
 use std::time::Instant;
 
-use crate::canvas;
-use crate::matrices;
-use crate::rays;
-use crate::spheres;
-use crate::transformations;
-use crate::tuples;
+use rust_ray_tracer_challenge::canvas;
+use rust_ray_tracer_challenge::matrices;
+use rust_ray_tracer_challenge::rays;
+use rust_ray_tracer_challenge::spheres;
+use rust_ray_tracer_challenge::transformations;
+use rust_ray_tracer_challenge::tuples;
 
 pub fn sphere_outline_main(w: u32, h: u32) {
     // w should equal h
     println!("sphere outline");
     let start1 = Instant::now();
+    let c = draw(w, h);
+    let duration1 = start1.elapsed();
+    println!("Time to calculate data: {:?}", duration1);
+
+    let start2 = Instant::now();
+    let data = c.ppm_get();
+    let duration2 = start2.elapsed();
+    println!("Time to generate file: {:?}", duration2);
+
+    let start3 = Instant::now();
+    let f = save(data);
+    match f {
+        Ok(file) => file,
+        Err(error) => panic!("Problem saving the file: {:?}", error),
+    };
+    let duration3 = start3.elapsed();
+    println!("Time to save file: {:?}", duration3);
+}
+
+pub fn draw(w: u32, h: u32) -> canvas::PixelCanvas {
     let mut c = canvas::pixel_canvas(w, h, tuples::COLOR_BLACK);
     let ray_origin = tuples::point(0.0, 0.0, -5.0);
     let wall_z: f64 = 10.0;
@@ -26,7 +46,7 @@ pub fn sphere_outline_main(w: u32, h: u32) {
     let _m3: matrices::Matrix4 = transformations::matrix4_shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
     let m = m1.multiply(&m2);
     //console.log("m1", m1, "m2", m2, "m", m);
-    shape.transform = m;
+    shape.set_transform(m);
     //console.log("st", shape.transform);
 
     for y in 0..h {
@@ -44,23 +64,7 @@ pub fn sphere_outline_main(w: u32, h: u32) {
             }
         }
     }
-
-    let duration1 = start1.elapsed();
-    println!("Time to calculate data: {:?}", duration1);
-
-    let start2 = Instant::now();
-    let data = c.ppm_get();
-    let duration2 = start2.elapsed();
-    println!("Time to generate file: {:?}", duration2);
-
-    let start3 = Instant::now();
-    let f = save(data);
-    let _f = match f {
-        Ok(file) => file,
-        Err(error) => panic!("Problem saving the file: {:?}", error),
-    };
-    let duration3 = start3.elapsed();
-    println!("Time to save file: {:?}", duration3);
+    c
 }
 
 fn save(string: String) -> std::io::Result<()> {
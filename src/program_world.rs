@@ -3,33 +3,18 @@ use std::f64::consts::PI;
 use std::fs;
 use std::time::Instant;
 
-use crate::camera;
-use crate::materials;
-use crate::shapes;
-use crate::spheres;
-use crate::transformations;
-use crate::tuples;
-use crate::worlds;
+use rust_ray_tracer_challenge::camera;
+use rust_ray_tracer_challenge::materials;
+use rust_ray_tracer_challenge::shapes;
+use rust_ray_tracer_challenge::spheres;
+use rust_ray_tracer_challenge::transformations;
+use rust_ray_tracer_challenge::tuples;
+use rust_ray_tracer_challenge::worlds;
 
 pub fn world_main(w: u32, h: u32) {
     println!("world - 6 objects!");
     let start1 = Instant::now();
-
-    let mut world = worlds::world_two_lights();
-    world.objects = vec![
-        shape_floor(),
-        shape_wall_left(),
-        shape_wall_right(),
-        shape_sphere_middle(),
-        shape_sphere_right2(),
-        shape_sphere_left(),
-    ];
-
-    let mut c = camera::camera(w, h, PI / 3.0);
-    let from = tuples::point(0.0, 1.5, -5.0);
-    let to = tuples::point(0.0, 1.0, 0.0);
-    let up = tuples::vector(0.0, 1.0, 0.0);
-    c.transform = transformations::view_transform(&from, &to, &up);
+    let (world, c) = scene(w, h);
     let image = c.render_percent_message(world, 0.01);
     let duration1 = start1.elapsed();
     println!("Time to calculate data: {:?}", duration1);
@@ -49,6 +34,25 @@ pub fn world_main(w: u32, h: u32) {
     println!("Time to save file: {:?}", duration3);
 }
 
+pub fn scene(w: u32, h: u32) -> (worlds::World, camera::Camera) {
+    let mut world = worlds::world_two_lights();
+    world.objects = vec![
+        shape_floor(),
+        shape_wall_left(),
+        shape_wall_right(),
+        shape_sphere_middle(),
+        shape_sphere_right2(),
+        shape_sphere_left(),
+    ];
+
+    let mut c = camera::camera(w, h, PI / 3.0);
+    let from = tuples::point(0.0, 1.5, -5.0);
+    let to = tuples::point(0.0, 1.0, 0.0);
+    let up = tuples::vector(0.0, 1.0, 0.0);
+    c.set_transform(transformations::view_transform(&from, &to, &up));
+    (world, c)
+}
+
 fn save(string: String) -> std::io::Result<()> {
     let utc = Utc::now();
     let d = utc.format("%Y-%m-%d-%H-%M").to_string();
@@ -65,7 +69,7 @@ pub fn material_floor() -> materials::Material {
 
 pub fn shape_floor() -> shapes::Shape {
     let mut shape = spheres::sphere();
-    shape.transform = transformations::matrix4_scaling(10.0, 0.01, 10.0);
+    shape.set_transform(transformations::matrix4_scaling(10.0, 0.01, 10.0));
     shape.material = material_floor();
     shape
 }
@@ -76,7 +80,9 @@ pub fn shape_wall_left() -> shapes::Shape {
     let t2 = transformations::matrix4_rotation_y_rad(-PI / 4.0);
     let t3 = transformations::matrix4_rotation_x_rad(PI / 2.0);
     let t4 = transformations::matrix4_scaling(10.0, 0.01, 10.0);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t4, t3, t2, t1]));
+    shape.set_transform(transformations::matrix4_transform_chain(
+        &(vec![t4, t3, t2, t1]),
+    ));
     shape.material = material_floor();
     shape
 }
@@ -87,14 +93,16 @@ pub fn shape_wall_right() -> shapes::Shape {
     let t2 = transformations::matrix4_rotation_y_rad(PI / 4.0);
     let t3 = transformations::matrix4_rotation_x_rad(PI / 2.0);
     let t4 = transformations::matrix4_scaling(10.0, 0.01, 10.0);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t4, t3, t2, t1]));
+    shape.set_transform(transformations::matrix4_transform_chain(
+        &(vec![t4, t3, t2, t1]),
+    ));
     shape.material = material_floor();
     shape
 }
 
 pub fn shape_sphere_middle() -> shapes::Shape {
     let mut shape = spheres::sphere();
-    shape.transform = transformations::matrix4_translation(-0.5, 1.0, 0.5);
+    shape.set_transform(transformations::matrix4_translation(-0.5, 1.0, 0.5));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(0.1, 1.0, 0.5);
@@ -109,7 +117,7 @@ pub fn shape_sphere_right() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(1.5, 0.5, -0.5);
     let t2 = transformations::matrix4_scaling(0.5, 0.5, 0.5);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t2, t1]));
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(0.5, 1.0, 0.1);
@@ -124,7 +132,7 @@ pub fn shape_sphere_right2() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(1.0, 0.5, 0.75);
     let t2 = transformations::matrix4_scaling(0.5, 0.5, 0.5);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t2, t1]));
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(0.5, 1.0, 0.1);
@@ -139,7 +147,7 @@ pub fn shape_sphere_left() -> shapes::Shape {
     let mut shape = spheres::sphere();
     let t1 = transformations::matrix4_translation(-1.5, 0.33, -0.75);
     let t2 = transformations::matrix4_scaling(0.33, 0.33, 0.33);
-    shape.transform = transformations::matrix4_transform_chain(&(vec![t2, t1]));
+    shape.set_transform(transformations::matrix4_transform_chain(&(vec![t2, t1])));
 
     let mut mat = materials::MATERIAL_DEFAULT;
     mat.color = tuples::color(1.0, 0.8, 0.1);
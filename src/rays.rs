@@ -1,6 +1,5 @@
 use crate::matrices;
 use crate::spheres;
-use crate::transformations;
 use crate::tuples;
 
 #[derive(Debug, Copy, Clone)]
@@ -29,9 +28,10 @@ impl Ray {
     }
 
     pub fn transform(&self, m: matrices::Matrix4) -> Ray {
-        let origin = transformations::transform_tuple_with_chain(&[m].to_vec(), &self.origin);
-        let direction = transformations::transform_tuple_with_chain(&[m].to_vec(), &self.direction);
-        ray(origin, direction)
+        ray(
+            m.tuple_multiply(&self.origin),
+            m.tuple_multiply(&self.direction),
+        )
     }
 }
 
@@ -50,6 +50,7 @@ pub fn ray(o: tuples::Point, d: tuples::Vector) -> Ray {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::transformations;
 
     #[test]
     fn test_ray() {
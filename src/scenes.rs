@@ -0,0 +1,1816 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::Marker;
+
+use crate::backgrounds;
+use crate::bumps;
+use crate::camera;
+use crate::canvas;
+use crate::cones;
+use crate::csgs;
+use crate::cubes;
+use crate::cylinders;
+use crate::groups;
+use crate::lights;
+use crate::materials;
+use crate::matrices;
+use crate::obj_files;
+use crate::patterns;
+use crate::planes;
+use crate::shapes;
+use crate::spheres;
+use crate::transformations;
+use crate::triangles;
+use crate::tuples;
+use crate::worlds;
+
+#[derive(Debug, Clone)]
+pub struct Scene {
+    pub world: worlds::World,
+    pub camera: camera::Camera,
+    //obj files that had unsupported lines, and how many of them were skipped
+    pub skipped: Vec<(String, usize)>,
+}
+
+//a parsed yaml value that remembers which line it started on, for error messages
+#[derive(Debug, Clone)]
+enum NodeValue {
+    Scalar(String),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(Node, Node)>),
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    value: NodeValue,
+    line: usize,
+}
+
+struct EventCollector {
+    events: Vec<(Event, Marker)>,
+}
+
+impl MarkedEventReceiver for EventCollector {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        self.events.push((ev, mark));
+    }
+}
+
+pub fn scene_from_file(path: &str) -> Result<Scene, String> {
+    let yaml = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    //files named in the scene are found relative to the scene itself
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    scene_in_dir(&yaml, dir).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn scene_from_str(yaml: &str) -> Result<Scene, String> {
+    scene_in_dir(yaml, Path::new(""))
+}
+
+fn scene_in_dir(yaml: &str, dir: &Path) -> Result<Scene, String> {
+    let root = parse_yaml(yaml)?;
+    let items = match &root.value {
+        NodeValue::Sequence(items) => items,
+        _ => {
+            return Err(error(
+                &root,
+                "expected a list of 'add' and 'define' entries",
+            ))
+        }
+    };
+    let mut defines: HashMap<String, Node> = HashMap::new();
+    let mut world = worlds::world();
+    let mut cam: Option<camera::Camera> = None;
+    let mut skipped: Vec<(String, usize)> = vec![];
+    for item in items {
+        let entries = mapping(item)?;
+        if let Some(kind) = get(entries, "add") {
+            match scalar(kind)?.as_str() {
+                "camera" => cam = Some(parse_camera(item)?),
+                "light" => world.light.push(parse_light(item)?),
+                "background" => world.background = parse_background(item, dir)?,
+                _ => world
+                    .objects
+                    .push(parse_shape(item, &defines, dir, None, &mut skipped)?),
+            }
+        } else if let Some(name) = get(entries, "define") {
+            let name = scalar(name)?;
+            let value = parse_define(item, &defines)?;
+            defines.insert(name, value);
+        } else {
+            return Err(error(item, "expected an 'add' or 'define' entry"));
+        }
+    }
+    match cam {
+        Some(c) => Ok(Scene {
+            world,
+            camera: c,
+            skipped,
+        }),
+        None => Err("scene has no camera ('add: camera')".to_string()),
+    }
+}
+
+fn parse_yaml(yaml: &str) -> Result<Node, String> {
+    let mut collector = EventCollector { events: vec![] };
+    let mut parser = Parser::new(yaml.chars());
+    parser.load(&mut collector, false).map_err(|e| {
+        //the scanner's message already ends with its own position, so reword it
+        let message = e.to_string();
+        let message = message.split(" at line ").next().unwrap_or("").to_string();
+        format!("line {}: {}", e.marker().line(), message)
+    })?;
+    let mut pos = 0;
+    while pos < collector.events.len() {
+        match collector.events[pos].0 {
+            Event::StreamStart | Event::DocumentStart | Event::Nothing => pos += 1,
+            _ => break,
+        }
+    }
+    match collector.events.get(pos) {
+        None | Some((Event::StreamEnd, _)) | Some((Event::DocumentEnd, _)) => {
+            Err("scene is empty".to_string())
+        }
+        _ => build_node(&collector.events, &mut pos),
+    }
+}
+
+fn build_node(events: &[(Event, Marker)], pos: &mut usize) -> Result<Node, String> {
+    let (ev, mark) = &events[*pos];
+    let line = mark.line();
+    *pos += 1;
+    match ev {
+        Event::Scalar(s, _, _, _) => Ok(Node {
+            value: NodeValue::Scalar(s.clone()),
+            line,
+        }),
+        Event::SequenceStart(_) => {
+            let mut items = vec![];
+            while let Some((ev, _)) = events.get(*pos) {
+                if let Event::SequenceEnd = ev {
+                    *pos += 1;
+                    break;
+                }
+                items.push(build_node(events, pos)?);
+            }
+            Ok(Node {
+                value: NodeValue::Sequence(items),
+                line,
+            })
+        }
+        Event::MappingStart(_) => {
+            let mut entries = vec![];
+            while let Some((ev, _)) = events.get(*pos) {
+                if let Event::MappingEnd = ev {
+                    *pos += 1;
+                    break;
+                }
+                let key = build_node(events, pos)?;
+                if let NodeValue::Scalar(_) = key.value {
+                } else {
+                    return Err(error(&key, "mapping keys must be plain names"));
+                }
+                let value = build_node(events, pos)?;
+                entries.push((key, value));
+            }
+            Ok(Node {
+                value: NodeValue::Mapping(entries),
+                line,
+            })
+        }
+        Event::Alias(_) => Err(format!("line {}: aliases are not supported", line)),
+        _ => Err(format!("line {}: unexpected yaml event", line)),
+    }
+}
+
+fn error(node: &Node, message: &str) -> String {
+    format!("line {}: {}", node.line, message)
+}
+
+fn key_name(key: &Node) -> &str {
+    match &key.value {
+        NodeValue::Scalar(s) => s,
+        _ => "",
+    }
+}
+
+fn get<'a>(entries: &'a [(Node, Node)], key: &str) -> Option<&'a Node> {
+    entries
+        .iter()
+        .find(|(k, _)| key_name(k) == key)
+        .map(|(_, v)| v)
+}
+
+fn require<'a>(node: &Node, entries: &'a [(Node, Node)], key: &str) -> Result<&'a Node, String> {
+    match get(entries, key) {
+        Some(v) => Ok(v),
+        None => Err(error(node, &format!("missing '{}'", key))),
+    }
+}
+
+fn check_keys(entries: &[(Node, Node)], what: &str, allowed: &[&str]) -> Result<(), String> {
+    for (k, _) in entries {
+        if !allowed.contains(&key_name(k)) {
+            return Err(error(
+                k,
+                &format!("unknown key '{}' for {}", key_name(k), what),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn mapping(node: &Node) -> Result<&[(Node, Node)], String> {
+    match &node.value {
+        NodeValue::Mapping(entries) => Ok(entries),
+        _ => Err(error(node, "expected a mapping of keys and values")),
+    }
+}
+
+fn sequence(node: &Node) -> Result<&[Node], String> {
+    match &node.value {
+        NodeValue::Sequence(items) => Ok(items),
+        _ => Err(error(node, "expected a list")),
+    }
+}
+
+fn scalar(node: &Node) -> Result<String, String> {
+    match &node.value {
+        NodeValue::Scalar(s) => Ok(s.clone()),
+        _ => Err(error(node, "expected a single value")),
+    }
+}
+
+fn number(node: &Node) -> Result<f64, String> {
+    let s = scalar(node)?;
+    s.trim()
+        .parse::<f64>()
+        .map_err(|_| error(node, &format!("expected a number, found '{}'", s)))
+}
+
+fn boolean(node: &Node) -> Result<bool, String> {
+    let s = scalar(node)?;
+    match s.trim() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(error(
+            node,
+            &format!("expected true or false, found '{}'", s),
+        )),
+    }
+}
+
+fn integer(node: &Node) -> Result<u32, String> {
+    let s = scalar(node)?;
+    match s.trim().parse::<u32>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(error(
+            node,
+            &format!("expected a whole number above zero, found '{}'", s),
+        )),
+    }
+}
+
+fn numbers(node: &Node, count: usize) -> Result<Vec<f64>, String> {
+    let items = sequence(node)?;
+    if items.len() != count {
+        return Err(error(
+            node,
+            &format!("expected {} numbers, found {}", count, items.len()),
+        ));
+    }
+    items.iter().map(number).collect()
+}
+
+fn point(node: &Node) -> Result<tuples::Point, String> {
+    let n = numbers(node, 3)?;
+    Ok(tuples::point(n[0], n[1], n[2]))
+}
+
+fn vector(node: &Node) -> Result<tuples::Vector, String> {
+    let n = numbers(node, 3)?;
+    Ok(tuples::vector(n[0], n[1], n[2]))
+}
+
+fn color(node: &Node) -> Result<tuples::Color, String> {
+    let n = numbers(node, 3)?;
+    Ok(tuples::color(n[0], n[1], n[2]))
+}
+
+fn parse_define(node: &Node, defines: &HashMap<String, Node>) -> Result<Node, String> {
+    let entries = mapping(node)?;
+    check_keys(entries, "define", &["define", "extend", "value"])?;
+    let value = require(node, entries, "value")?;
+    let parent = match get(entries, "extend") {
+        None => return Ok(value.clone()),
+        Some(name) => lookup(name, defines)?,
+    };
+    //extending merges mappings key by key, and appends lists to the parent list
+    match (&parent.value, &value.value) {
+        (NodeValue::Mapping(base), NodeValue::Mapping(extra)) => {
+            let mut merged: Vec<(Node, Node)> = base
+                .iter()
+                .filter(|(k, _)| get(extra, key_name(k)).is_none())
+                .cloned()
+                .collect();
+            merged.extend(extra.iter().cloned());
+            Ok(Node {
+                value: NodeValue::Mapping(merged),
+                line: value.line,
+            })
+        }
+        (NodeValue::Sequence(base), NodeValue::Sequence(extra)) => {
+            let mut merged = base.clone();
+            merged.extend(extra.iter().cloned());
+            Ok(Node {
+                value: NodeValue::Sequence(merged),
+                line: value.line,
+            })
+        }
+        _ => Err(error(
+            value,
+            "value must be the same kind (mapping or list) as the definition it extends",
+        )),
+    }
+}
+
+fn lookup<'a>(name: &Node, defines: &'a HashMap<String, Node>) -> Result<&'a Node, String> {
+    let key = scalar(name)?;
+    match defines.get(&key) {
+        Some(n) => Ok(n),
+        None => Err(error(name, &format!("'{}' has not been defined", key))),
+    }
+}
+
+fn parse_camera(node: &Node) -> Result<camera::Camera, String> {
+    let entries = mapping(node)?;
+    check_keys(
+        entries,
+        "camera",
+        &[
+            "add",
+            "width",
+            "height",
+            "field-of-view",
+            "from",
+            "to",
+            "up",
+        ],
+    )?;
+    let width = integer(require(node, entries, "width")?)?;
+    let height = integer(require(node, entries, "height")?)?;
+    let fov = number(require(node, entries, "field-of-view")?)?;
+    let from = point(require(node, entries, "from")?)?;
+    let to = point(require(node, entries, "to")?)?;
+    let up = vector(require(node, entries, "up")?)?;
+    let mut c = camera::camera(width, height, fov);
+    c.set_transform(transformations::view_transform(&from, &to, &up));
+    Ok(c)
+}
+
+fn parse_background(node: &Node, dir: &Path) -> Result<backgrounds::Background, String> {
+    let entries = mapping(node)?;
+    let kind_node = require(node, entries, "type")?;
+    let image = |name: &str| {
+        let file = require(node, entries, name)?;
+        canvas::pixel_canvas_from_path(&file_path(file, dir)?).map_err(|e| error(file, &e))
+    };
+    match scalar(kind_node)?.as_str() {
+        "flat" => {
+            check_keys(entries, "background", &["add", "type", "color"])?;
+            Ok(backgrounds::flat_background(color(require(
+                node, entries, "color",
+            )?)?))
+        }
+        "gradient" => {
+            check_keys(entries, "background", &["add", "type", "colors"])?;
+            let (bottom, top) = two_colors(node, entries)?;
+            Ok(backgrounds::gradient_background(bottom, top))
+        }
+        "equirectangular" => {
+            check_keys(entries, "background", &["add", "type", "file"])?;
+            Ok(backgrounds::equirectangular_background(image("file")?))
+        }
+        "cube" => {
+            check_keys(
+                entries,
+                "background",
+                &[
+                    "add", "type", "left", "front", "right", "back", "up", "down",
+                ],
+            )?;
+            Ok(backgrounds::cube_map_background(
+                image("left")?,
+                image("front")?,
+                image("right")?,
+                image("back")?,
+                image("up")?,
+                image("down")?,
+            ))
+        }
+        other => Err(error(
+            kind_node,
+            &format!("unknown background type '{}'", other),
+        )),
+    }
+}
+
+fn parse_light(node: &Node) -> Result<lights::Light, String> {
+    let entries = mapping(node)?;
+    //a corner makes it an area light, a direction a spot light (with 'at')
+    //or a directional light (without), otherwise it is a point light.
+    //each kind only takes the keys it uses
+    let (kind, allowed): (&str, &[&str]) = match (
+        get(entries, "corner"),
+        get(entries, "at"),
+        get(entries, "direction"),
+    ) {
+        (Some(_), _, _) => (
+            "area light",
+            &[
+                "add",
+                "intensity",
+                "corner",
+                "uvec",
+                "usteps",
+                "vvec",
+                "vsteps",
+                "jitter",
+            ],
+        ),
+        (None, Some(_), Some(_)) => (
+            "spot light",
+            &[
+                "add",
+                "at",
+                "intensity",
+                "direction",
+                "inner-angle",
+                "outer-angle",
+                "attenuation",
+            ],
+        ),
+        (None, None, Some(_)) => ("directional light", &["add", "intensity", "direction"]),
+        (None, _, None) => ("point light", &["add", "at", "intensity", "attenuation"]),
+    };
+    check_keys(entries, kind, allowed)?;
+    let intensity = color(require(node, entries, "intensity")?)?;
+    if get(entries, "corner").is_some() {
+        let mut light = lights::area_light(
+            point(require(node, entries, "corner")?)?,
+            vector(require(node, entries, "uvec")?)?,
+            integer(require(node, entries, "usteps")?)?,
+            vector(require(node, entries, "vvec")?)?,
+            integer(require(node, entries, "vsteps")?)?,
+            intensity,
+        );
+        if let Some(n) = get(entries, "jitter") {
+            light.jitter = boolean(n)?;
+        }
+        return Ok(light);
+    }
+    let mut light = match (get(entries, "at"), get(entries, "direction")) {
+        (Some(at), Some(direction)) => {
+            let outer = number(require(node, entries, "outer-angle")?)?;
+            let inner = match get(entries, "inner-angle") {
+                Some(n) => number(n)?,
+                None => outer,
+            };
+            lights::spot_light(point(at)?, vector(direction)?, inner, outer, intensity)
+        }
+        (None, Some(direction)) => lights::directional_light(vector(direction)?, intensity),
+        _ => lights::light_point(point(require(node, entries, "at")?)?, intensity),
+    };
+    //either inverse-square or [ constant, linear, quadratic ]
+    if let Some(n) = get(entries, "attenuation") {
+        match &n.value {
+            NodeValue::Scalar(s) if s.trim() == "inverse-square" => light.set_inverse_square(),
+            _ => {
+                let k = numbers(n, 3)?;
+                light.constant = k[0];
+                light.linear = k[1];
+                light.quadratic = k[2];
+            }
+        }
+    }
+    Ok(light)
+}
+
+fn parse_shape(
+    node: &Node,
+    defines: &HashMap<String, Node>,
+    dir: &Path,
+    inherited: Option<materials::Material>,
+    skipped: &mut Vec<(String, usize)>,
+) -> Result<shapes::Shape, String> {
+    let entries = mapping(node)?;
+    let kind_node = require(node, entries, "add")?;
+    let kind = scalar(kind_node)?;
+    let mut shape = match kind.as_str() {
+        "sphere" => spheres::sphere(),
+        "plane" => planes::plane(),
+        "cube" => cubes::cube(),
+        "cylinder" => cylinders::cylinder(),
+        "cone" => cones::cone(),
+        "group" => groups::group(),
+        "triangle" => triangles::triangle(
+            point(require(node, entries, "p1")?)?,
+            point(require(node, entries, "p2")?)?,
+            point(require(node, entries, "p3")?)?,
+        ),
+        "smooth-triangle" => triangles::smooth_triangle(
+            point(require(node, entries, "p1")?)?,
+            point(require(node, entries, "p2")?)?,
+            point(require(node, entries, "p3")?)?,
+            vector(require(node, entries, "n1")?)?,
+            vector(require(node, entries, "n2")?)?,
+            vector(require(node, entries, "n3")?)?,
+        ),
+        "obj" => parse_obj(require(node, entries, "file")?, dir, skipped)?,
+        "csg" => {
+            let op_node = require(node, entries, "operation")?;
+            let op = match scalar(op_node)?.as_str() {
+                "union" => csgs::CsgOperation::Union,
+                "intersection" => csgs::CsgOperation::Intersection,
+                "difference" => csgs::CsgOperation::Difference,
+                other => return Err(error(op_node, &format!("unknown operation '{}'", other))),
+            };
+            let own = match get(entries, "material") {
+                Some(m) => Some(parse_material(m, defines, dir)?),
+                None => inherited.clone(),
+            };
+            let left = parse_shape(
+                require(node, entries, "left")?,
+                defines,
+                dir,
+                own.clone(),
+                skipped,
+            )?;
+            let right = parse_shape(require(node, entries, "right")?, defines, dir, own, skipped)?;
+            csgs::csg(op, left, right)
+        }
+        _ => return Err(error(kind_node, &format!("unknown type '{}' to add", kind))),
+    };
+    let mut keys = vec!["add", "material", "transform"];
+    match kind.as_str() {
+        "cylinder" | "cone" => keys.extend(&["min", "max", "closed"]),
+        "group" => keys.push("children"),
+        "triangle" => keys.extend(&["p1", "p2", "p3"]),
+        "smooth-triangle" => keys.extend(&["p1", "p2", "p3", "n1", "n2", "n3"]),
+        "obj" => keys.push("file"),
+        "csg" => keys.extend(&["operation", "left", "right"]),
+        _ => (),
+    }
+    check_keys(entries, &kind, &keys)?;
+    if let Some(n) = get(entries, "min") {
+        shape.minimum = number(n)?;
+    }
+    if let Some(n) = get(entries, "max") {
+        shape.maximum = number(n)?;
+    }
+    if let Some(b) = get(entries, "closed") {
+        shape.closed = boolean(b)?;
+    }
+    //children without a material of their own take their group's
+    let material = match get(entries, "material") {
+        Some(m) => Some(parse_material(m, defines, dir)?),
+        None => inherited,
+    };
+    if let Some(m) = &material {
+        shape.set_material(m.clone());
+    }
+    if let Some(t) = get(entries, "transform") {
+        shape.set_transform(parse_transform(t, defines)?);
+    }
+    if let Some(c) = get(entries, "children") {
+        for child in sequence(c)? {
+            shape.add_child(parse_shape(child, defines, dir, material.clone(), skipped)?);
+        }
+    }
+    Ok(shape)
+}
+
+fn parse_obj(
+    node: &Node,
+    dir: &Path,
+    skipped: &mut Vec<(String, usize)>,
+) -> Result<shapes::Shape, String> {
+    let path = file_path(node, dir)?;
+    let obj = obj_files::obj_file_from_path(&path).map_err(|e| error(node, &e))?;
+    if obj.skipped > 0 {
+        skipped.push((path, obj.skipped));
+    }
+    Ok(obj.to_group())
+}
+
+fn parse_material(
+    node: &Node,
+    defines: &HashMap<String, Node>,
+    dir: &Path,
+) -> Result<materials::Material, String> {
+    let resolved = match &node.value {
+        NodeValue::Scalar(_) => lookup(node, defines)?,
+        _ => node,
+    };
+    let entries = mapping(resolved)?;
+    check_keys(
+        entries,
+        "material",
+        &[
+            "color",
+            "ambient",
+            "diffuse",
+            "specular",
+            "shininess",
+            "reflective",
+            "transparency",
+            "refractive-index",
+            "pattern",
+            "shadow",
+            "bump",
+        ],
+    )?;
+    let mut m = materials::MATERIAL_DEFAULT;
+    for (k, v) in entries {
+        match key_name(k) {
+            "color" => m.color = color(v)?,
+            "ambient" => m.ambient = number(v)?,
+            "diffuse" => m.diffuse = number(v)?,
+            "specular" => m.specular = number(v)?,
+            "shininess" => m.shininess = number(v)?,
+            "reflective" => m.reflective = number(v)?,
+            "transparency" => m.transparency = number(v)?,
+            "refractive-index" => m.refractive_index = number(v)?,
+            "pattern" => m.pattern = Some(parse_pattern(v, defines, dir)?),
+            "shadow" => m.casts_shadow = boolean(v)?,
+            "bump" => m.bump = Some(parse_bump(v, defines, dir)?),
+            _ => (),
+        }
+    }
+    Ok(m)
+}
+
+fn parse_bump(
+    node: &Node,
+    defines: &HashMap<String, Node>,
+    dir: &Path,
+) -> Result<bumps::Bump, String> {
+    let entries = mapping(node)?;
+    let kind_node = require(node, entries, "type")?;
+    let mut b = match scalar(kind_node)?.as_str() {
+        "noise" => {
+            check_keys(
+                entries,
+                "bump",
+                &["type", "amount", "octaves", "persistence", "transform"],
+            )?;
+            let mut b = bumps::BUMP_DEFAULT;
+            if let Some(n) = get(entries, "octaves") {
+                b.octaves = integer(n)?;
+            }
+            if let Some(n) = get(entries, "persistence") {
+                b.persistence = number(n)?;
+            }
+            b
+        }
+        "ripples" => {
+            check_keys(entries, "bump", &["type", "amount", "transform"])?;
+            let mut b = bumps::BUMP_DEFAULT;
+            b.bump_type = bumps::BumpType::Ripples;
+            b
+        }
+        "normal-map" => {
+            check_keys(
+                entries,
+                "bump",
+                &[
+                    "type",
+                    "amount",
+                    "file",
+                    "mapping",
+                    "filter",
+                    "wrap",
+                    "transform",
+                ],
+            )?;
+            let mut b = bumps::BUMP_DEFAULT;
+            b.bump_type = bumps::BumpType::NormalMap;
+            b.amount = 1.0;
+            let mut map = parse_image_pattern(node, entries, dir)?;
+            if let Some(n) = get(entries, "mapping") {
+                map.mapping = uv_mapping(n)?;
+            }
+            b.normal_map = Some(map);
+            b
+        }
+        other => return Err(error(kind_node, &format!("unknown bump type '{}'", other))),
+    };
+    if let Some(n) = get(entries, "amount") {
+        b.amount = number(n)?;
+    }
+    if let Some(t) = get(entries, "transform") {
+        b.set_transform(parse_transform(t, defines)?);
+    }
+    Ok(b)
+}
+
+fn parse_pattern(
+    node: &Node,
+    defines: &HashMap<String, Node>,
+    dir: &Path,
+) -> Result<patterns::Pattern, String> {
+    let entries = mapping(node)?;
+    let kind_node = require(node, entries, "type")?;
+    let kind = scalar(kind_node)?;
+    let mut p = match kind.as_str() {
+        "image" => {
+            check_keys(
+                entries,
+                "pattern",
+                &["type", "file", "filter", "wrap", "transform"],
+            )?;
+            parse_image_pattern(node, entries, dir)?
+        }
+        "map" => {
+            check_keys(
+                entries,
+                "pattern",
+                &[
+                    "type",
+                    "mapping",
+                    "uv_pattern",
+                    "left",
+                    "front",
+                    "right",
+                    "back",
+                    "up",
+                    "down",
+                    "transform",
+                ],
+            )?;
+            parse_mapped_pattern(node, entries, defines, dir)?
+        }
+        "perturb" => {
+            check_keys(
+                entries,
+                "pattern",
+                &[
+                    "type",
+                    "pattern",
+                    "scale",
+                    "octaves",
+                    "persistence",
+                    "transform",
+                ],
+            )?;
+            let inner = parse_pattern(require(node, entries, "pattern")?, defines, dir)?;
+            let mut p = patterns::perturb_pattern(inner, 0.2);
+            parse_noise(&mut p, entries)?;
+            p
+        }
+        _ => {
+            let pattern_type = match kind.as_str() {
+                "stripes" => patterns::PatternType::Stripe,
+                "gradient" => patterns::PatternType::Gradient,
+                "rings" => patterns::PatternType::Ring,
+                "checkers" => patterns::PatternType::Checkers,
+                "radial-gradient" => patterns::PatternType::RadialGradient,
+                "blend" => patterns::PatternType::Blend,
+                "marble" => patterns::PatternType::Marble,
+                "wood" => patterns::PatternType::Wood,
+                other => {
+                    return Err(error(
+                        kind_node,
+                        &format!("unknown pattern type '{}'", other),
+                    ))
+                }
+            };
+            let mut allowed = vec!["type", "colors", "transform"];
+            match pattern_type {
+                patterns::PatternType::Marble | patterns::PatternType::Wood => {
+                    allowed.extend(&["scale", "octaves", "persistence"])
+                }
+                patterns::PatternType::Blend => allowed.push("weight"),
+                _ => (),
+            }
+            check_keys(entries, "pattern", &allowed)?;
+            let mut p = patterns::PATTERN_DEFAULT;
+            p.pattern_type = pattern_type;
+            parse_slots(&mut p, node, entries, defines, dir)?;
+            parse_noise(&mut p, entries)?;
+            if let Some(n) = get(entries, "weight") {
+                p.weight = number(n)?;
+            }
+            p
+        }
+    };
+    if let Some(t) = get(entries, "transform") {
+        p.set_transform(parse_transform(t, defines)?);
+    }
+    Ok(p)
+}
+
+//each of the two colors can be a whole pattern instead
+fn parse_slots(
+    p: &mut patterns::Pattern,
+    node: &Node,
+    entries: &[(Node, Node)],
+    defines: &HashMap<String, Node>,
+    dir: &Path,
+) -> Result<(), String> {
+    let colors_node = require(node, entries, "colors")?;
+    let colors = sequence(colors_node)?;
+    if colors.len() != 2 {
+        return Err(error(
+            colors_node,
+            "expected a list of 2 colors or patterns",
+        ));
+    }
+    if let Some(c) = parse_slot(p, 0, &colors[0], defines, dir)? {
+        p.a = c;
+    }
+    if let Some(c) = parse_slot(p, 1, &colors[1], defines, dir)? {
+        p.b = c;
+    }
+    Ok(())
+}
+
+//a plain color, or else a pattern which is nested in the given slot
+fn parse_slot(
+    p: &mut patterns::Pattern,
+    slot: usize,
+    node: &Node,
+    defines: &HashMap<String, Node>,
+    dir: &Path,
+) -> Result<Option<tuples::Color>, String> {
+    match &node.value {
+        NodeValue::Mapping(_) => {
+            p.set_nested(slot, parse_pattern(node, defines, dir)?);
+            Ok(None)
+        }
+        _ => Ok(Some(color(node)?)),
+    }
+}
+
+fn parse_noise(p: &mut patterns::Pattern, entries: &[(Node, Node)]) -> Result<(), String> {
+    if let Some(n) = get(entries, "scale") {
+        p.noise_scale = number(n)?;
+    }
+    if let Some(n) = get(entries, "octaves") {
+        p.octaves = integer(n)?;
+    }
+    if let Some(n) = get(entries, "persistence") {
+        p.persistence = number(n)?;
+    }
+    Ok(())
+}
+
+fn two_colors(
+    node: &Node,
+    entries: &[(Node, Node)],
+) -> Result<(tuples::Color, tuples::Color), String> {
+    let colors_node = require(node, entries, "colors")?;
+    let colors = sequence(colors_node)?;
+    if colors.len() != 2 {
+        return Err(error(colors_node, "expected a list of 2 colors"));
+    }
+    Ok((color(&colors[0])?, color(&colors[1])?))
+}
+
+fn uv_mapping(node: &Node) -> Result<patterns::UvMapping, String> {
+    match scalar(node)?.as_str() {
+        "planar" => Ok(patterns::UvMapping::Planar),
+        "spherical" => Ok(patterns::UvMapping::Spherical),
+        "cylindrical" => Ok(patterns::UvMapping::Cylindrical),
+        "cube" => Ok(patterns::UvMapping::Cube),
+        "mesh" => Ok(patterns::UvMapping::Mesh),
+        other => Err(error(node, &format!("unknown mapping '{}'", other))),
+    }
+}
+
+fn parse_mapped_pattern(
+    node: &Node,
+    entries: &[(Node, Node)],
+    defines: &HashMap<String, Node>,
+    dir: &Path,
+) -> Result<patterns::Pattern, String> {
+    let mapping = uv_mapping(require(node, entries, "mapping")?)?;
+    //a cube can have its own pattern on each face instead of one for all six
+    if mapping == patterns::UvMapping::Cube && get(entries, "uv_pattern").is_none() {
+        let face = |name: &str| parse_uv_pattern(require(node, entries, name)?, defines, dir);
+        return Ok(patterns::cube_map_pattern(
+            face("left")?,
+            face("front")?,
+            face("right")?,
+            face("back")?,
+            face("up")?,
+            face("down")?,
+        ));
+    }
+    let uv_pattern = parse_uv_pattern(require(node, entries, "uv_pattern")?, defines, dir)?;
+    Ok(patterns::texture_map(uv_pattern, mapping))
+}
+
+fn parse_uv_pattern(
+    node: &Node,
+    defines: &HashMap<String, Node>,
+    dir: &Path,
+) -> Result<patterns::Pattern, String> {
+    let entries = mapping(node)?;
+    let kind_node = require(node, entries, "type")?;
+    match scalar(kind_node)?.as_str() {
+        "checkers" => {
+            check_keys(
+                entries,
+                "uv pattern",
+                &["type", "width", "height", "colors"],
+            )?;
+            let width = number(require(node, entries, "width")?)?;
+            let height = number(require(node, entries, "height")?)?;
+            let mut p = patterns::uv_checkers_pattern(
+                width,
+                height,
+                tuples::COLOR_WHITE,
+                tuples::COLOR_BLACK,
+            );
+            parse_slots(&mut p, node, entries, defines, dir)?;
+            Ok(p)
+        }
+        "align_check" => {
+            check_keys(entries, "uv pattern", &["type", "colors"])?;
+            let colors_node = require(node, entries, "colors")?;
+            let colors = mapping(colors_node)?;
+            let names = ["main", "ul", "ur", "bl", "br"];
+            check_keys(colors, "colors", &names)?;
+            let mut p = patterns::align_check_pattern(
+                tuples::COLOR_WHITE,
+                tuples::COLOR_BLACK,
+                tuples::COLOR_BLACK,
+                tuples::COLOR_BLACK,
+                tuples::COLOR_BLACK,
+            );
+            //main is slot 0 and the corners follow it
+            for (slot, name) in names.iter().enumerate() {
+                let c = parse_slot(
+                    &mut p,
+                    slot,
+                    require(colors_node, colors, name)?,
+                    defines,
+                    dir,
+                )?;
+                match (slot, c) {
+                    (0, Some(c)) => p.a = c,
+                    (_, Some(c)) => p.corners[slot - 1] = c,
+                    (_, None) => (),
+                }
+            }
+            Ok(p)
+        }
+        "image" => {
+            check_keys(entries, "uv pattern", &["type", "file", "filter", "wrap"])?;
+            parse_image_pattern(node, entries, dir)
+        }
+        other => Err(error(
+            kind_node,
+            &format!("unknown uv pattern type '{}'", other),
+        )),
+    }
+}
+
+fn parse_image_pattern(
+    node: &Node,
+    entries: &[(Node, Node)],
+    dir: &Path,
+) -> Result<patterns::Pattern, String> {
+    let file = require(node, entries, "file")?;
+    let texture =
+        canvas::pixel_canvas_from_path(&file_path(file, dir)?).map_err(|e| error(file, &e))?;
+    let mut p = patterns::image_pattern(texture);
+    if let Some(n) = get(entries, "filter") {
+        p.filter = match scalar(n)?.as_str() {
+            "nearest" => patterns::TextureFilter::Nearest,
+            "bilinear" => patterns::TextureFilter::Bilinear,
+            other => return Err(error(n, &format!("unknown filter '{}'", other))),
+        };
+    }
+    if let Some(n) = get(entries, "wrap") {
+        p.wrap = match scalar(n)?.as_str() {
+            "repeat" => patterns::TextureWrap::Repeat,
+            "clamp" => patterns::TextureWrap::Clamp,
+            other => return Err(error(n, &format!("unknown wrap '{}'", other))),
+        };
+    }
+    Ok(p)
+}
+
+//relative paths are found from dir, absolute ones are left alone
+fn file_path(node: &Node, dir: &Path) -> Result<String, String> {
+    Ok(dir.join(scalar(node)?).to_string_lossy().into_owned())
+}
+
+fn parse_transform(
+    node: &Node,
+    defines: &HashMap<String, Node>,
+) -> Result<matrices::Matrix4, String> {
+    let mut chain: Vec<matrices::Matrix4> = vec![];
+    add_transforms(node, defines, &mut chain)?;
+    Ok(transformations::matrix4_transform_chain(&chain))
+}
+
+fn add_transforms(
+    node: &Node,
+    defines: &HashMap<String, Node>,
+    chain: &mut Vec<matrices::Matrix4>,
+) -> Result<(), String> {
+    for item in sequence(node)? {
+        match &item.value {
+            //a named transform list from a 'define'
+            NodeValue::Scalar(_) => add_transforms(lookup(item, defines)?, defines, chain)?,
+            NodeValue::Sequence(parts) => {
+                if parts.is_empty() {
+                    return Err(error(item, "empty transform"));
+                }
+                let op = scalar(&parts[0])?;
+                let args = &parts[1..];
+                let expected = match op.as_str() {
+                    "translate" | "scale" => 3,
+                    "rotate-x" | "rotate-y" | "rotate-z" => 1,
+                    "shear" => 6,
+                    _ => return Err(error(&parts[0], &format!("unknown transform '{}'", op))),
+                };
+                if args.len() != expected {
+                    return Err(error(
+                        item,
+                        &format!(
+                            "'{}' expects {} numbers, found {}",
+                            op,
+                            expected,
+                            args.len()
+                        ),
+                    ));
+                }
+                let n: Vec<f64> = args.iter().map(number).collect::<Result<_, _>>()?;
+                chain.push(match op.as_str() {
+                    "translate" => transformations::matrix4_translation(n[0], n[1], n[2]),
+                    "scale" => transformations::matrix4_scaling(n[0], n[1], n[2]),
+                    "rotate-x" => transformations::matrix4_rotation_x_rad(n[0]),
+                    "rotate-y" => transformations::matrix4_rotation_y_rad(n[0]),
+                    "rotate-z" => transformations::matrix4_rotation_z_rad(n[0]),
+                    _ => transformations::matrix4_shearing(n[0], n[1], n[2], n[3], n[4], n[5]),
+                });
+            }
+            _ => return Err(error(item, "expected a transform like [ scale, 1, 2, 3 ]")),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    const SCENE: &str = "
+- add: camera
+  width: 100
+  height: 50
+  field-of-view: 0.785
+  from: [ 0, 1.5, -5 ]
+  to: [ 0, 1, 0 ]
+  up: [ 0, 1, 0 ]
+
+- add: light
+  at: [ -10, 10, -10 ]
+  intensity: [ 1, 1, 1 ]
+
+- define: white-material
+  value:
+    color: [ 1, 1, 1 ]
+    diffuse: 0.7
+    reflective: 0.1
+
+- define: blue-material
+  extend: white-material
+  value:
+    color: [ 0.5, 0.8, 0.9 ]
+
+- define: standard-transform
+  value:
+    - [ translate, 1, -1, 1 ]
+    - [ scale, 0.5, 0.5, 0.5 ]
+
+- add: plane
+  material:
+    pattern:
+      type: checkers
+      colors:
+        - [ 1, 1, 1 ]
+        - [ 0, 0, 0 ]
+      transform:
+        - [ scale, 2, 2, 2 ]
+
+- add: cube
+  material: blue-material
+  transform:
+    - standard-transform
+    - [ rotate-y, 1.5707963 ]
+
+- add: sphere
+";
+
+    #[test]
+    fn test_loading_a_scene_builds_world_and_camera() {
+        //Loading a scene builds the world's objects and lights, and the camera
+        let scene = scene_from_str(SCENE).unwrap();
+        assert_eq!(scene.camera.hsize, 100);
+        assert_eq!(scene.camera.vsize, 50);
+        assert!(tuples::get_bool_numbers_are_equal(
+            &scene.camera.field_of_view,
+            &0.785
+        ));
+        let from = tuples::point(0.0, 1.5, -5.0);
+        let to = tuples::point(0.0, 1.0, 0.0);
+        let up = tuples::vector(0.0, 1.0, 0.0);
+        assert!(scene
+            .camera
+            .transform()
+            .is_equal_to(&transformations::view_transform(&from, &to, &up)));
+        assert_eq!(scene.world.light.len(), 1);
+        assert!(scene.world.light[0]
+            .position
+            .is_equal_to(&tuples::point(-10.0, 10.0, -10.0)));
+        assert_eq!(scene.world.objects.len(), 3);
+    }
+
+    #[test]
+    fn test_defines_can_extend_materials_and_transforms() {
+        //A define can extend another, and transforms can use defined transform lists
+        let scene = scene_from_str(SCENE).unwrap();
+        let cube = &scene.world.objects[1];
+        assert!(cube
+            .material
+            .color
+            .is_equal_to(&tuples::color(0.5, 0.8, 0.9)));
+        assert!(tuples::get_bool_numbers_are_equal(
+            &cube.material.diffuse,
+            &0.7
+        ));
+        assert!(tuples::get_bool_numbers_are_equal(
+            &cube.material.reflective,
+            &0.1
+        ));
+        let expected = transformations::matrix4_transform_chain(&vec![
+            transformations::matrix4_translation(1.0, -1.0, 1.0),
+            transformations::matrix4_scaling(0.5, 0.5, 0.5),
+            transformations::matrix4_rotation_y_rad(PI / 2.0),
+        ]);
+        assert!(cube.transform().is_equal_to(&expected));
+    }
+
+    #[test]
+    fn test_materials_can_turn_off_shadows() {
+        //A material with shadow: false doesn't cast shadows
+        let yaml = format!(
+            "{}- add: sphere\n  material:\n    transparency: 0.8\n    shadow: false\n- add: sphere\n",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        assert!(!scene.world.objects[0].material.casts_shadow);
+        assert!(scene.world.objects[1].material.casts_shadow);
+    }
+
+    #[test]
+    fn test_patterns_are_loaded_onto_materials() {
+        //A material's pattern is loaded with its colors and transform
+        let scene = scene_from_str(SCENE).unwrap();
+        let plane = &scene.world.objects[0];
+        let p = plane.material.pattern.as_ref().unwrap();
+        assert!(p.a.is_equal_to(&tuples::COLOR_WHITE));
+        assert!(p.b.is_equal_to(&tuples::COLOR_BLACK));
+        assert!(p
+            .transform()
+            .is_equal_to(&transformations::matrix4_scaling(2.0, 2.0, 2.0)));
+    }
+
+    #[test]
+    fn test_cylinders_and_cones_are_loaded_with_min_max_and_closed() {
+        //A cylinder or cone is loaded with its truncation and caps
+        let yaml = format!(
+            "{}- add: cylinder\n  min: -1\n  max: 2.5\n  closed: true\n- add: cone\n  max: 0\n",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let cyl = &scene.world.objects[0];
+        assert_eq!(cyl.minimum, -1.0);
+        assert_eq!(cyl.maximum, 2.5);
+        assert!(cyl.closed);
+        let cone = &scene.world.objects[1];
+        assert_eq!(cone.maximum, 0.0);
+        assert!(!cone.closed);
+        let e = scene_from_str("- add: sphere\n  closed: true\n").unwrap_err();
+        assert_eq!(e, "line 2: unknown key 'closed' for sphere");
+        let e = scene_from_str("- add: cylinder\n  closed: yes please\n").unwrap_err();
+        assert_eq!(e, "line 2: expected true or false, found 'yes please'");
+    }
+
+    #[test]
+    fn test_groups_load_children_which_inherit_the_group_material() {
+        //A group's children are nested and take its material unless they have their own
+        let yaml = format!(
+            "{}{}",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            "- add: group
+  material:
+    color: [ 1, 0, 0 ]
+  transform:
+    - [ translate, 0, 1, 0 ]
+  children:
+    - add: sphere
+    - add: group
+      children:
+        - add: cube
+          material:
+            color: [ 0, 0, 1 ]
+"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let g = &scene.world.objects[0];
+        assert_eq!(g.children().len(), 2);
+        let red = tuples::color(1.0, 0.0, 0.0);
+        assert!(g.children()[0].material.color.is_equal_to(&red));
+        assert!(g.children()[1].material.color.is_equal_to(&red));
+        let cube = &g.children()[1].children()[0];
+        assert!(cube
+            .material
+            .color
+            .is_equal_to(&tuples::color(0.0, 0.0, 1.0)));
+        let e = scene_from_str("- add: sphere\n  children: []\n").unwrap_err();
+        assert_eq!(e, "line 2: unknown key 'children' for sphere");
+    }
+
+    #[test]
+    fn test_triangles_are_loaded_with_their_vertices() {
+        //A triangle needs its three points, and a smooth triangle its normals too
+        let yaml = format!(
+            "{}{}",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            "- add: triangle
+  p1: [ 0, 1, 0 ]
+  p2: [ -1, 0, 0 ]
+  p3: [ 1, 0, 0 ]
+- add: smooth-triangle
+  p1: [ 0, 1, 0 ]
+  p2: [ -1, 0, 0 ]
+  p3: [ 1, 0, 0 ]
+  n1: [ 0, 1, 0 ]
+  n2: [ -1, 0, 0 ]
+  n3: [ 1, 0, 0 ]
+"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let tri = scene.world.objects[0].triangle.unwrap();
+        assert!(tri.normal.is_equal_to(&tuples::vector(0.0, 0.0, -1.0)));
+        let smooth = scene.world.objects[1].triangle.unwrap();
+        assert!(smooth.n2.is_equal_to(&tuples::vector(-1.0, 0.0, 0.0)));
+        let e =
+            scene_from_str("- add: triangle\n  p1: [ 0, 1, 0 ]\n  p2: [ 1, 0, 0 ]\n").unwrap_err();
+        assert_eq!(e, "line 1: missing 'p3'");
+    }
+
+    #[test]
+    fn test_obj_files_are_loaded_as_groups() {
+        //An obj file is added as a group of its triangles, taking the given material,
+        //and the scene records how many of its lines were skipped
+        let path = std::env::temp_dir().join("scenes_test_obj_files_are_loaded_as_groups.obj");
+        fs::write(
+            &path,
+            "o quad\nv -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\ns 1\nf 1 2 3 4\n",
+        )
+        .unwrap();
+        let yaml = format!(
+            "{}- add: obj\n  file: {}\n  material:\n    color: [ 1, 0, 0 ]\n",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            path.display()
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let g = &scene.world.objects[0];
+        assert_eq!(g.children().len(), 2);
+        assert!(g.children()[1]
+            .material
+            .color
+            .is_equal_to(&tuples::color(1.0, 0.0, 0.0)));
+        //the unsupported 'o' and 's' lines are counted for the caller to report
+        assert_eq!(scene.skipped, vec![(path.display().to_string(), 2)]);
+        let e = scene_from_str("- add: obj\n  file: /no/such/model.obj\n").unwrap_err();
+        assert_eq!(
+            e,
+            "line 2: /no/such/model.obj: No such file or directory (os error 2)"
+        );
+    }
+
+    #[test]
+    fn test_scene_files_find_their_files_relative_to_themselves() {
+        //A scene file names its obj, image, bump and background files relative to
+        //its own directory rather than to wherever the renderer was started from
+        let dir = std::env::temp_dir().join("scenes_test_scene_files_find_their_files");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("quad.obj"),
+            "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nf 1 2 3 4\n",
+        )
+        .unwrap();
+        fs::write(dir.join("texture.ppm"), "P3\n1 1\n255\n255 0 0\n").unwrap();
+        let scene_path = dir.join("scene.yml");
+        fs::write(
+            &scene_path,
+            format!(
+                "{}{}{}",
+                "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+                "- add: background\n  type: equirectangular\n  file: texture.ppm\n",
+                "- add: obj\n  file: quad.obj\n  material:\n    pattern:\n      type: image\n      file: texture.ppm\n    bump:\n      type: normal-map\n      file: texture.ppm\n",
+            ),
+        )
+        .unwrap();
+        let scene = scene_from_file(&scene_path.display().to_string()).unwrap();
+        let g = &scene.world.objects[0];
+        assert_eq!(g.children().len(), 2);
+        let p = g.children()[0].material.pattern.as_ref().unwrap();
+        assert!(p.uv_image_at(0.0, 0.0).is_equal_to(&tuples::COLOR_RED));
+        assert!(g.children()[0].material.bump.is_some());
+        //a bare string scene still looks in the current directory
+        let e = scene_from_str("- add: obj\n  file: quad.obj\n").unwrap_err();
+        assert_eq!(
+            e,
+            "line 2: quad.obj: No such file or directory (os error 2)"
+        );
+    }
+
+    #[test]
+    fn test_image_patterns_load_their_texture() {
+        //An image pattern loads its file with the given filter and wrap
+        let path = std::env::temp_dir().join("scenes_test_image_patterns_load_their_texture.ppm");
+        fs::write(&path, "P3\n2 1\n255\n255 0 0 0 0 255\n").unwrap();
+        let yaml = format!(
+            "{}- add: plane\n  material:\n    pattern:\n      type: image\n      file: {}\n      filter: nearest\n      wrap: clamp\n",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            path.display()
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let p = scene.world.objects[0].material.pattern.as_ref().unwrap();
+        assert_eq!(p.filter, patterns::TextureFilter::Nearest);
+        assert_eq!(p.wrap, patterns::TextureWrap::Clamp);
+        assert!(p.uv_image_at(0.0, 0.0).is_equal_to(&tuples::COLOR_RED));
+        assert!(p
+            .uv_image_at(1.0, 0.0)
+            .is_equal_to(&tuples::color(0.0, 0.0, 1.0)));
+        let yaml = "- add: plane\n  material:\n    pattern:\n      type: image\n      file: /no/such/texture.png\n";
+        let e = scene_from_str(yaml).unwrap_err();
+        assert!(e.starts_with("line 5: Problem loading /no/such/texture.png"));
+    }
+
+    #[test]
+    fn test_mapped_patterns_are_loaded_with_their_uv_patterns() {
+        //A map pattern wraps its uv pattern, or one per face for a cube
+        let yaml = format!(
+            "{}{}",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            "- add: sphere
+  material:
+    pattern:
+      type: map
+      mapping: spherical
+      uv_pattern:
+        type: checkers
+        width: 16
+        height: 8
+        colors:
+          - [ 0, 0, 0 ]
+          - [ 1, 1, 1 ]
+- add: cube
+  material:
+    pattern:
+      type: map
+      mapping: cube
+      left: { type: align_check, colors: { main: [ 1, 1, 0 ], ul: [ 0, 1, 1 ], ur: [ 1, 0, 0 ], bl: [ 0, 0, 1 ], br: [ 1, 0.5, 0 ] } }
+      front: { type: align_check, colors: { main: [ 0, 1, 1 ], ul: [ 1, 0, 0 ], ur: [ 1, 1, 0 ], bl: [ 1, 0.5, 0 ], br: [ 0, 1, 0 ] } }
+      right: { type: align_check, colors: { main: [ 1, 0, 0 ], ul: [ 1, 1, 0 ], ur: [ 1, 0, 1 ], bl: [ 0, 1, 0 ], br: [ 1, 1, 1 ] } }
+      back: { type: align_check, colors: { main: [ 0, 1, 0 ], ul: [ 1, 0, 1 ], ur: [ 0, 1, 1 ], bl: [ 1, 1, 1 ], br: [ 0, 0, 1 ] } }
+      up: { type: align_check, colors: { main: [ 1, 0.5, 0 ], ul: [ 0, 1, 1 ], ur: [ 1, 0, 1 ], bl: [ 1, 0, 0 ], br: [ 1, 1, 0 ] } }
+      down:
+        type: checkers
+        width: 2
+        height: 2
+        colors:
+          - [ 1, 0, 0 ]
+          - [ 0, 0, 1 ]
+"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let sphere = scene.world.objects[0].material.pattern.as_ref().unwrap();
+        assert_eq!(sphere.mapping, patterns::UvMapping::Spherical);
+        assert!(sphere
+            .pattern_at(&tuples::point(0.4315, 0.4670, 0.7719))
+            .is_equal_to(&tuples::COLOR_WHITE));
+        let cube = scene.world.objects[1].material.pattern.as_ref().unwrap();
+        assert_eq!(cube.children.len(), 6);
+        assert!(cube
+            .pattern_at(&tuples::point(-1.0, 0.9, 0.9))
+            .is_equal_to(&tuples::COLOR_RED));
+        assert!(cube
+            .pattern_at(&tuples::point(-0.75, -1.0, -0.75))
+            .is_equal_to(&tuples::COLOR_RED));
+        let yaml =
+            "- add: plane\n  material:\n    pattern:\n      type: map\n      mapping: conical\n";
+        let e = scene_from_str(yaml).unwrap_err();
+        assert_eq!(e, "line 5: unknown mapping 'conical'");
+    }
+
+    #[test]
+    fn test_uv_pattern_colors_can_be_nested_patterns() {
+        //A uv checker or align check color can be a pattern, laid on its uv square
+        let yaml = format!(
+            "{}{}",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            "- add: plane
+  material:
+    pattern:
+      type: map
+      mapping: planar
+      uv_pattern:
+        type: checkers
+        width: 2
+        height: 2
+        colors:
+          - type: stripes
+            colors:
+              - [ 1, 0, 0 ]
+              - [ 0, 0, 1 ]
+            transform:
+              - [ scale, 0.25, 1, 1 ]
+          - [ 0, 0, 0 ]
+- add: plane
+  material:
+    pattern:
+      type: map
+      mapping: planar
+      uv_pattern:
+        type: align_check
+        colors:
+          main: [ 1, 1, 1 ]
+          ul: [ 1, 0, 0 ]
+          ur: [ 1, 0, 0 ]
+          bl: [ 1, 0, 0 ]
+          br: { type: stripes, colors: [ [ 0, 0, 1 ], [ 0, 0, 1 ] ] }
+"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let checkers = scene.world.objects[0].material.pattern.as_ref().unwrap();
+        let tests = [
+            (tuples::point(0.1, 0.0, 0.1), tuples::COLOR_RED),
+            (tuples::point(0.3, 0.0, 0.1), tuples::color(0.0, 0.0, 1.0)),
+            (tuples::point(0.6, 0.0, 0.1), tuples::COLOR_BLACK),
+        ];
+        for (p, expected) in tests.iter() {
+            assert!(checkers.pattern_at(p).is_equal_to(expected));
+        }
+        let align = scene.world.objects[1].material.pattern.as_ref().unwrap();
+        assert!(align
+            .pattern_at(&tuples::point(0.9, 0.0, 0.1))
+            .is_equal_to(&tuples::color(0.0, 0.0, 1.0)));
+        assert!(align
+            .pattern_at(&tuples::point(0.1, 0.0, 0.1))
+            .is_equal_to(&tuples::COLOR_RED));
+    }
+
+    #[test]
+    fn test_backgrounds_are_loaded_onto_the_world() {
+        //A background replaces the default black seen by rays that miss
+        let camera = "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n";
+        let scene = scene_from_str(camera).unwrap();
+        assert_eq!(
+            scene.world.background.background_type,
+            backgrounds::BackgroundType::Flat
+        );
+        let yaml = format!(
+            "{}- add: background\n  type: gradient\n  colors:\n    - [ 1, 1, 1 ]\n    - [ 0.2, 0.4, 1 ]\n",
+            camera
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let up = scene
+            .world
+            .background
+            .color_at(&tuples::vector(0.0, 1.0, 0.0));
+        assert!(up.is_equal_to(&tuples::color(0.2, 0.4, 1.0)));
+        let path =
+            std::env::temp_dir().join("scenes_test_backgrounds_are_loaded_onto_the_world.ppm");
+        fs::write(&path, "P3\n1 1\n255\n255 0 0\n").unwrap();
+        let yaml = format!(
+            "{}- add: background\n  type: equirectangular\n  file: {}\n",
+            camera,
+            path.display()
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let col = scene
+            .world
+            .background
+            .color_at(&tuples::vector(0.3, 0.2, -1.0));
+        assert!(col.is_equal_to(&tuples::COLOR_RED));
+        let yaml = "- add: background\n  type: stars\n";
+        let e = scene_from_str(yaml).unwrap_err();
+        assert_eq!(e, "line 2: unknown background type 'stars'");
+    }
+
+    #[test]
+    fn test_noise_patterns_are_loaded_with_their_settings() {
+        //Perturb wraps its inner pattern, and marble and wood take noise settings
+        let yaml = format!(
+            "{}{}",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            "- add: plane
+  material:
+    pattern:
+      type: perturb
+      scale: 0.3
+      octaves: 2
+      pattern:
+        type: stripes
+        colors:
+          - [ 1, 1, 1 ]
+          - [ 0, 0, 0 ]
+        transform:
+          - [ scale, 0.5, 1, 1 ]
+- add: sphere
+  material:
+    pattern:
+      type: wood
+      colors:
+        - [ 0.6, 0.4, 0.2 ]
+        - [ 0.3, 0.2, 0.1 ]
+      scale: 0.5
+      persistence: 0.7
+"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let p = scene.world.objects[0].material.pattern.as_ref().unwrap();
+        assert_eq!(p.noise_scale, 0.3);
+        assert_eq!(p.octaves, 2);
+        assert_eq!(p.persistence, 0.5);
+        assert!(p.children[0]
+            .as_ref()
+            .unwrap()
+            .transform()
+            .is_equal_to(&transformations::matrix4_scaling(0.5, 1.0, 1.0)));
+        let p = scene.world.objects[1].material.pattern.as_ref().unwrap();
+        assert_eq!(p.noise_scale, 0.5);
+        assert_eq!(p.octaves, 4);
+        assert_eq!(p.persistence, 0.7);
+        assert!(p.a.is_equal_to(&tuples::color(0.6, 0.4, 0.2)));
+    }
+
+    #[test]
+    fn test_pattern_colors_can_be_nested_patterns() {
+        //Either color of a pattern can be another pattern with its own transform
+        let yaml = format!(
+            "{}{}",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            "- add: plane
+  material:
+    pattern:
+      type: stripes
+      colors:
+        - type: checkers
+          colors:
+            - [ 1, 0, 0 ]
+            - [ 0, 0, 0 ]
+          transform:
+            - [ scale, 0.25, 0.25, 0.25 ]
+        - [ 1, 1, 1 ]
+- add: plane
+  material:
+    pattern:
+      type: blend
+      weight: 0.25
+      colors:
+        - type: radial-gradient
+          colors:
+            - [ 1, 1, 1 ]
+            - [ 0, 0, 0 ]
+        - [ 0, 0, 0 ]
+"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let p = scene.world.objects[0].material.pattern.as_ref().unwrap();
+        assert!(p.children.get(1).is_none());
+        assert!(p
+            .pattern_at(&tuples::point(0.3, 0.1, 0.1))
+            .is_equal_to(&tuples::COLOR_BLACK));
+        assert!(p
+            .pattern_at(&tuples::point(0.6, 0.1, 0.1))
+            .is_equal_to(&tuples::COLOR_RED));
+        let p = scene.world.objects[1].material.pattern.as_ref().unwrap();
+        assert_eq!(p.weight, 0.25);
+        assert!(p
+            .pattern_at(&tuples::point(0.5, 0.0, 0.0))
+            .is_equal_to(&tuples::color(0.375, 0.375, 0.375)));
+    }
+
+    #[test]
+    fn test_bumps_are_loaded_onto_materials() {
+        //A material's bump is loaded with its amount and transform, or its normal map
+        let path = std::env::temp_dir().join("scenes_test_bumps_are_loaded_onto_materials.ppm");
+        fs::write(&path, "P3\n1 1\n255\n255 128 255\n").unwrap();
+        let yaml = format!(
+            "{}- add: plane\n  material:\n    bump:\n      type: ripples\n      amount: 0.5\n      transform:\n        - [ scale, 2, 2, 2 ]\n- add: sphere\n  material:\n    bump:\n      type: normal-map\n      file: {}\n      mapping: spherical\n- add: cube\n  material:\n    bump:\n      type: noise\n      octaves: 2\n",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            path.display()
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let b = scene.world.objects[0].material.bump.as_ref().unwrap();
+        assert_eq!(b.bump_type, bumps::BumpType::Ripples);
+        assert_eq!(b.amount, 0.5);
+        assert!(b
+            .transform()
+            .is_equal_to(&transformations::matrix4_scaling(2.0, 2.0, 2.0)));
+        let b = scene.world.objects[1].material.bump.as_ref().unwrap();
+        assert_eq!(b.bump_type, bumps::BumpType::NormalMap);
+        assert_eq!(b.amount, 1.0);
+        let map = b.normal_map.as_ref().unwrap();
+        assert_eq!(map.mapping, patterns::UvMapping::Spherical);
+        let b = scene.world.objects[2].material.bump.as_ref().unwrap();
+        assert_eq!(b.bump_type, bumps::BumpType::Noise);
+        assert_eq!(b.octaves, 2);
+        let yaml = "- add: plane\n  material:\n    bump:\n      type: dents\n";
+        let e = scene_from_str(yaml).unwrap_err();
+        assert_eq!(e, "line 4: unknown bump type 'dents'");
+    }
+
+    #[test]
+    fn test_csgs_are_loaded_with_their_operation_and_shapes() {
+        //A csg is loaded with its operation and left and right shapes
+        let yaml = format!(
+            "{}{}",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n",
+            "- add: csg
+  operation: difference
+  left:
+    add: cube
+  right:
+    add: sphere
+    transform:
+      - [ scale, 1.3, 1.3, 1.3 ]
+"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let c = &scene.world.objects[0];
+        assert_eq!(c.operation, Some(csgs::CsgOperation::Difference));
+        assert_eq!(c.children().len(), 2);
+        let e = scene_from_str("- add: csg\n  operation: xor\n").unwrap_err();
+        assert_eq!(e, "line 2: unknown operation 'xor'");
+    }
+
+    #[test]
+    fn test_unknown_key_reports_its_line() {
+        //An unknown key is reported with its line number
+        let yaml = "- add: sphere\n  colour: [ 1, 0, 0 ]\n";
+        let e = scene_from_str(yaml).unwrap_err();
+        assert_eq!(e, "line 2: unknown key 'colour' for sphere");
+    }
+
+    #[test]
+    fn test_area_lights_have_a_corner_and_edges() {
+        //A light with a corner is an area light split into u and v steps
+        let yaml = format!(
+            "{}- add: light\n  corner: [ -1, 2, 4 ]\n  uvec: [ 2, 0, 0 ]\n  usteps: 4\n  vvec: [ 0, 2, 0 ]\n  vsteps: 2\n  jitter: true\n  intensity: [ 1, 1, 1 ]\n",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let light = scene.world.light[0];
+        assert_eq!(light.light_type, lights::LightType::Area);
+        assert!(light.corner.is_equal_to(&tuples::point(-1.0, 2.0, 4.0)));
+        assert!(light.uvec.is_equal_to(&tuples::vector(0.5, 0.0, 0.0)));
+        assert!(light.vvec.is_equal_to(&tuples::vector(0.0, 1.0, 0.0)));
+        assert_eq!(light.sample_positions(&tuples::POINT_ORIGIN).len(), 8);
+        assert!(light.jitter);
+        let yaml = "- add: light\n  corner: [ -1, 2, 4 ]\n  uvec: [ 2, 0, 0 ]\n  usteps: 4\n  intensity: [ 1, 1, 1 ]\n";
+        let e = scene_from_str(yaml).unwrap_err();
+        assert_eq!(e, "line 1: missing 'vvec'");
+    }
+
+    #[test]
+    fn test_spot_and_directional_lights_have_a_direction() {
+        //A light with a direction is a spot light if it has a position, else directional
+        let yaml = format!(
+            "{}- add: light\n  at: [ 0, 5, 0 ]\n  direction: [ 0, -1, 0 ]\n  inner-angle: 0.2\n  outer-angle: 0.4\n  intensity: [ 1, 1, 1 ]\n- add: light\n  direction: [ 1, -1, 0 ]\n  intensity: [ 1, 1, 1 ]\n",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let spot = scene.world.light[0];
+        assert_eq!(spot.light_type, lights::LightType::Spot);
+        assert!(spot.position.is_equal_to(&tuples::point(0.0, 5.0, 0.0)));
+        assert_eq!(spot.inner_angle, 0.2);
+        assert_eq!(spot.outer_angle, 0.4);
+        let sun = scene.world.light[1];
+        assert_eq!(sun.light_type, lights::LightType::Directional);
+        assert!(sun
+            .direction
+            .is_equal_to(&tuples::vector(1.0, -1.0, 0.0).normalize()));
+        let yaml = "- add: light\n  at: [ 0, 5, 0 ]\n  direction: [ 0, -1, 0 ]\n  intensity: [ 1, 1, 1 ]\n";
+        let e = scene_from_str(yaml).unwrap_err();
+        assert_eq!(e, "line 1: missing 'outer-angle'");
+    }
+
+    #[test]
+    fn test_lights_reject_keys_their_kind_does_not_use() {
+        //Jitter is only for area lights, angles for spot lights, and attenuation for point and spot lights
+        let tests = [
+            (
+                "- add: light\n  at: [ 0, 5, 0 ]\n  jitter: true\n  intensity: [ 1, 1, 1 ]\n",
+                "line 3: unknown key 'jitter' for point light",
+            ),
+            (
+                "- add: light\n  at: [ 0, 5, 0 ]\n  intensity: [ 1, 1, 1 ]\n  outer-angle: 0.4\n",
+                "line 4: unknown key 'outer-angle' for point light",
+            ),
+            (
+                "- add: light\n  direction: [ 0, -1, 0 ]\n  attenuation: inverse-square\n  intensity: [ 1, 1, 1 ]\n",
+                "line 3: unknown key 'attenuation' for directional light",
+            ),
+            (
+                "- add: light\n  direction: [ 0, -1, 0 ]\n  inner-angle: 0.2\n  intensity: [ 1, 1, 1 ]\n",
+                "line 3: unknown key 'inner-angle' for directional light",
+            ),
+            (
+                "- add: light\n  at: [ 0, 5, 0 ]\n  direction: [ 0, -1, 0 ]\n  outer-angle: 0.4\n  jitter: false\n  intensity: [ 1, 1, 1 ]\n",
+                "line 5: unknown key 'jitter' for spot light",
+            ),
+            (
+                "- add: light\n  corner: [ -1, 2, 4 ]\n  uvec: [ 2, 0, 0 ]\n  usteps: 4\n  vvec: [ 0, 2, 0 ]\n  vsteps: 2\n  attenuation: [ 1, 0, 0 ]\n  intensity: [ 1, 1, 1 ]\n",
+                "line 7: unknown key 'attenuation' for area light",
+            ),
+        ];
+        for (yaml, expected) in tests.iter() {
+            assert_eq!(scene_from_str(yaml).unwrap_err(), *expected);
+        }
+    }
+
+    #[test]
+    fn test_lights_can_be_attenuated() {
+        //A light's attenuation is inverse-square or a list of three coefficients
+        let yaml = format!(
+            "{}- add: light\n  at: [ 0, 5, 0 ]\n  attenuation: inverse-square\n  intensity: [ 1, 1, 1 ]\n- add: light\n  at: [ 0, 5, 0 ]\n  attenuation: [ 1, 0.2, 0.01 ]\n  intensity: [ 1, 1, 1 ]\n",
+            "- add: camera\n  width: 10\n  height: 10\n  field-of-view: 1\n  from: [ 0, 0, -5 ]\n  to: [ 0, 0, 0 ]\n  up: [ 0, 1, 0 ]\n"
+        );
+        let scene = scene_from_str(&yaml).unwrap();
+        let l = scene.world.light[0];
+        assert_eq!((l.constant, l.linear, l.quadratic), (0.0, 0.0, 1.0));
+        let l = scene.world.light[1];
+        assert_eq!((l.constant, l.linear, l.quadratic), (1.0, 0.2, 0.01));
+        let e = scene_from_str(
+            "- add: light\n  at: [ 0, 5, 0 ]\n  attenuation: lots\n  intensity: [ 1, 1, 1 ]\n",
+        )
+        .unwrap_err();
+        assert_eq!(e, "line 3: expected a list");
+    }
+
+    #[test]
+    fn test_bad_value_reports_its_line() {
+        //A value of the wrong kind is reported with its line number
+        let yaml = "- add: light\n  at: [ 1, 2, 3 ]\n  intensity: [ 1, white, 1 ]\n";
+        let e = scene_from_str(yaml).unwrap_err();
+        assert_eq!(e, "line 3: expected a number, found 'white'");
+        let yaml = "- add: light\n  at: [ 1, 2 ]\n  intensity: [ 1, 1, 1 ]\n";
+        let e = scene_from_str(yaml).unwrap_err();
+        assert_eq!(e, "line 2: expected 3 numbers, found 2");
+    }
+
+    #[test]
+    fn test_undefined_names_and_unknown_types_are_errors() {
+        //Referring to an undefined name or adding an unknown type is an error
+        let e = scene_from_str("- add: sphere\n  material: glass\n").unwrap_err();
+        assert_eq!(e, "line 2: 'glass' has not been defined");
+        let e = scene_from_str("\n- add: teapot\n").unwrap_err();
+        assert_eq!(e, "line 2: unknown type 'teapot' to add");
+        let e = scene_from_str("- add: cube\n  transform:\n    - [ twist, 1 ]\n").unwrap_err();
+        assert_eq!(e, "line 3: unknown transform 'twist'");
+    }
+
+    #[test]
+    fn test_scene_without_camera_is_an_error() {
+        //A scene must add a camera
+        let e = scene_from_str("- add: sphere\n").unwrap_err();
+        assert_eq!(e, "scene has no camera ('add: camera')");
+    }
+
+    #[test]
+    fn test_yaml_syntax_error_reports_its_line() {
+        //Malformed yaml is reported with its line number
+        let e = scene_from_str("- add: sphere\n  transform: [ [ scale, 1, 1, 1 ]\n").unwrap_err();
+        assert_eq!(
+            e,
+            "line 3: while parsing a flow sequence, expected ',' or ']'"
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//numbers in the range 0..1 for jittering samples
+//either a fixed list that repeats (handy in tests) or a seeded pseudo-random stream,
+//seeding from something like the pixel keeps renders the same whatever the thread count
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub values: Vec<f64>,
+    pub index: usize,
+    pub state: u64,
+}
+
+impl Sequence {
+    pub fn next_value(&mut self) -> f64 {
+        if self.values.is_empty() {
+            //splitmix64, so any seed (even 0) gives a good stream
+            self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = self.state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^= z >> 31;
+            //top 53 bits as a fraction in 0..1
+            (z >> 11) as f64 / (1u64 << 53) as f64
+        } else {
+            let v = self.values[self.index % self.values.len()];
+            self.index += 1;
+            v
+        }
+    }
+}
+
+pub fn sequence(values: Vec<f64>) -> Sequence {
+    Sequence {
+        values,
+        index: 0,
+        state: 0,
+    }
+}
+
+pub fn random_sequence(seed: u64) -> Sequence {
+    Sequence {
+        values: vec![],
+        index: 0,
+        state: seed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_a_number_generator_returns_a_cyclic_sequence_of_numbers() {
+        //A number generator returns a cyclic sequence of numbers
+        let mut gen = sequence(vec![0.1, 0.5, 1.0]);
+        assert_eq!(gen.next_value(), 0.1);
+        assert_eq!(gen.next_value(), 0.5);
+        assert_eq!(gen.next_value(), 1.0);
+        assert_eq!(gen.next_value(), 0.1);
+    }
+
+    #[test]
+    fn test_random_sequences_stay_in_range_and_repeat_for_a_seed() {
+        //A random sequence stays in 0..1 and is the same for the same seed
+        let mut a = random_sequence(42);
+        let mut b = random_sequence(42);
+        let mut c = random_sequence(43);
+        let mut differs = false;
+        for _ in 0..1000 {
+            let v = a.next_value();
+            assert!((0.0..1.0).contains(&v));
+            assert_eq!(v, b.next_value());
+            if v != c.next_value() {
+                differs = true;
+            }
+        }
+        assert!(differs);
+    }
+}
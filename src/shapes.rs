@@ -1,68 +1,257 @@
 use uuid::Uuid;
 
+use crate::bounds;
+use crate::cones;
+use crate::csgs;
 use crate::cubes;
+use crate::cylinders;
+use crate::groups;
 use crate::intersections;
 use crate::materials;
 use crate::matrices;
 use crate::planes;
 use crate::rays;
 use crate::spheres;
+use crate::triangles;
 use crate::tuples;
 
 #[derive(Debug, Clone)]
 pub struct Shape {
     pub id: String,
-    pub transform: matrices::Matrix4,
+    //set with set_transform, which keeps the inverses below in step
+    transform: matrices::Matrix4,
+    transform_inverse: matrices::Matrix4,
+    transform_inverse_transpose: matrices::Matrix4,
     pub material: materials::Material,
     pub shape_type: ShapeType,
+    //only used by truncated shapes like cylinders and cones
+    pub minimum: f64,
+    pub maximum: f64,
+    pub closed: bool,
+    //only used by groups, and by csgs which hold their left and right shapes here.
+    //changed through add_child and children_mut, which keep the cached bounds right
+    children: Vec<Shape>,
+    //only used by groups, true for the ones the bvh adds which aren't objects of their own
+    pub partition: bool,
+    //inverse transforms of the groups containing this shape, innermost first,
+    //and their transposes for normals. pushed by add_parent_inverse as
+    //intersections pass back up through each group
+    parent_inverses: Vec<matrices::Matrix4>,
+    parent_inverse_transposes: Vec<matrices::Matrix4>,
+    //only used by triangles and smooth triangles
+    pub triangle: Option<triangles::Triangle>,
+    //only used by csgs
+    pub operation: Option<csgs::CsgOperation>,
+    //cached bounds of a group or csg, kept by add_child and divide, so rays that miss skip all the children
+    bounds: Option<bounds::BoundingBox>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum ShapeType {
+    Cone,
+    Csg,
     Cube,
+    Cylinder,
+    Group,
     Plane,
     ShapeTest,
+    SmoothTriangle,
     Sphere,
+    Triangle,
 }
 
 pub fn shape(shape_type: ShapeType) -> Shape {
     Shape {
         id: format!("{}", Uuid::new_v4()),
         transform: matrices::IDENTITY_MATRIX,
+        transform_inverse: matrices::IDENTITY_MATRIX,
+        transform_inverse_transpose: matrices::IDENTITY_MATRIX,
         material: materials::MATERIAL_DEFAULT,
-        shape_type: shape_type,
+        shape_type,
+        minimum: -f64::INFINITY,
+        maximum: f64::INFINITY,
+        closed: false,
+        children: vec![],
+        partition: false,
+        parent_inverses: vec![],
+        parent_inverse_transposes: vec![],
+        triangle: None,
+        operation: None,
+        bounds: None,
     }
 }
 
 impl Shape {
+    pub fn set_transform(&mut self, transform: matrices::Matrix4) {
+        self.transform = transform;
+        self.transform_inverse = transform.inverse();
+        self.transform_inverse_transpose = self.transform_inverse.transpose();
+    }
+
+    pub fn transform(&self) -> &matrices::Matrix4 {
+        &self.transform
+    }
+
+    pub fn transform_inverse(&self) -> &matrices::Matrix4 {
+        &self.transform_inverse
+    }
+
+    pub fn parent_inverses(&self) -> &[matrices::Matrix4] {
+        &self.parent_inverses
+    }
+
+    pub fn add_parent_inverse(&mut self, parent_inverse: &matrices::Matrix4) {
+        self.parent_inverses.push(*parent_inverse);
+        self.parent_inverse_transposes
+            .push(parent_inverse.transpose());
+    }
+
     pub fn intersect(&self, r: &rays::Ray) -> Result<Vec<intersections::Intersection>, String> {
-        let local_r: rays::Ray = r.transform(self.transform.inverse());
+        let local_r: rays::Ray = r.transform(self.transform_inverse);
         match self.shape_type {
-            ShapeType::Cube => cubes::local_intersect(&self, &local_r),
-            ShapeType::Plane => planes::local_intersect(&self, &local_r),
+            ShapeType::Cone => cones::local_intersect(self, &local_r),
+            ShapeType::Csg => csgs::local_intersect(self, &local_r),
+            ShapeType::Cube => cubes::local_intersect(self, &local_r),
+            ShapeType::Cylinder => cylinders::local_intersect(self, &local_r),
+            ShapeType::Group => groups::local_intersect(self, &local_r),
+            ShapeType::Plane => planes::local_intersect(self, &local_r),
             ShapeType::ShapeTest => test_local_intersect(&local_r),
-            ShapeType::Sphere => spheres::local_intersect(&self, &local_r),
+            ShapeType::SmoothTriangle => triangles::local_intersect(self, &local_r),
+            ShapeType::Sphere => spheres::local_intersect(self, &local_r),
+            ShapeType::Triangle => triangles::local_intersect(self, &local_r),
         }
     }
 
     pub fn normal_at(&self, world_point: &tuples::Point) -> tuples::Vector {
-        let local_point: tuples::Point = self.transform.inverse().tuple_multiply(&world_point);
+        self.normal_at_uv(world_point, 0.0, 0.0)
+    }
+
+    pub fn normal_at_hit(
+        &self,
+        world_point: &tuples::Point,
+        hit: &intersections::Intersection,
+    ) -> tuples::Vector {
+        //smooth triangles need to know where on their face the hit landed
+        self.normal_at_uv(world_point, hit.u, hit.v)
+    }
+
+    fn normal_at_uv(&self, world_point: &tuples::Point, u: f64, v: f64) -> tuples::Vector {
+        let local_point: tuples::Point = self.world_to_object(world_point);
         let local_normal = match self.shape_type {
+            ShapeType::Cone => cones::local_normal_at(self, &local_point),
             ShapeType::Cube => cubes::local_normal_at(&local_point),
+            ShapeType::Cylinder => cylinders::local_normal_at(self, &local_point),
+            ShapeType::Csg => panic!("Csgs have no normals, only their children do"),
+            ShapeType::Group => panic!("Groups have no normals, only their children do"),
             ShapeType::Plane => planes::local_normal_at(),
             ShapeType::ShapeTest => test_local_normal_at(&local_point),
+            ShapeType::SmoothTriangle => triangles::local_normal_at(self, u, v),
             ShapeType::Sphere => spheres::local_normal_at(&local_point),
+            ShapeType::Triangle => triangles::local_normal_at(self, u, v),
         };
-        let mut world_normal: tuples::Vector = self
-            .transform
-            .inverse()
-            .transpose()
-            .tuple_multiply(&local_normal);
-        world_normal.w = 0;
-        world_normal.normalize()
+        self.normal_to_world(&local_normal)
+    }
+
+    pub fn world_to_object(&self, world_point: &tuples::Point) -> tuples::Point {
+        let mut p: tuples::Point = *world_point;
+        for parent_inverse in self.parent_inverses.iter().rev() {
+            p = parent_inverse.tuple_multiply(&p);
+        }
+        self.transform_inverse.tuple_multiply(&p)
+    }
+
+    pub fn normal_to_world(&self, local_normal: &tuples::Vector) -> tuples::Vector {
+        let mut n: tuples::Vector =
+            to_parent_space(&self.transform_inverse_transpose, local_normal);
+        for parent_inverse_transpose in self.parent_inverse_transposes.iter() {
+            n = to_parent_space(parent_inverse_transpose, &n);
+        }
+        n
+    }
+
+    pub fn add_child(&mut self, child: Shape) {
+        //the cached bounds grow with each child, so groups cull rays without a bvh
+        let mut b = self.bounds_of();
+        b.add_box(&child.parent_space_bounds_of());
+        self.children.push(child);
+        self.bounds = Some(b);
+    }
+
+    pub fn children(&self) -> &[Shape] {
+        &self.children
+    }
+
+    //any child may be changed through this, so the cached bounds are dropped
+    //until divide works them out again
+    pub fn children_mut(&mut self) -> &mut Vec<Shape> {
+        self.bounds = None;
+        &mut self.children
+    }
+
+    pub fn cached_bounds(&self) -> Option<bounds::BoundingBox> {
+        self.bounds
+    }
+
+    pub fn bounds_of(&self) -> bounds::BoundingBox {
+        //in object space
+        if let Some(b) = self.bounds {
+            return b;
+        }
+        let unit = bounds::bounding_box(
+            tuples::point(-1.0, -1.0, -1.0),
+            tuples::point(1.0, 1.0, 1.0),
+        );
+        match self.shape_type {
+            ShapeType::Cone => cones::local_bounds(self),
+            ShapeType::Csg => groups::local_bounds(self),
+            ShapeType::Cube => unit,
+            ShapeType::Cylinder => cylinders::local_bounds(self),
+            ShapeType::Group => groups::local_bounds(self),
+            ShapeType::Plane => planes::local_bounds(),
+            ShapeType::ShapeTest => unit,
+            ShapeType::SmoothTriangle => triangles::local_bounds(self),
+            ShapeType::Sphere => unit,
+            ShapeType::Triangle => triangles::local_bounds(self),
+        }
+    }
+
+    pub fn parent_space_bounds_of(&self) -> bounds::BoundingBox {
+        self.bounds_of().transform(&self.transform)
+    }
+
+    pub fn divide(&mut self, threshold: usize) {
+        //builds the bounding volume hierarchy below this shape
+        match self.shape_type {
+            ShapeType::Group => groups::divide(self, threshold),
+            ShapeType::Csg => {
+                for child in self.children.iter_mut() {
+                    child.divide(threshold);
+                }
+            }
+            _ => return,
+        }
+        self.bounds = None;
+        self.bounds = Some(self.bounds_of());
+    }
+
+    pub fn set_material(&mut self, material: materials::Material) {
+        //a group passes its material down to everything it contains
+        for child in self.children.iter_mut() {
+            child.set_material(material.clone());
+        }
+        self.material = material;
     }
 }
 
+fn to_parent_space(
+    inverse_transpose: &matrices::Matrix4,
+    normal: &tuples::Vector,
+) -> tuples::Vector {
+    let mut n: tuples::Vector = inverse_transpose.tuple_multiply(normal);
+    n.w = 0;
+    n.normalize()
+}
+
 fn test_local_intersect(local_r: &rays::Ray) -> Result<Vec<intersections::Intersection>, String> {
     //only used by tests - via intersect - so can't move to tests area
     //all this function needs to do is return the ray for testing
@@ -100,6 +289,20 @@ mod tests {
         assert_eq!(s.transform.is_equal_to(&matrices::IDENTITY_MATRIX), true);
     }
 
+    #[test]
+    fn test_setting_a_transformation_caches_its_inverses() {
+        //Setting a shape's transformation updates its cached inverses
+        let mut s = shape(ShapeType::ShapeTest);
+        let t = transformations::matrix4_translation(2.0, 3.0, 4.0)
+            .multiply(&transformations::matrix4_scaling(1.0, 2.0, 3.0));
+        s.set_transform(t);
+        assert!(s.transform.is_equal_to(&t));
+        assert!(s.transform_inverse.is_equal_to(&t.inverse()));
+        assert!(s
+            .transform_inverse_transpose
+            .is_equal_to(&t.inverse().transpose()));
+    }
+
     #[test]
     fn test_assign_material_to_shape() {
         //A shape may be assigned a material
@@ -133,7 +336,7 @@ mod tests {
         //Intersecting a scaled shape with a ray
         let r = rays::ray(tuples::point(0.0, 0.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
         let mut s = shape(ShapeType::ShapeTest);
-        s.transform = transformations::matrix4_scaling(2.0, 2.0, 2.0);
+        s.set_transform(transformations::matrix4_scaling(2.0, 2.0, 2.0));
         let expected_error = s.intersect(&r);
         match expected_error {
             Ok(_) => {
@@ -149,7 +352,7 @@ mod tests {
         //Intersecting a translated shape with a ray
         let r = rays::ray(tuples::point(0.0, 0.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
         let mut s = shape(ShapeType::ShapeTest);
-        s.transform = transformations::matrix4_translation(5.0, 0.0, 0.0);
+        s.set_transform(transformations::matrix4_translation(5.0, 0.0, 0.0));
         let expected_error = s.intersect(&r);
         match expected_error {
             Ok(_) => {
@@ -164,7 +367,7 @@ mod tests {
     fn test_computing_the_normal_on_a_translated_shape() {
         //Computing the normal on a translated shape
         let mut s = shape(ShapeType::ShapeTest);
-        s.transform = transformations::matrix4_translation(0.0, 1.0, 0.0);
+        s.set_transform(transformations::matrix4_translation(0.0, 1.0, 0.0));
         let n = s.normal_at(&tuples::point(0.0, 1.70711, -0.70711));
         assert_eq!(n.is_equal_to(&tuples::vector(0.0, 0.70711, -0.70711)), true)
     }
@@ -176,9 +379,48 @@ mod tests {
         let scaling = transformations::matrix4_scaling(1.0, 0.5, 1.0);
         let rot_z = transformations::matrix4_rotation_z_rad(PI / 5.0);
         let m = transformations::matrix4_transform_chain(&([rot_z, scaling].to_vec()));
-        s.transform = m;
+        s.set_transform(m);
         let n = s.normal_at(&tuples::point(0.0, 2.0_f64.sqrt(), -2.0_f64.sqrt()));
         println!("v({},{},{},{})", n.x, n.y, n.z, n.w,);
         assert_eq!(n.is_equal_to(&tuples::vector(0.0, 0.97014, -0.24254)), true)
     }
+
+    fn nested_sphere() -> Shape {
+        //a sphere inside g2 (scaled) inside g1 (rotated), as the book builds it
+        let mut s = shape(ShapeType::Sphere);
+        s.set_transform(transformations::matrix4_translation(5.0, 0.0, 0.0));
+        s.add_parent_inverse(&transformations::matrix4_scaling(1.0, 2.0, 3.0).inverse());
+        s.add_parent_inverse(&transformations::matrix4_rotation_y_rad(PI / 2.0).inverse());
+        s
+    }
+
+    #[test]
+    fn test_converting_a_point_from_world_to_object_space() {
+        //Converting a point from world to object space
+        let mut s = shape(ShapeType::Sphere);
+        s.set_transform(transformations::matrix4_translation(5.0, 0.0, 0.0));
+        s.add_parent_inverse(&transformations::matrix4_scaling(2.0, 2.0, 2.0).inverse());
+        s.add_parent_inverse(&transformations::matrix4_rotation_y_rad(PI / 2.0).inverse());
+        let p = s.world_to_object(&tuples::point(-2.0, 0.0, -10.0));
+        assert!(p.is_equal_to(&tuples::point(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn test_converting_a_normal_from_object_to_world_space() {
+        //Converting a normal from object to world space
+        let s = nested_sphere();
+        let x = 3.0_f64.sqrt() / 3.0;
+        let n = s.normal_to_world(&tuples::vector(x, x, x));
+        let expected = tuples::vector(2.0 / 7.0, 3.0 / 7.0, -6.0 / 7.0);
+        assert!(n.is_equal_to(&expected));
+    }
+
+    #[test]
+    fn test_finding_the_normal_on_a_child_object() {
+        //Finding the normal on a child object
+        let s = nested_sphere();
+        let n = s.normal_at(&tuples::point(1.7321, 1.1547, -5.5774));
+        println!("v({},{},{},{})", n.x, n.y, n.z, n.w,);
+        assert!(n.is_equal_to(&tuples::vector(0.2857, 0.42854, -0.85716)));
+    }
 }
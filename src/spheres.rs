@@ -180,7 +180,7 @@ mod tests {
         let r = rays::ray(tuples::point(0.0, 0.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
         let mut s = sphere();
         let t = transformations::matrix4_scaling(2.0, 2.0, 2.0);
-        s.transform = t;
+        s.set_transform(t);
         let x = s.intersect(&r);
         match x {
             Err(e) => println!("XS Error: {}", e),
@@ -198,7 +198,7 @@ mod tests {
         let r = rays::ray(tuples::point(0.0, 0.0, -5.0), tuples::vector(0.0, 0.0, 1.0));
         let mut s = sphere();
         let t = transformations::matrix4_translation(5.0, 0.0, 0.0);
-        s.transform = t;
+        s.set_transform(t);
         let x = s.intersect(&r);
         match x {
             Err(e) => assert_eq!(e == "No intersections", true),
@@ -275,7 +275,7 @@ mod tests {
     fn test_normal_on_translated_sphere() {
         //Computing the normal on a translated sphere
         let mut s = sphere();
-        s.transform = transformations::matrix4_translation(0.0, 1.0, 0.0);
+        s.set_transform(transformations::matrix4_translation(0.0, 1.0, 0.0));
         let n = s.normal_at(&tuples::point(0.0, 1.70711, -0.70711));
         let r = tuples::vector(0.0, 0.70711, -0.70711);
         assert_eq!(r.is_equal_to(&n), true);
@@ -288,7 +288,7 @@ mod tests {
         let m1 = transformations::matrix4_scaling(1.0, 0.5, 1.0);
         let m2 = transformations::matrix4_rotation_z_rad(PI / 5.0);
         let m = m1.multiply(&m2);
-        s.transform = m;
+        s.set_transform(m);
         let n = s.normal_at(&tuples::point(
             0.0,
             2.0_f64.sqrt() / 2.0,
@@ -310,7 +310,7 @@ mod tests {
     fn test_a_helper_for_producing_a_sphere_with_a_glassy_material() {
         //A helper for producing a sphere with a glassy material
         let s = sphere_glass();
-        assert_eq!(s.transform.is_equal_to(&matrices::IDENTITY_MATRIX), true);
+        assert_eq!(s.transform().is_equal_to(&matrices::IDENTITY_MATRIX), true);
         assert_eq!(
             tuples::get_bool_numbers_are_equal(&s.material.transparency, &1.0),
             true
@@ -0,0 +1,316 @@
+use crate::bounds;
+use crate::intersections;
+use crate::rays;
+use crate::shapes;
+use crate::tuples;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Triangle {
+    pub p1: tuples::Point,
+    pub p2: tuples::Point,
+    pub p3: tuples::Point,
+    pub e1: tuples::Vector,
+    pub e2: tuples::Vector,
+    pub normal: tuples::Vector,
+    //vertex normals, only used by smooth triangles
+    pub n1: tuples::Vector,
+    pub n2: tuples::Vector,
+    pub n3: tuples::Vector,
+    //texture coordinates at p1, p2 and p3, from an obj file's vt records
+    pub uvs: Option<[(f64, f64); 3]>,
+}
+
+pub fn triangle(p1: tuples::Point, p2: tuples::Point, p3: tuples::Point) -> shapes::Shape {
+    let e1 = p2.subtract(&p1);
+    let e2 = p3.subtract(&p1);
+    let normal = e2.cross_product(&e1).normalize();
+    let mut s = shapes::shape(shapes::ShapeType::Triangle);
+    s.triangle = Some(Triangle {
+        p1,
+        p2,
+        p3,
+        e1,
+        e2,
+        normal,
+        n1: normal,
+        n2: normal,
+        n3: normal,
+        uvs: None,
+    });
+    s
+}
+
+pub fn smooth_triangle(
+    p1: tuples::Point,
+    p2: tuples::Point,
+    p3: tuples::Point,
+    n1: tuples::Vector,
+    n2: tuples::Vector,
+    n3: tuples::Vector,
+) -> shapes::Shape {
+    let mut s = triangle(p1, p2, p3);
+    s.shape_type = shapes::ShapeType::SmoothTriangle;
+    if let Some(t) = s.triangle.as_mut() {
+        t.n1 = n1;
+        t.n2 = n2;
+        t.n3 = n3;
+    }
+    s
+}
+
+fn triangle_of(s: &shapes::Shape) -> &Triangle {
+    match &s.triangle {
+        Some(t) => t,
+        None => panic!("Triangle shape {} has no vertices", s.id),
+    }
+}
+
+pub fn local_intersect(
+    s: &shapes::Shape,
+    local_r: &rays::Ray,
+) -> Result<Vec<intersections::Intersection>, String> {
+    //Möller–Trumbore
+    let tri = triangle_of(s);
+    let dir_cross_e2 = local_r.direction.cross_product(&tri.e2);
+    let det = tri.e1.dot_product(&dir_cross_e2);
+    if det.abs() < tuples::EPSILON {
+        return Err("No intersections".to_string());
+    }
+    let f = 1.0 / det;
+    let p1_to_origin = local_r.origin.subtract(&tri.p1);
+    let u = f * p1_to_origin.dot_product(&dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return Err("No intersections".to_string());
+    }
+    let origin_cross_e1 = p1_to_origin.cross_product(&tri.e1);
+    let v = f * local_r.direction.dot_product(&origin_cross_e1);
+    if v < 0.0 || (u + v) > 1.0 {
+        return Err("No intersections".to_string());
+    }
+    let t = f * tri.e2.dot_product(&origin_cross_e1);
+    Ok(vec![intersections::intersection_with_uv(
+        t,
+        s.clone(),
+        u,
+        v,
+    )])
+}
+
+pub fn local_bounds(s: &shapes::Shape) -> bounds::BoundingBox {
+    let tri = triangle_of(s);
+    let mut b = bounds::bounding_box_empty();
+    b.add_point(&tri.p1);
+    b.add_point(&tri.p2);
+    b.add_point(&tri.p3);
+    b
+}
+
+pub fn local_normal_at(s: &shapes::Shape, u: f64, v: f64) -> tuples::Vector {
+    let tri = triangle_of(s);
+    match s.shape_type {
+        shapes::ShapeType::SmoothTriangle => tri
+            .n2
+            .multiply(&u)
+            .add(&tri.n3.multiply(&v))
+            .add(&tri.n1.multiply(&(1.0 - u - v))),
+        _ => tri.normal,
+    }
+}
+
+//the texture coordinates at a point on the triangle, blended from its corners
+pub fn texture_uv_at(s: &shapes::Shape, local_point: &tuples::Point) -> Option<(f64, f64)> {
+    let tri = s.triangle.as_ref()?;
+    let uvs = tri.uvs?;
+    //barycentric weights of p2 and p3
+    let p = local_point.subtract(&tri.p1);
+    let (d11, d12, d22) = (
+        tri.e1.dot_product(&tri.e1),
+        tri.e1.dot_product(&tri.e2),
+        tri.e2.dot_product(&tri.e2),
+    );
+    let (dp1, dp2) = (p.dot_product(&tri.e1), p.dot_product(&tri.e2));
+    let denom = d11 * d22 - d12 * d12;
+    let w2 = (d22 * dp1 - d12 * dp2) / denom;
+    let w3 = (d11 * dp2 - d12 * dp1) / denom;
+    let w1 = 1.0 - w2 - w3;
+    Some((
+        uvs[0].0 * w1 + uvs[1].0 * w2 + uvs[2].0 * w3,
+        uvs[0].1 * w1 + uvs[1].1 * w2 + uvs[2].1 * w3,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_triangle() -> shapes::Shape {
+        triangle(
+            tuples::point(0.0, 1.0, 0.0),
+            tuples::point(-1.0, 0.0, 0.0),
+            tuples::point(1.0, 0.0, 0.0),
+        )
+    }
+
+    fn default_smooth_triangle() -> shapes::Shape {
+        smooth_triangle(
+            tuples::point(0.0, 1.0, 0.0),
+            tuples::point(-1.0, 0.0, 0.0),
+            tuples::point(1.0, 0.0, 0.0),
+            tuples::vector(0.0, 1.0, 0.0),
+            tuples::vector(-1.0, 0.0, 0.0),
+            tuples::vector(1.0, 0.0, 0.0),
+        )
+    }
+
+    fn assert_misses(s: &shapes::Shape, r: &rays::Ray) {
+        match local_intersect(s, r) {
+            Err(e) => assert_eq!(e, "No intersections"),
+            Ok(_xs) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_constructing_a_triangle() {
+        //Constructing a triangle
+        let t = default_triangle();
+        let tri = t.triangle.unwrap();
+        assert!(tri.p1.is_equal_to(&tuples::point(0.0, 1.0, 0.0)));
+        assert!(tri.p2.is_equal_to(&tuples::point(-1.0, 0.0, 0.0)));
+        assert!(tri.p3.is_equal_to(&tuples::point(1.0, 0.0, 0.0)));
+        assert!(tri.e1.is_equal_to(&tuples::vector(-1.0, -1.0, 0.0)));
+        assert!(tri.e2.is_equal_to(&tuples::vector(1.0, -1.0, 0.0)));
+        assert!(tri.normal.is_equal_to(&tuples::vector(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn test_finding_the_normal_on_a_triangle() {
+        //Finding the normal on a triangle
+        let t = default_triangle();
+        let normal = t.triangle.unwrap().normal;
+        let n1 = t.normal_at(&tuples::point(0.0, 0.5, 0.0));
+        let n2 = t.normal_at(&tuples::point(-0.5, 0.75, 0.0));
+        let n3 = t.normal_at(&tuples::point(0.5, 0.25, 0.0));
+        assert!(n1.is_equal_to(&normal));
+        assert!(n2.is_equal_to(&normal));
+        assert!(n3.is_equal_to(&normal));
+    }
+
+    #[test]
+    fn test_intersecting_a_ray_parallel_to_the_triangle() {
+        //Intersecting a ray parallel to the triangle
+        let t = default_triangle();
+        let r = rays::ray(
+            tuples::point(0.0, -1.0, -2.0),
+            tuples::vector(0.0, 1.0, 0.0),
+        );
+        assert_misses(&t, &r);
+    }
+
+    #[test]
+    fn test_a_ray_misses_the_triangle_edges() {
+        //A ray misses the p1-p3 edge, the p1-p2 edge and the p2-p3 edge
+        let t = default_triangle();
+        let d = tuples::vector(0.0, 0.0, 1.0);
+        assert_misses(&t, &rays::ray(tuples::point(1.0, 1.0, -2.0), d));
+        assert_misses(&t, &rays::ray(tuples::point(-1.0, 1.0, -2.0), d));
+        assert_misses(&t, &rays::ray(tuples::point(0.0, -1.0, -2.0), d));
+    }
+
+    #[test]
+    fn test_a_ray_strikes_a_triangle() {
+        //A ray strikes a triangle
+        let t = default_triangle();
+        let r = rays::ray(tuples::point(0.0, 0.5, -2.0), tuples::vector(0.0, 0.0, 1.0));
+        match local_intersect(&t, &r) {
+            Ok(xs) => {
+                assert_eq!(xs.len(), 1);
+                assert!(tuples::get_bool_numbers_are_equal(&xs[0].t, &2.0));
+            }
+            Err(_) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_a_triangle_has_a_bounding_box() {
+        //A triangle has a bounding box
+        let t = triangle(
+            tuples::point(-3.0, 7.0, 2.0),
+            tuples::point(6.0, 2.0, -4.0),
+            tuples::point(2.0, -1.0, -1.0),
+        );
+        let b = t.bounds_of();
+        assert!(b.min.is_equal_to(&tuples::point(-3.0, -1.0, -4.0)));
+        assert!(b.max.is_equal_to(&tuples::point(6.0, 7.0, 2.0)));
+    }
+
+    #[test]
+    fn test_an_intersection_with_a_smooth_triangle_stores_u_v() {
+        //An intersection with a smooth triangle stores u/v
+        let tri = default_smooth_triangle();
+        let r = rays::ray(
+            tuples::point(-0.2, 0.3, -2.0),
+            tuples::vector(0.0, 0.0, 1.0),
+        );
+        match local_intersect(&tri, &r) {
+            Ok(xs) => {
+                assert!(tuples::get_bool_numbers_are_equal(&xs[0].u, &0.45));
+                assert!(tuples::get_bool_numbers_are_equal(&xs[0].v, &0.25));
+            }
+            Err(_) => {
+                println!("Not possible in this test");
+                assert_eq!(false, true);
+            }
+        }
+    }
+
+    #[test]
+    fn test_a_smooth_triangle_uses_u_v_to_interpolate_the_normal() {
+        //A smooth triangle uses u/v to interpolate the normal
+        let tri = default_smooth_triangle();
+        let i = intersections::intersection_with_uv(1.0, tri.clone(), 0.45, 0.25);
+        let n = tri.normal_at_hit(&tuples::point(0.0, 0.0, 0.0), &i);
+        assert!(n.is_equal_to(&tuples::vector(-0.5547, 0.83205, 0.0)));
+    }
+
+    #[test]
+    fn test_preparing_the_normal_on_a_smooth_triangle() {
+        //Preparing the normal on a smooth triangle
+        let tri = default_smooth_triangle();
+        let i = intersections::intersection_with_uv(1.0, tri, 0.45, 0.25);
+        let r = rays::ray(
+            tuples::point(-0.2, 0.3, -2.0),
+            tuples::vector(0.0, 0.0, 1.0),
+        );
+        let xs = intersections::intersection_list(vec![i.clone()]);
+        let comps = i.prepare_computations(&r, &Some(xs));
+        assert!(comps
+            .normalv
+            .is_equal_to(&tuples::vector(-0.5547, 0.83205, 0.0)));
+    }
+
+    #[test]
+    fn test_texture_coordinates_are_blended_across_a_triangle() {
+        //Texture coordinates at each corner, and blended in between
+        let mut tri = default_triangle();
+        assert_eq!(texture_uv_at(&tri, &tuples::point(0.0, 0.5, 0.0)), None);
+        tri.triangle.as_mut().unwrap().uvs = Some([(0.5, 1.0), (0.0, 0.0), (1.0, 0.0)]);
+        let tests = [
+            (tuples::point(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (tuples::point(-1.0, 0.0, 0.0), (0.0, 0.0)),
+            (tuples::point(1.0, 0.0, 0.0), (1.0, 0.0)),
+            (tuples::point(0.0, 0.0, 0.0), (0.5, 0.0)),
+            (tuples::point(-0.25, 0.5, 0.0), (0.375, 0.5)),
+        ];
+        for (p, (u, v)) in tests.iter() {
+            let (tu, tv) = texture_uv_at(&tri, p).unwrap();
+            assert!((tu - u).abs() < tuples::EPSILON);
+            assert!((tv - v).abs() < tuples::EPSILON);
+        }
+    }
+}
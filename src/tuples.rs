@@ -124,6 +124,11 @@ pub fn environment(gravity: Vector, wind: Vector) -> Environment {
     }
 }
 
+//free function version of Tuple::multiply, kept for the benchmark comparison
+pub fn tuple_multiply_borrow(t: &Tuple, s: &f64) -> Tuple {
+    t.multiply(s)
+}
+
 impl Tuple {
     pub fn add(&self, b: &Tuple) -> Tuple {
         let t = tuple(self.x + b.x, self.y + b.y, self.z + b.z, self.w + b.w);
@@ -1,3 +1,5 @@
+use crate::backgrounds;
+use crate::groups;
 use crate::intersections;
 use crate::lights;
 use crate::materials;
@@ -10,11 +12,21 @@ use crate::tuples;
 #[derive(Debug, Clone)]
 pub struct World {
     pub objects: Vec<shapes::Shape>,
-    pub light: Vec<lights::LightPoint>,
+    pub light: Vec<lights::Light>,
+    //seen by any ray that misses every object, including reflected and refracted rays
+    pub background: backgrounds::Background,
 }
 
 pub const RECURSIVE_DEPTH: i32 = 4;
 
+pub fn world() -> World {
+    World {
+        objects: vec![],
+        light: vec![],
+        background: backgrounds::BACKGROUND_DEFAULT,
+    }
+}
+
 pub fn world_default() -> World {
     let mut s1 = spheres::sphere();
     let mut m1 = materials::MATERIAL_DEFAULT;
@@ -24,40 +36,60 @@ pub fn world_default() -> World {
     s1.material = m1;
 
     let mut s2 = spheres::sphere();
-    s2.transform = transformations::matrix4_scaling(0.5, 0.5, 0.5);
+    s2.set_transform(transformations::matrix4_scaling(0.5, 0.5, 0.5));
 
-    let lights = vec![lights::LightPoint {
-        position: tuples::point(-10.0, 10.0, -10.0),
-        intensity: tuples::COLOR_WHITE,
-    }];
+    let lights = vec![lights::light_point(
+        tuples::point(-10.0, 10.0, -10.0),
+        tuples::COLOR_WHITE,
+    )];
 
     World {
         objects: vec![s1, s2],
         light: lights,
+        background: backgrounds::BACKGROUND_DEFAULT,
     }
 }
 
 pub fn world_two_lights() -> World {
     let mut w: World = world_default();
     w.light = vec![
-        lights::LightPoint {
-            position: tuples::point(-10.0, 10.0, -10.0),
-            intensity: tuples::color(0.5, 0.9, 1.0),
-        },
-        lights::LightPoint {
-            position: tuples::point(9.0, 4.0, -9.0),
-            intensity: tuples::color(0.8, 0.0, 0.0),
-        },
+        lights::light_point(
+            tuples::point(-10.0, 10.0, -10.0),
+            tuples::color(0.5, 0.9, 1.0),
+        ),
+        lights::light_point(tuples::point(9.0, 4.0, -9.0), tuples::color(0.8, 0.0, 0.0)),
     ];
     w
 }
 
 impl World {
+    pub fn build_bvh(&mut self, threshold: usize) -> groups::BvhStats {
+        //shapes with finite bounds are gathered into one group and subdivided,
+        //unbounded ones like planes stay at the top level and are always tested
+        let mut bounded: Vec<shapes::Shape> = vec![];
+        let mut unbounded: Vec<shapes::Shape> = vec![];
+        for object in self.objects.drain(..) {
+            if object.parent_space_bounds_of().is_finite() {
+                bounded.push(object);
+            } else {
+                unbounded.push(object);
+            }
+        }
+        if bounded.len() >= threshold {
+            bounded = vec![groups::partition_of(bounded)];
+        }
+        for object in bounded.iter_mut().chain(unbounded.iter_mut()) {
+            object.divide(threshold);
+        }
+        self.objects = bounded;
+        self.objects.append(&mut unbounded);
+        groups::bvh_stats(&self.objects)
+    }
+
     pub fn intersect(&self, r: &rays::Ray) -> Vec<intersections::Intersection> {
         let mut xs_list_unsorted: Vec<intersections::Intersection> = vec![];
         for index in 0..self.objects.len() {
-            let this_sphere = self.objects[index].clone();
-            let xs_for_this_sphere = this_sphere.intersect(r);
+            let xs_for_this_sphere = self.objects[index].intersect(r);
             match xs_for_this_sphere {
                 Err(_) => (), //println!("XS Error: {}", e),
                 Ok(mut xs) => {
@@ -70,16 +102,15 @@ impl World {
 
     pub fn shade_hit(&self, c: &intersections::Comps, remaining: &i32) -> tuples::Color {
         let mut col = tuples::COLOR_BLACK;
-        for index in 0..self.light.len() {
-            let this_light = self.clone().light[index];
+        for this_light in self.light.iter() {
             let this_lights_effect = lights::lighting(
                 &c.object.material,
                 &c.object,
-                &this_light,
+                this_light,
                 &c.over_point,
                 &c.eyev,
                 &c.normalv,
-                &self.is_shadowed(&c.over_point), //TODO maybe try ternary for under_point?
+                &self.intensity_at(this_light, &c.over_point), //TODO maybe try ternary for under_point?
             );
             col = col.add(&this_lights_effect);
         }
@@ -100,7 +131,7 @@ impl World {
         let xs = self.intersect(&r);
         let hit_temp = intersections::hit(&xs);
         match hit_temp {
-            Err(_) => tuples::COLOR_BLACK,
+            Err(_) => self.background.color_at(&r.direction),
             Ok(hit) => {
                 let comp = hit.prepare_computations(&r, &Some(xs));
                 self.shade_hit(&comp, &remaining)
@@ -108,24 +139,59 @@ impl World {
         }
     }
 
-    pub fn is_shadowed(&self, p: &tuples::Point) -> bool {
-        //TODO make work for multiple lights??
-        let v = self.light[0].position.subtract(&p);
+    //how much of each channel of the light reaches p, black in full shadow and white fully lit
+    pub fn intensity_at(&self, light: &lights::Light, p: &tuples::Point) -> tuples::Color {
+        match light.light_type {
+            lights::LightType::Point | lights::LightType::Spot => {
+                self.visibility(&light.position, p)
+            }
+            lights::LightType::Directional => {
+                //the light is infinitely far away so anything in the way casts a shadow
+                let r = rays::ray(*p, light.direction.negate().normalize());
+                self.transmitted(&r, f64::INFINITY)
+            }
+            lights::LightType::Area => {
+                let positions = light.sample_positions(p);
+                let mut total = tuples::COLOR_BLACK;
+                for position in positions.iter() {
+                    total = total.add(&self.visibility(position, p));
+                }
+                total.scalar_multiply(&(1.0 / positions.len() as f64))
+            }
+        }
+    }
+
+    //the fraction of each channel of light from light_position that reaches p
+    pub fn visibility(&self, light_position: &tuples::Point, p: &tuples::Point) -> tuples::Color {
+        let v = light_position.subtract(p);
         let distance = v.magnitude();
         let direction = v.normalize();
-        let r = rays::ray(*p, direction);
+        self.transmitted(&rays::ray(*p, direction), distance)
+    }
+
+    //opaque objects block the ray, transparent ones let it through tinted by their color,
+    //each object only counts once however many of its surfaces the ray crosses,
+    //a whole group like an obj mesh being one object
+    fn transmitted(&self, r: &rays::Ray, distance: f64) -> tuples::Color {
         let xs = self.intersect(&r);
-        let hit_temp = intersections::hit(&xs);
-        match hit_temp {
-            Err(_) => false,
-            Ok(h) => {
-                if h.t < distance {
-                    true
-                } else {
-                    false
-                }
+        let mut filter = tuples::COLOR_WHITE;
+        let mut crossed: Vec<&str> = vec![];
+        for x in xs
+            .iter()
+            .filter(|x| x.t >= tuples::EPSILON && x.t < distance)
+        {
+            let m = &x.object.material;
+            let id = x.shadow_id();
+            if !m.casts_shadow || crossed.contains(&id) {
+                continue;
+            }
+            if m.transparency <= 0.0 {
+                return tuples::COLOR_BLACK;
             }
+            filter = filter.multiply(&m.color.scalar_multiply(&m.transparency.min(1.0)));
+            crossed.push(id);
         }
+        filter
     }
 
     pub fn reflected_color(&self, c: &intersections::Comps, remaining: &i32) -> tuples::Color {
@@ -166,18 +232,12 @@ impl World {
 }
 
 #[cfg(test)]
-use crate::matrices;
 mod tests {
     use super::*;
+    use crate::matrices;
     use crate::patterns;
     use crate::planes;
-
-    fn world() -> World {
-        World {
-            objects: vec![],
-            light: vec![],
-        }
-    }
+    use crate::triangles;
 
     #[test]
     fn test_creating_a_world() {
@@ -206,7 +266,7 @@ mod tests {
         //object1
         assert_eq!(
             w.objects[0]
-                .transform
+                .transform()
                 .is_equal_to(&matrices::IDENTITY_MATRIX),
             true
         );
@@ -221,7 +281,7 @@ mod tests {
         //object2
         assert_eq!(
             w.objects[1]
-                .transform
+                .transform()
                 .is_equal_to(&matrices::IDENTITY_MATRIX),
             false
         );
@@ -293,7 +353,7 @@ mod tests {
         let s1 = spheres::sphere();
         w.objects.push(s1);
         let mut s2 = spheres::sphere();
-        s2.transform = transformations::matrix4_translation(0.0, 0.0, 10.0);
+        s2.set_transform(transformations::matrix4_translation(0.0, 0.0, 10.0));
         w.objects.push(s2.clone());
         let r = rays::ray(tuples::point(0.0, 0.0, 5.0), tuples::vector(0.0, 0.0, 1.0));
         let i = intersections::intersection(4.0, s2);
@@ -338,36 +398,286 @@ mod tests {
         assert_eq!(c.is_equal_to(&w.objects[1].material.color), true);
     }
 
+    #[test]
+    fn test_a_ray_that_misses_sees_the_background() {
+        //A ray that misses everything takes the background color for its direction
+        let mut w = world_default();
+        w.background =
+            backgrounds::gradient_background(tuples::COLOR_BLACK, tuples::color(0.2, 0.4, 1.0));
+        let r = rays::ray(tuples::point(0.0, 0.0, -5.0), tuples::vector(0.0, 1.0, 0.0));
+        let c = w.color_at(&r, &RECURSIVE_DEPTH);
+        assert!(c.is_equal_to(&tuples::color(0.2, 0.4, 1.0)));
+    }
+
     #[test]
     fn test_no_shadow_when_nothing_between_point_and_light() {
         //There is no shadow when nothing is collinear with point and light
         let w = world_default();
+        let light_position = tuples::point(-10.0, 10.0, -10.0);
         let p = tuples::point(0.0, 10.0, 0.0);
-        assert_eq!(w.is_shadowed(&p), false);
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::COLOR_WHITE));
     }
 
     #[test]
     fn test_shadow_when_something_between_point_and_light() {
         //The shadow when an object is between the point and the light
         let w = world_default();
+        let light_position = tuples::point(-10.0, 10.0, -10.0);
         let p = tuples::point(10.0, -10.0, 10.0);
-        assert_eq!(w.is_shadowed(&p), true);
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::COLOR_BLACK));
     }
 
     #[test]
     fn test_no_shadow_when_object_behind_light() {
         //There is no shadow when an object is behind the light
         let w = world_default();
+        let light_position = tuples::point(-10.0, 10.0, -10.0);
         let p = tuples::point(-20.0, 20.0, -20.0);
-        assert_eq!(w.is_shadowed(&p), false);
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::COLOR_WHITE));
     }
 
     #[test]
     fn test_no_shadow_when_object_behind_point() {
         //There is no shadow when an object is behind the point
         let w = world_default();
+        let light_position = tuples::point(-10.0, 10.0, -10.0);
         let p = tuples::point(-2.0, 2.0, -2.0);
-        assert_eq!(w.is_shadowed(&p), false);
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::COLOR_WHITE));
+    }
+
+    #[test]
+    fn test_each_light_is_tested_for_its_own_shadows() {
+        //A point can be in the shadow of one light but not another
+        let w = world_two_lights();
+        let behind_from_first = tuples::point(10.0, -10.0, 10.0);
+        let behind_from_second = tuples::point(-9.0, -4.0, 9.0);
+        assert!(w
+            .visibility(&w.light[0].position, &behind_from_first)
+            .is_equal_to(&tuples::COLOR_BLACK));
+        assert!(w
+            .visibility(&w.light[1].position, &behind_from_first)
+            .is_equal_to(&tuples::COLOR_WHITE));
+        assert!(w
+            .visibility(&w.light[0].position, &behind_from_second)
+            .is_equal_to(&tuples::COLOR_WHITE));
+        assert!(w
+            .visibility(&w.light[1].position, &behind_from_second)
+            .is_equal_to(&tuples::COLOR_BLACK));
+        assert!(w
+            .intensity_at(&w.light[1], &behind_from_second)
+            .is_equal_to(&tuples::COLOR_BLACK));
+    }
+
+    #[test]
+    fn test_directional_lights_cast_shadows_from_infinitely_far_away() {
+        //Anything between a point and a directional light casts a shadow, however far away
+        let w = world_default();
+        let sun = lights::directional_light(tuples::vector(0.0, -1.0, 0.0), tuples::COLOR_WHITE);
+        assert!(w
+            .intensity_at(&sun, &tuples::point(0.0, -2.0, 0.0))
+            .is_equal_to(&tuples::COLOR_BLACK));
+        assert!(w
+            .intensity_at(&sun, &tuples::point(0.0, -1000.0, 0.0))
+            .is_equal_to(&tuples::COLOR_BLACK));
+        assert!(w
+            .intensity_at(&sun, &tuples::point(0.0, 2.0, 0.0))
+            .is_equal_to(&tuples::COLOR_WHITE));
+        assert!(w
+            .intensity_at(&sun, &tuples::point(5.0, -2.0, 0.0))
+            .is_equal_to(&tuples::COLOR_WHITE));
+    }
+
+    #[test]
+    fn test_spot_lights_cast_shadows_from_their_position() {
+        //A spot light's shadows are tested against its position like a point light's
+        let w = world_default();
+        let spot = lights::spot_light(
+            tuples::point(0.0, 10.0, 0.0),
+            tuples::vector(0.0, -1.0, 0.0),
+            0.5,
+            0.6,
+            tuples::COLOR_WHITE,
+        );
+        assert!(w
+            .intensity_at(&spot, &tuples::point(0.0, -2.0, 0.0))
+            .is_equal_to(&tuples::COLOR_BLACK));
+        assert!(w
+            .intensity_at(&spot, &tuples::point(0.0, 2.0, 0.0))
+            .is_equal_to(&tuples::COLOR_WHITE));
+    }
+
+    #[test]
+    fn test_transparent_objects_cast_partial_shadows() {
+        //Light passing through a transparent object is dimmed by its transparency and color
+        let mut w = world();
+        let mut glass = spheres::sphere();
+        glass.material.transparency = 0.5;
+        w.objects.push(glass);
+        let light_position = tuples::point(0.0, 0.0, -10.0);
+        let p = tuples::point(0.0, 0.0, 5.0);
+        //both of the sphere's surfaces are crossed but it only counts once
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::color(0.5, 0.5, 0.5)));
+        w.objects[0].material.color = tuples::color(1.0, 0.5, 0.0);
+        w.objects[0].material.transparency = 1.0;
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::color(1.0, 0.5, 0.0)));
+        let mut pane = spheres::sphere();
+        pane.material.transparency = 0.5;
+        pane.set_transform(transformations::matrix4_translation(0.0, 0.0, 3.0));
+        w.objects.push(pane);
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::color(0.5, 0.25, 0.0)));
+        w.objects[1].material.transparency = 0.0;
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::COLOR_BLACK));
+    }
+
+    #[test]
+    fn test_a_transparent_mesh_casts_its_shadow_once() {
+        //A ray entering and leaving a mesh through two of its triangles is only
+        //dimmed once, like a sphere, even after the bvh splits the triangles up
+        let pane = |z: f64| {
+            triangles::triangle(
+                tuples::point(-1.0, -1.0, z),
+                tuples::point(1.0, -1.0, z),
+                tuples::point(0.0, 1.0, z),
+            )
+        };
+        let mut glass = materials::MATERIAL_DEFAULT;
+        glass.transparency = 0.5;
+        let mut mesh = groups::group_of(vec![pane(-1.0), pane(1.0)]);
+        mesh.set_material(glass.clone());
+        let mut w = world();
+        w.objects.push(mesh);
+        let light_position = tuples::point(0.0, 0.0, -10.0);
+        let p = tuples::point(0.0, 0.0, 5.0);
+        let half = tuples::color(0.5, 0.5, 0.5);
+        assert!(w.visibility(&light_position, &p).is_equal_to(&half));
+        w.build_bvh(1);
+        assert!(w.visibility(&light_position, &p).is_equal_to(&half));
+        //a second mesh is a separate object and dims the light again,
+        //though the bvh now holds both of them in one group
+        let mut other = groups::group_of(vec![pane(2.0), pane(3.0)]);
+        other.set_material(glass);
+        w.objects.push(other);
+        w.build_bvh(1);
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::color(0.25, 0.25, 0.25)));
+    }
+
+    #[test]
+    fn test_objects_that_do_not_cast_shadows_are_ignored() {
+        //An object whose material doesn't cast shadows lets all the light through
+        let mut w = world_default();
+        let light_position = tuples::point(-10.0, 10.0, -10.0);
+        let p = tuples::point(10.0, -10.0, 10.0);
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::COLOR_BLACK));
+        w.objects[0].material.casts_shadow = false;
+        w.objects[1].material.casts_shadow = false;
+        assert!(w
+            .visibility(&light_position, &p)
+            .is_equal_to(&tuples::COLOR_WHITE));
+    }
+
+    #[test]
+    fn test_two_lights_cast_two_distinct_shadows() {
+        //A red light on the left and a blue light on the right each cast their own shadow
+        let mut w = world();
+        w.objects.push(spheres::sphere());
+        let mut floor = planes::plane();
+        floor.set_transform(transformations::matrix4_translation(0.0, -1.0, 0.0));
+        w.objects.push(floor);
+        w.light = vec![
+            lights::light_point(tuples::point(-5.0, 5.0, 0.0), tuples::COLOR_RED),
+            lights::light_point(tuples::point(5.0, 5.0, 0.0), tuples::color(0.0, 0.0, 1.0)),
+        ];
+        //the red light's shadow falls to the right of the sphere, the blue one's to the left
+        let in_red_shadow = w.color_at(
+            &rays::ray(
+                tuples::point(1.0, 0.5, -3.0),
+                tuples::vector(0.0, -1.5, 3.0).normalize(),
+            ),
+            &RECURSIVE_DEPTH,
+        );
+        let in_blue_shadow = w.color_at(
+            &rays::ray(
+                tuples::point(-1.0, 0.5, -3.0),
+                tuples::vector(0.0, -1.5, 3.0).normalize(),
+            ),
+            &RECURSIVE_DEPTH,
+        );
+        assert!(tuples::get_bool_numbers_are_equal(&in_red_shadow.red, &0.1));
+        assert!(in_red_shadow.blue > 0.1);
+        assert!(tuples::get_bool_numbers_are_equal(
+            &in_blue_shadow.blue,
+            &0.1
+        ));
+        assert!(in_blue_shadow.red > 0.1);
+    }
+
+    #[test]
+    fn test_point_lights_evaluate_the_light_intensity_at_a_given_point() {
+        //Point lights evaluate the light intensity at a given point
+        let w = world_default();
+        let light = w.light[0];
+        let tests = [
+            (tuples::point(0.0, 1.0001, 0.0), 1.0),
+            (tuples::point(-1.0001, 0.0, 0.0), 1.0),
+            (tuples::point(0.0, 0.0, -1.0001), 1.0),
+            (tuples::point(0.0, 0.0, 1.0001), 0.0),
+            (tuples::point(1.0001, 0.0, 0.0), 0.0),
+            (tuples::point(0.0, -1.0001, 0.0), 0.0),
+            (tuples::point(0.0, 0.0, 0.0), 0.0),
+        ];
+        for (p, expected) in tests.iter() {
+            let e = *expected;
+            assert!(w
+                .intensity_at(&light, p)
+                .is_equal_to(&tuples::color(e, e, e)));
+        }
+    }
+
+    #[test]
+    fn test_the_area_light_intensity_function() {
+        //The area light intensity function
+        let w = world_default();
+        let light = lights::area_light(
+            tuples::point(-0.5, -0.5, -5.0),
+            tuples::vector(1.0, 0.0, 0.0),
+            2,
+            tuples::vector(0.0, 1.0, 0.0),
+            2,
+            tuples::COLOR_WHITE,
+        );
+        let tests = [
+            (tuples::point(0.0, 0.0, 2.0), 0.0),
+            (tuples::point(1.0, -1.0, 2.0), 0.25),
+            (tuples::point(1.5, 0.0, 2.0), 0.5),
+            (tuples::point(1.25, 1.25, 3.0), 0.75),
+            (tuples::point(0.0, 0.0, -2.0), 1.0),
+        ];
+        for (p, expected) in tests.iter() {
+            let e = *expected;
+            assert!(w
+                .intensity_at(&light, p)
+                .is_equal_to(&tuples::color(e, e, e)));
+        }
     }
 
     #[test]
@@ -389,7 +699,7 @@ mod tests {
         let mut w = world_default();
         let mut s = planes::plane();
         s.material.reflective = 0.5;
-        s.transform = transformations::matrix4_translation(0.0, -1.0, 0.0);
+        s.set_transform(transformations::matrix4_translation(0.0, -1.0, 0.0));
         w.objects.push(s.clone());
         let r = rays::ray(
             tuples::point(0.0, 0.0, -3.0),
@@ -404,13 +714,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_a_reflective_surface_reflects_the_background() {
+        //A mirror with nothing in front of it reflects the background
+        let mut w = world();
+        w.light = world_default().light;
+        w.background = backgrounds::flat_background(tuples::color(0.2, 0.4, 1.0));
+        let mut s = planes::plane();
+        s.material.reflective = 1.0;
+        s.material.ambient = 0.0;
+        s.material.diffuse = 0.0;
+        s.material.specular = 0.0;
+        w.objects.push(s);
+        let r = rays::ray(
+            tuples::point(0.0, 1.0, -1.0),
+            tuples::vector(0.0, -1.0, 1.0).normalize(),
+        );
+        let col = w.color_at(&r, &RECURSIVE_DEPTH);
+        assert!(col.is_equal_to(&tuples::color(0.2, 0.4, 1.0)));
+    }
+
     #[test]
     fn test_shade_hit_with_reflective_material() {
         //shade_hit() with a reflective material
         let mut w = world_default();
         let mut s = planes::plane();
         s.material.reflective = 0.5;
-        s.transform = transformations::matrix4_translation(0.0, -1.0, 0.0);
+        s.set_transform(transformations::matrix4_translation(0.0, -1.0, 0.0));
         w.objects.push(s.clone());
         let r = rays::ray(
             tuples::point(0.0, 0.0, -3.0),
@@ -436,12 +766,12 @@ mod tests {
 
         let mut lower = planes::plane();
         lower.material.reflective = 1.0;
-        lower.transform = transformations::matrix4_translation(0.0, -1.0, 0.0);
+        lower.set_transform(transformations::matrix4_translation(0.0, -1.0, 0.0));
         w.objects.push(lower);
 
         let mut upper = planes::plane();
         upper.material.reflective = 1.0;
-        upper.transform = transformations::matrix4_translation(0.0, 1.0, 0.0);
+        upper.set_transform(transformations::matrix4_translation(0.0, 1.0, 0.0));
         w.objects.push(upper);
 
         let r = rays::ray(tuples::point(0.0, 0.0, 0.0), tuples::vector(0.0, 1.0, 0.0));
@@ -455,7 +785,7 @@ mod tests {
         let mut w = world_default();
         let mut s = planes::plane();
         s.material.reflective = 0.5;
-        s.transform = transformations::matrix4_translation(0.0, -1.0, 0.0);
+        s.set_transform(transformations::matrix4_translation(0.0, -1.0, 0.0));
         w.objects.push(s.clone());
 
         let r = rays::ray(
@@ -546,7 +876,7 @@ mod tests {
         let mut w = world_default();
 
         let mut floor = planes::plane();
-        floor.transform = transformations::matrix4_translation(0.0, -1.0, 0.0);
+        floor.set_transform(transformations::matrix4_translation(0.0, -1.0, 0.0));
         floor.material.transparency = 0.5;
         floor.material.refractive_index = 1.5;
         w.objects.push(floor.clone());
@@ -554,7 +884,7 @@ mod tests {
         let mut ball = w.objects[0].clone();
         ball.material.color = tuples::color(1.0, 0.0, 0.0);
         ball.material.ambient = 0.5;
-        ball.transform = transformations::matrix4_translation(0.0, -3.5, -0.5);
+        ball.set_transform(transformations::matrix4_translation(0.0, -3.5, -0.5));
         w.objects.push(ball);
 
         let r = rays::ray(
@@ -565,10 +895,9 @@ mod tests {
         let xs = intersections::intersection_list(vec![i]);
         let comps = xs[0].prepare_computations(&r, &Some(xs.clone()));
         let col = w.shade_hit(&comps, &RECURSIVE_DEPTH);
-        assert_eq!(
-            col.is_equal_to(&tuples::color(0.93642, 0.68642, 0.68642)),
-            true
-        );
+        //the book has 0.93642 red, but the half transparent floor now only
+        //half shadows the red ball so more of it shows through
+        assert!(col.is_equal_to(&tuples::color(1.08346, 0.68642, 0.68642)));
     }
 
     #[test]
@@ -577,7 +906,7 @@ mod tests {
         let mut w = world_default();
 
         let mut floor = planes::plane();
-        floor.transform = transformations::matrix4_translation(0.0, -1.0, 0.0);
+        floor.set_transform(transformations::matrix4_translation(0.0, -1.0, 0.0));
         floor.material.reflective = 0.5;
         floor.material.transparency = 0.5;
         floor.material.refractive_index = 1.5;
@@ -586,7 +915,7 @@ mod tests {
         let mut ball = w.objects[0].clone();
         ball.material.color = tuples::color(1.0, 0.0, 0.0);
         ball.material.ambient = 0.5;
-        ball.transform = transformations::matrix4_translation(0.0, -3.5, -0.5);
+        ball.set_transform(transformations::matrix4_translation(0.0, -3.5, -0.5));
         w.objects.push(ball);
 
         let r = rays::ray(
@@ -598,9 +927,35 @@ mod tests {
         let comps = xs[0].prepare_computations(&r, &Some(xs.clone()));
         let col = w.shade_hit(&comps, &RECURSIVE_DEPTH);
         println!("{} {} {}", col.red, col.green, col.blue);
-        assert_eq!(
-            col.is_equal_to(&tuples::color(0.93391, 0.69643, 0.69243)),
-            true
-        );
+        //the book has 0.93391 red, the ball is brighter as its shadow is only half dark
+        assert!(col.is_equal_to(&tuples::color(1.07476, 0.69643, 0.69243)));
+    }
+
+    #[test]
+    fn test_building_a_bvh_keeps_colors_and_leaves_planes_at_the_top() {
+        //Building a bvh groups bounded shapes, leaves planes alone, and renders the same
+        let mut w = world();
+        w.light = world_default().light;
+        let mut floor = crate::planes::plane();
+        floor.set_transform(transformations::matrix4_translation(0.0, -1.0, 0.0));
+        w.objects.push(floor);
+        for i in 0..10 {
+            let mut s = spheres::sphere();
+            s.set_transform(transformations::matrix4_transform_chain(&vec![
+                transformations::matrix4_scaling(0.3, 0.3, 0.3),
+                transformations::matrix4_translation(i as f64 - 5.0, 0.0, 0.0),
+            ]));
+            w.objects.push(s);
+        }
+        let mut divided = w.clone();
+        let stats = divided.build_bvh(groups::BVH_THRESHOLD);
+        assert_eq!(divided.objects.len(), 2);
+        assert_eq!(stats.shapes, 10);
+        for x in [-5.0, -2.2, 0.0, 3.9].iter() {
+            let r = rays::ray(tuples::point(*x, 0.5, -5.0), tuples::vector(0.0, -0.1, 1.0));
+            let a = w.color_at(&r, &RECURSIVE_DEPTH);
+            let b = divided.color_at(&r, &RECURSIVE_DEPTH);
+            assert!(a.is_equal_to(&b));
+        }
     }
 }